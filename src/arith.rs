@@ -0,0 +1,130 @@
+//! Arithmetic expansion (`$((expr))`).
+use crate::expand::expand_word_into_string;
+use crate::parser::Expr;
+use crate::shell::Shell;
+
+/// Evaluates an arithmetic expression. Like other shells, comparisons
+/// return 1 (true) or 0 (false) and overflows wrap around.
+pub fn eval_arith(shell: &mut Shell, expr: &Expr) -> anyhow::Result<i32> {
+    let binary = |shell: &mut Shell, lhs: &Expr, rhs: &Expr| -> anyhow::Result<(i32, i32)> {
+        Ok((eval_arith(shell, lhs)?, eval_arith(shell, rhs)?))
+    };
+
+    let value = match expr {
+        Expr::Num(num) => *num,
+        Expr::Var(name) => get_var(shell, name)?,
+        Expr::Parameter(word) => {
+            let value = expand_word_into_string(shell, word)?;
+            parse_value(&value)?
+        }
+        Expr::Neg(expr) => eval_arith(shell, expr)?.wrapping_neg(),
+        Expr::Add(lhs, rhs) => {
+            let (lhs, rhs) = binary(shell, lhs, rhs)?;
+            lhs.wrapping_add(rhs)
+        }
+        Expr::Sub(lhs, rhs) => {
+            let (lhs, rhs) = binary(shell, lhs, rhs)?;
+            lhs.wrapping_sub(rhs)
+        }
+        Expr::Mul(lhs, rhs) => {
+            let (lhs, rhs) = binary(shell, lhs, rhs)?;
+            lhs.wrapping_mul(rhs)
+        }
+        Expr::Div(lhs, rhs) => {
+            let (lhs, rhs) = binary(shell, lhs, rhs)?;
+            if rhs == 0 {
+                anyhow::bail!("division by 0");
+            }
+
+            lhs.wrapping_div(rhs)
+        }
+        Expr::Inc(name) => {
+            let value = get_var(shell, name)?;
+            set_var(shell, name, value.wrapping_add(1));
+            value
+        }
+        Expr::Dec(name) => {
+            let value = get_var(shell, name)?;
+            set_var(shell, name, value.wrapping_sub(1));
+            value
+        }
+        Expr::Assign { name, rhs } => {
+            let value = eval_arith(shell, rhs)?;
+            set_var(shell, name, value);
+            value
+        }
+        Expr::Eq(lhs, rhs) => {
+            let (lhs, rhs) = binary(shell, lhs, rhs)?;
+            i32::from(lhs == rhs)
+        }
+        Expr::Ne(lhs, rhs) => {
+            let (lhs, rhs) = binary(shell, lhs, rhs)?;
+            i32::from(lhs != rhs)
+        }
+        Expr::Lt(lhs, rhs) => {
+            let (lhs, rhs) = binary(shell, lhs, rhs)?;
+            i32::from(lhs < rhs)
+        }
+        Expr::Le(lhs, rhs) => {
+            let (lhs, rhs) = binary(shell, lhs, rhs)?;
+            i32::from(lhs <= rhs)
+        }
+        Expr::Gt(lhs, rhs) => {
+            let (lhs, rhs) = binary(shell, lhs, rhs)?;
+            i32::from(lhs > rhs)
+        }
+        Expr::Ge(lhs, rhs) => {
+            let (lhs, rhs) = binary(shell, lhs, rhs)?;
+            i32::from(lhs >= rhs)
+        }
+    };
+
+    Ok(value)
+}
+
+/// Returns the variable as an integer. An unset or empty variable is 0.
+fn get_var(shell: &Shell, name: &str) -> anyhow::Result<i32> {
+    parse_value(&shell.get_str(name).unwrap_or_default())
+}
+
+fn parse_value(value: &str) -> anyhow::Result<i32> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(0);
+    }
+
+    match value.parse() {
+        Ok(value) => Ok(value),
+        Err(_) => anyhow::bail!("{}: invalid number", value),
+    }
+}
+
+fn set_var(shell: &mut Shell, name: &str, value: i32) {
+    shell.set_string(name, value.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::Shell;
+    use crate::ExitStatus;
+
+    #[test]
+    fn arithmetic_expansion() {
+        let mut shell = Shell::new_for_test();
+        let (_, stdout, _) = shell.run_capture(concat!(
+            "echo $((1 + 2 * 3)) $(( (1 + 2) * 3 )) $((10 - 2 - 3)) $((7 / 2)) $((-3 + 1));",
+            "echo $((1 < 2)) $((2 <= 1)) $((3 == 3)) $((3 != 3))"
+        ));
+        assert_eq!(stdout, "7 9 5 3 -2\n1 0 1 0\n");
+
+        let (_, stdout, _) = shell.run_capture(concat!(
+            "i=5; echo $((i++)) $i $((i--)) $i;",
+            "echo $((x = i * 2)) $x $(($x + ${#x})) \"$((unset + 1))\""
+        ));
+        assert_eq!(stdout, "5 6 6 5\n10 10 12 1\n");
+
+        let (status, _, stderr) = shell.run_capture("echo $((1 / 0))");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: division by 0\n");
+    }
+}
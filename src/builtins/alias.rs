@@ -1,10 +1,11 @@
 use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::expand::quote;
 use crate::parser;
 use crate::process::ExitStatus;
 
 use pest::Parser;
 use pest_derive::Parser;
-use std::io::Write;
+use std::path::PathBuf;
 use tracing::debug;
 
 #[derive(Parser)]
@@ -27,6 +28,10 @@ pub struct Alias;
 impl BuiltinCommand for Alias {
     fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
         debug!("alias: argv={:?}", ctx.argv);
+        if ctx.argv.get(1).map(|arg| arg.as_str()) == Some("-s") {
+            return save_aliases(ctx);
+        }
+
         if let Some(alias) = ctx.argv.get(1) {
             match parse_alias(alias) {
                 Ok((name, body)) => {
@@ -35,15 +40,76 @@ impl BuiltinCommand for Alias {
                     return ExitStatus::ExitedWith(0);
                 }
                 Err(parser::ParseError::Fatal(err)) => {
-                    writeln!(ctx.stderr, "nsh: alias: {}", err).ok();
+                    ctx.error(format_args!("alias: {}", err));
                     return ExitStatus::ExitedWith(1);
                 }
                 Err(parser::ParseError::Empty) => {
-                    writeln!(ctx.stderr, "nsh: alias: alias can't be empty string").ok();
+                    ctx.error("alias: alias can't be empty string");
                     return ExitStatus::ExitedWith(1);
                 }
             }
         }
+
+        // No arguments: list all aliases.
+        let mut aliases: Vec<(&String, &String)> = ctx.shell.aliases().collect();
+        aliases.sort();
+        for (name, body) in aliases {
+            writeln!(ctx.stdout, "{}", alias_definition(name, body)).ok();
+        }
+
         ExitStatus::ExitedWith(0)
     }
 }
+
+/// The file to which `alias -s` saves aliases. It's loaded on startup.
+pub fn aliases_file() -> Option<PathBuf> {
+    dirs::home_dir().map(|home_dir| home_dir.join(".smash_aliases"))
+}
+
+/// Writes all aliases to `aliases_file()` in a form which can be `source`d.
+fn save_aliases(ctx: &mut BuiltinCommandContext) -> ExitStatus {
+    let path = match aliases_file() {
+        Some(path) => path,
+        None => {
+            ctx.error("alias: failed to get the home directory");
+            return ExitStatus::ExitedWith(1);
+        }
+    };
+
+    let mut aliases: Vec<(&String, &String)> = ctx.shell.aliases().collect();
+    aliases.sort();
+
+    let mut content = String::new();
+    for (name, body) in aliases {
+        content.push_str(&alias_definition(name, body));
+        content.push('\n');
+    }
+
+    if let Err(err) = std::fs::write(&path, content) {
+        ctx.error(format_args!("alias: {}: {}", path.display(), err));
+        return ExitStatus::ExitedWith(1);
+    }
+
+    ExitStatus::ExitedWith(0)
+}
+
+/// Returns `alias name='body'`.
+fn alias_definition(name: &str, body: &str) -> String {
+    format!("alias {}={}", name, quote(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::Shell;
+
+    #[test]
+    fn alias_definition_is_sourceable() {
+        let definition = alias_definition("greet", "echo it's me");
+        assert_eq!(definition, "alias greet='echo it'\\''s me'");
+
+        let mut shell = Shell::new_for_test();
+        shell.run_script(&definition);
+        assert_eq!(shell.lookup_alias("greet"), Some("echo it's me".to_owned()));
+    }
+}
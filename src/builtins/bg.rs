@@ -0,0 +1,71 @@
+use super::fg::find_job;
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::process::{continue_job, wait_for_any_process, ExitStatus};
+
+use tracing::debug;
+
+/// `bg [%id]`
+pub struct Bg;
+
+impl BuiltinCommand for Bg {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("bg: argv={:?}", ctx.argv);
+        let job = match find_job(ctx.shell, ctx.argv.get(1).map(String::as_str)) {
+            Some(job) => job,
+            None => {
+                ctx.error("bg: no such job");
+                return ExitStatus::ExitedWith(1);
+            }
+        };
+
+        // Update the process states without blocking.
+        while let Ok(Some(_)) = wait_for_any_process(ctx.shell, true) {}
+
+        if !job.stopped(ctx.shell) {
+            ctx.error(format_args!("bg: job {} is not stopped", job.id()));
+            return ExitStatus::ExitedWith(1);
+        }
+
+        if let Err(err) = continue_job(ctx.shell, &job) {
+            ctx.error(format_args!("bg: {}", err.desc()));
+            return ExitStatus::ExitedWith(1);
+        }
+
+        writeln!(ctx.stdout, "[{}] {} &", job.id(), job.cmd).ok();
+        ExitStatus::ExitedWith(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::Shell;
+    use crate::ExitStatus;
+    use nix::sys::signal::{kill, Signal};
+
+    #[test]
+    fn bg() {
+        let mut shell = Shell::new_for_test();
+
+        shell.run_script("sleep 5 &");
+        let pid = shell.last_background_pid().unwrap();
+        let (status, _, stderr) = shell.run_capture("bg");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: bg: job 1 is not stopped\n");
+
+        kill(pid, Signal::SIGSTOP).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let (status, stdout, _) = shell.run_capture("bg %1");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "[1] sleep 5 &\n");
+
+        // The job keeps running in the background.
+        let (_, stdout, _) = shell.run_capture("jobs");
+        assert_eq!(stdout, "[1]  Running  sleep 5\n");
+
+        let (status, _, stderr) = shell.run_capture("bg %2");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: bg: no such job\n");
+
+        kill(pid, Signal::SIGKILL).unwrap();
+    }
+}
@@ -1,7 +1,7 @@
 use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::variable::Value;
 use crate::ExitStatus;
 
-use std::io::Write;
 use std::path::Path;
 use tracing::debug;
 
@@ -11,44 +11,112 @@ impl BuiltinCommand for Cd {
     fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
         debug!("cd: argv={:?}", ctx.argv);
         let current_dir = std::env::current_dir().expect("failed to getcwd()");
-        let (dir, pushd) = match ctx.argv.get(1).map(|s| s.as_str()) {
+        let dir = match ctx.argv.get(1).map(|s| s.as_str()) {
             Some("-") => {
-                if let Some(d) = ctx.shell.popd() {
-                    (d, false)
+                if let Some(d) = ctx.shell.get_str("OLDPWD") {
+                    d
                 } else {
+                    ctx.error("cd: OLDPWD not set");
                     return ExitStatus::ExitedWith(1);
                 }
             }
-            Some(dir) if dir.starts_with('/') => (dir.to_string(), true),
+            Some(dir) if dir.starts_with('/') => dir.to_string(),
             // relative path
-            Some(dir) => (
-                Path::new(&current_dir)
-                    .join(dir)
-                    .to_string_lossy()
-                    .into_owned(),
-                true,
-            ),
+            Some(dir) => Path::new(&current_dir)
+                .join(dir)
+                .to_string_lossy()
+                .into_owned(),
             // with no arguments
-            None => (
+            None => {
                 if let Some(home_dir) = dirs::home_dir() {
                     home_dir.to_string_lossy().into_owned()
                 } else {
                     String::from("/")
-                },
-                true,
-            ),
+                }
+            }
         };
 
-        if pushd {
-            ctx.shell.pushd(current_dir.to_str().unwrap().to_owned());
+        if !change_dir(ctx, &dir) {
+            return ExitStatus::ExitedWith(1);
         }
 
-        match std::env::set_current_dir(&dir) {
-            Ok(_) => ExitStatus::ExitedWith(0),
-            Err(err) => {
-                writeln!(ctx.stderr, "smash: cd: {}: `{}'", err, dir).ok();
-                ExitStatus::ExitedWith(1)
-            }
+        // Like other shells, `cd -` prints the new directory.
+        if ctx.argv.get(1).map(String::as_str) == Some("-") {
+            let new_dir = std::env::current_dir().expect("failed to getcwd()");
+            writeln!(ctx.stdout, "{}", new_dir.display()).ok();
+        }
+
+        if ctx.shell.option("auto_pushd") {
+            ctx.shell.pushd(current_dir.to_string_lossy().into_owned());
         }
+
+        ExitStatus::ExitedWith(0)
+    }
+}
+
+/// Changes the current directory, updates `$OLDPWD` and `$PWD`, and calls the
+/// `chpwd` hook. Prints an error message and returns `false` on failure.
+pub fn change_dir(ctx: &mut BuiltinCommandContext, dir: &str) -> bool {
+    let current_dir = std::env::current_dir().expect("failed to getcwd()");
+    if let Err(err) = std::env::set_current_dir(dir) {
+        ctx.error(format_args!("{}: {}: `{}'", ctx.argv[0], err, dir));
+        return false;
+    }
+
+    let new_dir = std::env::current_dir().expect("failed to getcwd()");
+    ctx.shell.set(
+        "OLDPWD",
+        Value::String(current_dir.to_string_lossy().into_owned()),
+        false,
+    );
+    ctx.shell.set(
+        "PWD",
+        Value::String(new_dir.to_string_lossy().into_owned()),
+        false,
+    );
+    ctx.shell
+        .record_cd_history(new_dir.to_string_lossy().into_owned());
+    ctx.shell
+        .dir_database_mut()
+        .record(&new_dir.to_string_lossy());
+
+    ctx.shell.run_hook("chpwd", &[]);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::Shell;
+    use crate::ExitStatus;
+
+    #[test]
+    fn chpwd_hook() {
+        // `cd .` doesn't change the current directory of the test process,
+        // which other tests depend on.
+        let cwd = std::env::current_dir().unwrap();
+
+        let mut shell = Shell::new_for_test();
+        // `cd` in `chpwd` must not call `chpwd` again.
+        shell.run_script("chpwd() { export HOOKED=\"$HOOKED$PWD\"; cd .; }");
+        shell.run_script("cd .");
+        assert_eq!(shell.get_str("HOOKED"), Some(cwd.display().to_string()));
+        assert_eq!(std::env::current_dir().unwrap(), cwd);
+    }
+
+    #[test]
+    fn cd_minus() {
+        let mut shell = Shell::new_for_test();
+        let (status, stdout, stderr) = shell.run_capture("cd -");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stdout, "");
+        assert_eq!(stderr, "smash: cd: OLDPWD not set\n");
+
+        // `cd -` prints the new directory. `$OLDPWD` is the current directory
+        // so that the test process's cwd doesn't change.
+        let cwd = std::env::current_dir().unwrap();
+        shell.set_string("OLDPWD", cwd.display().to_string());
+        let (status, stdout, _) = shell.run_capture("cd -");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, format!("{}\n", cwd.display()));
     }
 }
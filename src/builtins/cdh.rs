@@ -0,0 +1,39 @@
+use super::cd::change_dir;
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::path::abbreviate_home;
+use crate::ExitStatus;
+
+use tracing::debug;
+
+/// `cdh` lists recently visited directories and `cdh N` goes to the N-th one.
+pub struct Cdh;
+
+impl BuiltinCommand for Cdh {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("cdh: argv={:?}", ctx.argv);
+        let arg = match ctx.argv.get(1) {
+            Some(arg) => arg,
+            None => {
+                for (i, dir) in ctx.shell.cd_history().iter().enumerate() {
+                    writeln!(ctx.stdout, "{:>2}  {}", i + 1, abbreviate_home(dir)).ok();
+                }
+
+                return ExitStatus::ExitedWith(0);
+            }
+        };
+
+        let dir = arg
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|index| ctx.shell.cd_history().get(index).cloned());
+        match dir {
+            Some(dir) if change_dir(ctx, &dir) => ExitStatus::ExitedWith(0),
+            Some(_) => ExitStatus::ExitedWith(1),
+            None => {
+                ctx.error(format_args!("cdh: {}: no such entry", arg));
+                ExitStatus::ExitedWith(1)
+            }
+        }
+    }
+}
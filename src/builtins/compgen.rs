@@ -0,0 +1,107 @@
+use super::{command_names, BuiltinCommand, BuiltinCommandContext, BUILTIN_COMMANDS};
+use crate::event::path_completion;
+use crate::path::FsDirReader;
+use crate::ExitStatus;
+
+use tracing::debug;
+
+/// `compgen [-abcdfv]... [PREFIX]`: prints completion candidates which start
+/// with `PREFIX`.
+pub struct Compgen;
+
+impl BuiltinCommand for Compgen {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("compgen: argv={:?}", ctx.argv);
+        let mut args = ctx.argv.iter().skip(1).peekable();
+        let mut flags = Vec::new();
+        while let Some(arg) = args.next_if(|arg| arg.starts_with('-') && arg.len() > 1) {
+            if arg == "--" {
+                break;
+            }
+
+            for flag in arg[1..].chars() {
+                if !"abcdfv".contains(flag) {
+                    ctx.error(format_args!("compgen: -{}: invalid option", flag));
+                    return ExitStatus::ExitedWith(2);
+                }
+
+                flags.push(flag);
+            }
+        }
+
+        let prefix = args.next().map(String::as_str).unwrap_or("");
+        let mut candidates = Vec::new();
+        for flag in flags {
+            match flag {
+                'a' => candidates.extend(ctx.shell.aliases().map(|(name, _)| name.clone())),
+                'b' => candidates.extend(BUILTIN_COMMANDS.iter().map(|name| name.to_string())),
+                'c' => candidates.extend(command_names(ctx.shell)),
+                'd' => candidates.extend(path_completion(&FsDirReader, prefix, true).entries),
+                'f' => candidates.extend(path_completion(&FsDirReader, prefix, false).entries),
+                'v' => candidates.extend(ctx.shell.variable_names()),
+                _ => unreachable!(),
+            }
+        }
+
+        candidates.retain(|candidate| candidate.starts_with(prefix));
+        candidates.sort();
+        candidates.dedup();
+        for candidate in &candidates {
+            writeln!(ctx.stdout, "{}", candidate).ok();
+        }
+
+        if candidates.is_empty() {
+            ExitStatus::ExitedWith(1)
+        } else {
+            ExitStatus::ExitedWith(0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::{builtin_command, BUILTIN_COMMANDS};
+    use crate::shell::{temp_path, Shell};
+    use crate::ExitStatus;
+
+    #[test]
+    fn compgen() {
+        let mut shell = Shell::new_for_test();
+        shell.run_script("alias ll='ls -l'; alias la='ls -a'; LANG_X=1; LANG_Y=2");
+
+        let (status, stdout, _) = shell.run_capture("compgen -a l");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "la\nll\n");
+
+        let (_, stdout, _) = shell.run_capture("compgen -v LANG_");
+        assert_eq!(stdout, "LANG_X\nLANG_Y\n");
+
+        let (_, stdout, _) = shell.run_capture("compgen -ab c");
+        assert_eq!(stdout, "cd\ncdh\ncompgen\n");
+
+        let (status, stdout, _) = shell.run_capture("compgen -c no-such-command");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stdout, "");
+
+        let dir = temp_path("compgen");
+        std::fs::create_dir_all(dir.join("subdir")).unwrap();
+        std::fs::write(dir.join("file"), "").unwrap();
+        let prefix = dir.to_str().unwrap();
+        let (_, stdout, _) = shell.run_capture(&format!("compgen -f {}/", prefix));
+        assert_eq!(stdout, format!("{0}/file\n{0}/subdir\n", prefix));
+        let (_, stdout, _) = shell.run_capture(&format!("compgen -d {}/", prefix));
+        assert_eq!(stdout, format!("{}/subdir\n", prefix));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let (status, _, stderr) = shell.run_capture("compgen -x");
+        assert_eq!(status, ExitStatus::ExitedWith(2));
+        assert_eq!(stderr, "smash: compgen: -x: invalid option\n");
+    }
+
+    #[test]
+    fn builtin_commands_are_listed() {
+        for name in BUILTIN_COMMANDS {
+            assert!(builtin_command(name).is_some(), "{}", name);
+        }
+    }
+}
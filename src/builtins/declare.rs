@@ -0,0 +1,163 @@
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::expand::quote;
+use crate::variable::{is_valid_name, Value};
+use crate::ExitStatus;
+
+use tracing::debug;
+
+pub struct Declare;
+
+impl BuiltinCommand for Declare {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("declare: argv={:?}", ctx.argv);
+        let mut print = false;
+        let mut export = false;
+        let mut args = ctx.argv.iter().skip(1).peekable();
+        while let Some(arg) = args.peek() {
+            match arg.as_str() {
+                "-p" => print = true,
+                "-x" => export = true,
+                "--" => {
+                    args.next();
+                    break;
+                }
+                _ if arg.starts_with('-') => {
+                    ctx.error(format_args!("declare: {}: invalid option", arg));
+                    return ExitStatus::ExitedWith(1);
+                }
+                _ => break,
+            }
+
+            args.next();
+        }
+
+        let names: Vec<String> = args.cloned().collect();
+        if print {
+            return print_declarations(ctx, names);
+        }
+
+        // `declare [-x] name[=value]...`
+        let mut status = 0;
+        for arg in names {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (arg.as_str(), None),
+            };
+
+            if !is_valid_name(name) {
+                ctx.error(format_args!("declare: `{}': not a valid identifier", arg));
+                status = 1;
+                continue;
+            }
+
+            if let Some(value) = value {
+                ctx.shell.set(name, Value::String(value.to_owned()), true);
+            } else if ctx.shell.get(name).is_none() {
+                ctx.shell.set(name, Value::String(String::new()), true);
+            }
+
+            if export {
+                ctx.shell.export(name);
+            }
+        }
+
+        ExitStatus::ExitedWith(status)
+    }
+}
+
+/// Prints variables in a form which can be reused as input. All variables are
+/// printed if `names` is empty.
+fn print_declarations(ctx: &mut BuiltinCommandContext, names: Vec<String>) -> ExitStatus {
+    let names = if names.is_empty() {
+        ctx.shell.variable_names()
+    } else {
+        names
+    };
+
+    let mut status = 0;
+    for name in names {
+        match declaration(ctx, &name) {
+            Some(declaration) => {
+                writeln!(ctx.stdout, "{}", declaration).ok();
+            }
+            None => {
+                ctx.error(format_args!("declare: {}: not found", name));
+                status = 1;
+            }
+        }
+    }
+
+    ExitStatus::ExitedWith(status)
+}
+
+/// Returns `declare -- name='value'`, `declare -a name=([0]='a' [1]='b')`, etc.
+fn declaration(ctx: &BuiltinCommandContext, name: &str) -> Option<String> {
+    let var = ctx.shell.get(name)?;
+    let (mut flags, value) = match var.value() {
+        Some(Value::String(value)) => (String::new(), quote(value)),
+        Some(Value::Array(elems)) => {
+            let elems: Vec<String> = elems
+                .iter()
+                .enumerate()
+                .map(|(i, elem)| format!("[{}]={}", i, quote(elem)))
+                .collect();
+            ("a".to_owned(), format!("({})", elems.join(" ")))
+        }
+        None => return None,
+    };
+
+    if ctx.shell.is_exported(name) {
+        flags.push('x');
+    }
+
+    let flags = if flags.is_empty() {
+        "--".to_owned()
+    } else {
+        format!("-{}", flags)
+    };
+
+    Some(format!("declare {} {}={}", flags, name, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::Shell;
+    use crate::variable::Value;
+    use crate::ExitStatus;
+
+    #[test]
+    fn declare_p() {
+        let mut shell = Shell::new_for_test();
+        shell.run_script("declare x=\"it's\"; declare -x EXPORTED=1");
+        shell.set(
+            "arr",
+            Value::Array(vec!["a".to_owned(), "b c".to_owned()]),
+            false,
+        );
+
+        let (status, stdout, _) = shell.run_capture("declare -p x EXPORTED arr");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(
+            stdout,
+            concat!(
+                "declare -- x='it'\\''s'\n",
+                "declare -x EXPORTED='1'\n",
+                "declare -a arr=([0]='a' [1]='b c')\n",
+            )
+        );
+
+        // The output can be sourced.
+        let mut other = Shell::new_for_test();
+        other.run_script(stdout.lines().next().unwrap());
+        assert_eq!(other.get_str("x"), Some("it's".to_owned()));
+
+        let (status, _, stderr) = shell.run_capture("declare -p undefined");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: declare: undefined: not found\n");
+
+        let (status, _, stderr) = shell.run_capture("declare 1x=2 y=3");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: declare: `1x=2': not a valid identifier\n");
+        assert_eq!(shell.get_str("y"), Some("3".to_owned()));
+    }
+}
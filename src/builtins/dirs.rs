@@ -0,0 +1,72 @@
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::path::abbreviate_home;
+use crate::ExitStatus;
+
+pub struct Dirs;
+
+impl BuiltinCommand for Dirs {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        let mut verbose = false;
+        for arg in ctx.argv.iter().skip(1) {
+            match arg.as_str() {
+                // Clear the directory stack.
+                "-c" => {
+                    ctx.shell.clear_dir_stack();
+                    return ExitStatus::ExitedWith(0);
+                }
+                "-v" => verbose = true,
+                _ => {
+                    ctx.error(format_args!("dirs: {}: invalid option", arg));
+                    return ExitStatus::ExitedWith(1);
+                }
+            }
+        }
+
+        if verbose {
+            for (i, dir) in dir_stack(ctx).iter().enumerate() {
+                writeln!(ctx.stdout, "{:>2}  {}", i, dir).ok();
+            }
+        } else {
+            print_dirs(ctx);
+        }
+
+        ExitStatus::ExitedWith(0)
+    }
+}
+
+/// Prints the directory stack from the current directory to the oldest one.
+pub fn print_dirs(ctx: &mut BuiltinCommandContext) {
+    let dirs = dir_stack(ctx);
+    writeln!(ctx.stdout, "{}", dirs.join(" ")).ok();
+}
+
+/// Returns the current directory and the directory stack (the most recent one
+/// first) with the home directory replaced by `~`.
+fn dir_stack(ctx: &BuiltinCommandContext) -> Vec<String> {
+    let current_dir = std::env::current_dir().expect("failed to getcwd()");
+    let mut dirs = vec![current_dir.to_string_lossy().into_owned()];
+    dirs.extend(ctx.shell.dir_stack().iter().rev().cloned());
+    dirs.iter().map(|dir| abbreviate_home(dir)).collect()
+}
+
+/// Returns `true` if `arg` is `+N` or `-N`.
+pub fn is_stack_index(arg: &str) -> bool {
+    arg.len() > 1
+        && (arg.starts_with('+') || arg.starts_with('-'))
+        && arg[1..].chars().all(|c| c.is_ascii_digit())
+}
+
+/// Converts `+N` (counting from the left of `dirs`) or `-N` (counting from the
+/// right) into an index in the `dirs` order (0 is the current directory).
+pub fn stack_index(arg: &str, num_dirs: usize) -> Option<usize> {
+    let n: usize = arg[1..].parse().ok()?;
+    if n >= num_dirs {
+        return None;
+    }
+
+    if arg.starts_with('+') {
+        Some(n)
+    } else {
+        Some(num_dirs - 1 - n)
+    }
+}
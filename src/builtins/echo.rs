@@ -0,0 +1,78 @@
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::expand::expand_escapes;
+use crate::ExitStatus;
+
+use tracing::debug;
+
+/// `echo [-neE] [arg...]`
+pub struct Echo;
+
+impl BuiltinCommand for Echo {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("echo: argv={:?}", ctx.argv);
+        let mut newline = true;
+        let mut escapes = false;
+        let mut args = ctx.argv[1..].iter().peekable();
+
+        // Options can be combined (e.g. `-ne`). An argument with other
+        // characters like `-x` is printed as is.
+        while let Some(arg) = args.peek() {
+            let flags = match arg.strip_prefix('-') {
+                Some(flags) if !flags.is_empty() && flags.chars().all(|ch| "neE".contains(ch)) => {
+                    flags
+                }
+                _ => break,
+            };
+
+            for flag in flags.chars() {
+                match flag {
+                    'n' => newline = false,
+                    'e' => escapes = true,
+                    _ => escapes = false,
+                }
+            }
+
+            args.next();
+        }
+
+        let mut output = args.map(String::as_str).collect::<Vec<_>>().join(" ");
+        if escapes {
+            output = expand_escapes(&output);
+        }
+
+        if newline {
+            output.push('\n');
+        }
+
+        write!(ctx.stdout, "{}", output).ok();
+        ExitStatus::ExitedWith(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::process::ExitStatus;
+    use crate::shell::Shell;
+
+    #[test]
+    fn echo() {
+        let mut shell = Shell::new_for_test();
+        let cases = [
+            ("echo", "\n"),
+            ("echo hello   world", "hello world\n"),
+            ("echo -n hello", "hello"),
+            ("echo 'a\\tb'", "a\\tb\n"),
+            ("echo -e 'a\\tb\\\\c'", "a\tb\\c\n"),
+            ("echo -ne 'a\\tb'", "a\tb"),
+            ("echo -n -e 'a\\nb'", "a\nb"),
+            ("echo -eE 'a\\tb'", "a\\tb\n"),
+            ("echo -x -n", "-x -n\n"),
+        ];
+
+        for (script, expected) in cases {
+            let (status, stdout, _) = shell.run_capture(script);
+            assert_eq!(status, ExitStatus::ExitedWith(0));
+            assert_eq!(stdout, expected, "{}", script);
+        }
+    }
+}
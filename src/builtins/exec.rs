@@ -0,0 +1,86 @@
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::parser::RedirectionType;
+use crate::process::open_redirection;
+use crate::ExitStatus;
+
+use nix::unistd::dup;
+use tracing::debug;
+
+pub struct Exec;
+
+impl BuiltinCommand for Exec {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("exec: argv={:?}", ctx.argv);
+        if ctx.argv.len() > 1 {
+            ctx.error("exec: running a command is not supported");
+            return ExitStatus::ExitedWith(1);
+        }
+
+        // `exec > file`: redirect the shell's stdio for subsequent commands.
+        for redirect in ctx.redirects {
+            if redirect.fd > 2 {
+                ctx.error(format_args!("exec: {}: unsupported fd", redirect.fd));
+                return ExitStatus::ExitedWith(1);
+            }
+
+            // `exec 2>&1`
+            if let RedirectionType::Fd(target_fd) = redirect.target {
+                if target_fd > 2 {
+                    ctx.error(format_args!("exec: {}: unsupported fd", target_fd));
+                    return ExitStatus::ExitedWith(1);
+                }
+
+                match dup(ctx.shell.stdio(target_fd)) {
+                    Ok(fd) => ctx.shell.set_stdio(redirect.fd, fd),
+                    Err(err) => {
+                        ctx.error(format_args!("exec: {}: {}", target_fd, err.desc()));
+                        return ExitStatus::ExitedWith(1);
+                    }
+                }
+
+                continue;
+            }
+
+            match open_redirection(ctx.shell, redirect) {
+                Ok(fd) => ctx.shell.set_stdio(redirect.fd, fd),
+                Err(err) => {
+                    ctx.error(err);
+                    return ExitStatus::ExitedWith(1);
+                }
+            }
+        }
+
+        ExitStatus::ExitedWith(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::{temp_path, Shell};
+    use crate::ExitStatus;
+
+    #[test]
+    fn exec_redirects_subsequent_commands() {
+        let log = temp_path("exec.log");
+
+        let mut shell = Shell::new_for_test();
+        shell.run_script(&format!("exec 2> {}", log.display()));
+        shell.run_script("cd /smash-no-such-dir");
+        shell.run_script("exec 2> /dev/null");
+        shell.run_script("cd /smash-no-such-dir");
+
+        let output = std::fs::read_to_string(&log).unwrap();
+        assert_eq!(output.lines().count(), 1);
+        assert!(output.contains("/smash-no-such-dir"));
+
+        std::fs::remove_file(&log).unwrap();
+
+        // Errors are the same as the ones in other redirections.
+        let (status, _, stderr) = shell.run_capture("exec > /nonexistent/file");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(
+            stderr,
+            "smash: /nonexistent/file: No such file or directory\n"
+        );
+    }
+}
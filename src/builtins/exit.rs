@@ -4,7 +4,9 @@ use crate::ExitStatus;
 pub struct Exit;
 
 impl BuiltinCommand for Exit {
-    fn run(&self, _: &mut BuiltinCommandContext) -> ExitStatus {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        // `exit` skips destructors.
+        ctx.shell.dir_database_mut().flush();
         std::process::exit(0);
     }
 }
@@ -1,41 +1,99 @@
 use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::expand::quote;
 use crate::process::ExitStatus;
 use crate::variable::Value;
 
-use std::io::Write;
 use tracing::debug;
 
 pub struct Export;
 
 impl BuiltinCommand for Export {
     fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
-        if ctx.argv.is_empty() {
-            for name in ctx.shell.exported_names() {
-                if let Some(var) = ctx.shell.get(name) {
-                    writeln!(ctx.stdout, "{}={}", name, var.as_str()).ok();
-                }
+        if ctx.argv.len() == 1 {
+            let mut names: Vec<&String> = ctx.shell.exported_names().collect();
+            names.sort();
+            for name in names {
+                let declaration = match ctx.shell.get_str(name) {
+                    Some(value) => format!("declare -x {}={}", name, quote(&value)),
+                    None => format!("declare -x {}", name),
+                };
+                writeln!(ctx.stdout, "{}", declaration).ok();
             }
 
             return ExitStatus::ExitedWith(0);
         }
 
-        for arg in ctx.argv {
-            let frags: Vec<&str> = arg.splitn(2, '=').collect();
-            debug!("f={:?}", frags);
-            let mut iter = frags.iter();
-            match (iter.next(), iter.next()) {
-                (Some(name), Some(value)) => {
+        // `export -p` prints `export` commands which can be sourced as is.
+        if ctx.argv[1] == "-p" {
+            let mut names: Vec<&String> = ctx.shell.exported_names().collect();
+            names.sort();
+            for name in names {
+                let command = match ctx.shell.get_str(name) {
+                    Some(value) => format!("export {}={}", name, quote(&value)),
+                    None => format!("export {}", name),
+                };
+                writeln!(ctx.stdout, "{}", command).ok();
+            }
+
+            return ExitStatus::ExitedWith(0);
+        }
+
+        for arg in &ctx.argv[1..] {
+            debug!("export: arg={}", arg);
+            // `export URL=a=b` sets `URL` to `a=b`.
+            match arg.split_once('=') {
+                Some((name, value)) => {
+                    ctx.shell.set(name, Value::String(value.to_owned()), false);
                     ctx.shell.export(name);
-                    ctx.shell
-                        .set(name, Value::String(value.to_owned().to_string()), false);
                 }
-                (Some(name), None) => {
-                    ctx.shell.export(name);
+                None => {
+                    ctx.shell.export(arg);
                 }
-                _ => {}
             }
         }
 
         ExitStatus::ExitedWith(0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::Shell;
+    use crate::ExitStatus;
+
+    #[test]
+    fn export() {
+        let mut shell = Shell::new_for_test();
+        shell.run_script("export URL=a=b; QUOTED='say \"$hi\"'; export QUOTED UNSET");
+        assert_eq!(shell.get_str("URL"), Some("a=b".to_owned()));
+        assert!(shell.is_exported("URL"));
+
+        let (status, stdout, _) = shell.run_capture("export");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(
+            stdout,
+            concat!(
+                "declare -x QUOTED='say \"$hi\"'\n",
+                "declare -x UNSET\n",
+                "declare -x URL='a=b'\n",
+            )
+        );
+
+        shell.run_script("export QUOTED=\"it's \\$hi\"");
+        let (_, stdout, _) = shell.run_capture("export -p");
+        assert_eq!(
+            stdout,
+            concat!(
+                "export QUOTED='it'\\''s $hi'\n",
+                "export UNSET\n",
+                "export URL='a=b'\n",
+            )
+        );
+
+        // The output can be sourced.
+        let mut other = Shell::new_for_test();
+        other.run_script(&stdout);
+        assert_eq!(other.get_str("QUOTED"), Some("it's $hi".to_owned()));
+        assert!(other.is_exported("UNSET"));
+    }
+}
@@ -0,0 +1,107 @@
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::process::{
+    continue_job, restore_terminal_attrs, run_in_foreground, ExitStatus, Job, JobId, ProcessState,
+};
+use crate::shell::Shell;
+
+use std::rc::Rc;
+use tracing::debug;
+
+/// `fg [%id]`
+pub struct Fg;
+
+impl BuiltinCommand for Fg {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("fg: argv={:?}", ctx.argv);
+        let job = match find_job(ctx.shell, ctx.argv.get(1).map(String::as_str)) {
+            Some(job) => job,
+            None => {
+                ctx.error("fg: no such job");
+                return ExitStatus::ExitedWith(1);
+            }
+        };
+
+        writeln!(ctx.stdout, "{}", job.cmd).ok();
+
+        // Restore the terminal attributes saved when the job was stopped
+        // (e.g. the raw mode of an editor).
+        if ctx.shell.interactive() {
+            if let Some(termios) = job.termios.borrow().as_ref() {
+                restore_terminal_attrs(termios);
+            }
+        }
+
+        if let Err(err) = continue_job(ctx.shell, &job) {
+            ctx.error(format_args!("fg: {}", err.desc()));
+            return ExitStatus::ExitedWith(1);
+        }
+
+        match run_in_foreground(ctx.shell, &job) {
+            Ok(ProcessState::Completed(status)) => ExitStatus::ExitedWith(status),
+            // 128 + SIGTSTP, like other shells.
+            Ok(ProcessState::Stopped(_)) => ExitStatus::ExitedWith(148),
+            Ok(ProcessState::Running) => {
+                ctx.error(format_args!("fg: {}: still running", job.cmd));
+                ExitStatus::ExitedWith(1)
+            }
+            Err(err) => {
+                ctx.error(format_args!("fg: {}", err));
+                ExitStatus::ExitedWith(1)
+            }
+        }
+    }
+}
+
+/// Looks for the job specified by `%id` (or `id`). Without an argument, the
+/// most recent foreground job or the job with the highest id is returned.
+pub fn find_job(shell: &Shell, arg: Option<&str>) -> Option<Rc<Job>> {
+    match arg {
+        Some(arg) => {
+            let id = arg.strip_prefix('%').unwrap_or(arg).parse().ok()?;
+            shell.jobs().get(&JobId::new(id)).cloned()
+        }
+        None => shell
+            .last_fore_job
+            .as_ref()
+            .filter(|job| shell.jobs().contains_key(&job.id()))
+            .or_else(|| shell.jobs().values().max_by_key(|job| job.id()))
+            .cloned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::Shell;
+    use crate::ExitStatus;
+    use nix::sys::signal::{kill, Signal};
+
+    #[test]
+    fn fg() {
+        let mut shell = Shell::new_for_test();
+
+        let (status, _, stderr) = shell.run_capture("fg");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: fg: no such job\n");
+
+        // Resume a stopped job.
+        shell.run_script("sleep 0.2 &");
+        let pid = shell.last_background_pid().unwrap();
+        kill(pid, Signal::SIGSTOP).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let (_, stdout, _) = shell.run_capture("jobs");
+        assert_eq!(stdout, "[1]  Stopped  sleep 0.2\n");
+
+        let (status, _, stderr) = shell.run_capture("fg %2");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: fg: no such job\n");
+
+        let (status, stdout, _) = shell.run_capture("fg %1");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "sleep 0.2\n");
+        assert!(shell.jobs().is_empty());
+
+        shell.run_script("false &");
+        let (status, _, _) = shell.run_capture("fg");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+    }
+}
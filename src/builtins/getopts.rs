@@ -0,0 +1,164 @@
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::variable::Value;
+use crate::ExitStatus;
+
+use tracing::debug;
+
+/// `getopts OPTSTRING NAME [ARG...]`
+///
+/// The progress is kept in `$OPTIND` so that scripts can restart parsing by
+/// `OPTIND=1`.
+pub struct Getopts;
+
+impl BuiltinCommand for Getopts {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("getopts: argv={:?}", ctx.argv);
+        let (optstring, name) = match (ctx.argv.get(1), ctx.argv.get(2)) {
+            (Some(optstring), Some(name)) => (optstring.as_str(), name.as_str()),
+            _ => {
+                ctx.error("getopts: usage: getopts optstring name [arg ...]");
+                return ExitStatus::ExitedWith(2);
+            }
+        };
+
+        // Parse the positional parameters if no arguments are given.
+        let args: Vec<String> = if ctx.argv.len() > 3 {
+            ctx.argv[3..].to_vec()
+        } else {
+            ctx.shell.current_frame().args().to_vec()
+        };
+
+        // A leading `:` suppresses error messages.
+        let (silent, optstring) = match optstring.strip_prefix(':') {
+            Some(optstring) => (true, optstring),
+            None => (false, optstring),
+        };
+
+        let optind = ctx
+            .shell
+            .get_str("OPTIND")
+            .and_then(|optind| optind.parse().ok())
+            .filter(|optind| *optind > 0)
+            .unwrap_or(1);
+        let charindex = ctx.shell.getopts_charindex(optind);
+
+        let arg: Vec<char> = match args.get(optind - 1) {
+            Some(arg) if arg == "--" => {
+                ctx.shell.set_string("OPTIND", (optind + 1).to_string());
+                ctx.shell.set_string(name, "?");
+                return ExitStatus::ExitedWith(1);
+            }
+            Some(arg) if arg.starts_with('-') && arg != "-" => arg.chars().collect(),
+            // No more options.
+            _ => {
+                ctx.shell.set_string(name, "?");
+                return ExitStatus::ExitedWith(1);
+            }
+        };
+
+        let opt = arg[charindex.min(arg.len() - 1)];
+        let (mut next_optind, mut next_charindex) = if charindex + 1 < arg.len() {
+            (optind, charindex + 1)
+        } else {
+            (optind + 1, 1)
+        };
+
+        let takes_arg = match optstring.find(opt) {
+            Some(index) if opt != ':' => optstring[index + opt.len_utf8()..].starts_with(':'),
+            _ => {
+                if silent {
+                    ctx.shell.set_string("OPTARG", opt.to_string());
+                } else {
+                    ctx.error(format_args!("getopts: illegal option -- {}", opt));
+                    ctx.shell.unset("OPTARG");
+                }
+
+                ctx.shell.set_string(name, "?");
+                ctx.shell.set_string("OPTIND", next_optind.to_string());
+                ctx.shell.set_getopts_charindex(next_optind, next_charindex);
+                return ExitStatus::ExitedWith(0);
+            }
+        };
+
+        ctx.shell.set_string(name, opt.to_string());
+        if takes_arg {
+            // `-bfoo` or `-b foo`.
+            let optarg = if charindex + 1 < arg.len() {
+                next_optind = optind + 1;
+                Some(arg[charindex + 1..].iter().collect())
+            } else {
+                let optarg = args.get(optind).cloned();
+                next_optind = optind + 1 + usize::from(optarg.is_some());
+                optarg
+            };
+            next_charindex = 1;
+
+            match optarg {
+                Some(optarg) => ctx.shell.set("OPTARG", Value::String(optarg), false),
+                None if silent => {
+                    ctx.shell.set_string(name, ":");
+                    ctx.shell.set_string("OPTARG", opt.to_string());
+                }
+                None => {
+                    ctx.error(format_args!(
+                        "getopts: option requires an argument -- {}",
+                        opt
+                    ));
+                    ctx.shell.set_string(name, "?");
+                    ctx.shell.unset("OPTARG");
+                }
+            }
+        } else {
+            ctx.shell.unset("OPTARG");
+        }
+
+        ctx.shell.set_string("OPTIND", next_optind.to_string());
+        ctx.shell.set_getopts_charindex(next_optind, next_charindex);
+        ExitStatus::ExitedWith(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::Shell;
+
+    #[test]
+    fn getopts() {
+        let mut shell = Shell::new_for_test();
+        shell.run_script(
+            "parse() { OPTIND=1; while getopts ab:c opt; do echo \"$opt=$OPTARG\"; done; echo $OPTIND; }",
+        );
+
+        let (_, stdout, _) = shell.run_capture("parse -a -b foo -c rest");
+        assert_eq!(stdout, "a=\nb=foo\nc=\n5\n");
+
+        // Grouped options and an option argument in the same word.
+        let (_, stdout, _) = shell.run_capture("parse -ca -bfoo -- -c");
+        assert_eq!(stdout, "c=\na=\nb=foo\n4\n");
+
+        let (_, stdout, stderr) = shell.run_capture("parse -x -b");
+        assert_eq!(stdout, "?=\n?=\n3\n");
+        assert_eq!(
+            stderr,
+            concat!(
+                "smash: getopts: illegal option -- x\n",
+                "smash: getopts: option requires an argument -- b\n"
+            )
+        );
+
+        // Without resetting `OPTIND`, the second call continues from where
+        // the first one stopped.
+        shell.run_script("noreset() { while getopts a opt; do echo $opt; done; }");
+        let (_, stdout, _) =
+            shell.run_capture("OPTIND=1; noreset -a; noreset -a; OPTIND=1; noreset -a");
+        assert_eq!(stdout, "a\na\n");
+
+        // Silent mode.
+        let (_, stdout, stderr) = shell.run_capture(concat!(
+            "OPTIND=1; getopts :b: opt -x; echo $opt $OPTARG;",
+            "OPTIND=1; getopts :b: opt -b; echo $opt $OPTARG"
+        ));
+        assert_eq!(stdout, "? x\n: b\n");
+        assert_eq!(stderr, "");
+    }
+}
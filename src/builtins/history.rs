@@ -0,0 +1,81 @@
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::path::abbreviate_home;
+use crate::ExitStatus;
+
+use tracing::debug;
+
+/// `history [-l] [N]`
+pub struct History;
+
+impl BuiltinCommand for History {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("history: argv={:?}", ctx.argv);
+        let mut show_dirs = false;
+        let mut count = None;
+        for arg in &ctx.argv[1..] {
+            match arg.as_str() {
+                "-l" => show_dirs = true,
+                _ => match arg.parse::<usize>() {
+                    Ok(n) if count.is_none() => count = Some(n),
+                    Ok(_) => {
+                        ctx.error("history: too many arguments");
+                        return ExitStatus::ExitedWith(1);
+                    }
+                    Err(_) => {
+                        ctx.error(format_args!("history: {}: invalid argument", arg));
+                        return ExitStatus::ExitedWith(1);
+                    }
+                },
+            }
+        }
+
+        let history = ctx.shell.history();
+        let entries = history.entries();
+        let skip = entries.len() - count.unwrap_or(entries.len()).min(entries.len());
+        for (i, cmd) in entries.iter().enumerate().skip(skip) {
+            if show_dirs {
+                let dir = history
+                    .cwd(cmd)
+                    .map(|dir| abbreviate_home(&dir.to_string_lossy()))
+                    .unwrap_or_default();
+                writeln!(ctx.stdout, "{:>5}  {}  {}", i + 1, dir, cmd).ok();
+            } else {
+                writeln!(ctx.stdout, "{:>5}  {}", i + 1, cmd).ok();
+            }
+        }
+
+        ExitStatus::ExitedWith(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::path::abbreviate_home;
+    use crate::shell::Shell;
+    use crate::ExitStatus;
+
+    #[test]
+    fn history() {
+        let mut shell = Shell::new_for_test();
+        shell.history_mut().append("echo a");
+        shell.history_mut().append("echo b");
+
+        let (status, stdout, _) = shell.run_capture("history 1");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "    2  echo b\n");
+
+        // Other tests may change the current directory concurrently.
+        let dir = |cmd| abbreviate_home(&shell.history().cwd(cmd).unwrap().to_string_lossy());
+        let expected = format!(
+            "    1  {}  echo a\n    2  {}  echo b\n",
+            dir("echo a"),
+            dir("echo b")
+        );
+        let (_, stdout, _) = shell.run_capture("history -l");
+        assert_eq!(stdout, expected);
+
+        let (status, _, stderr) = shell.run_capture("history x");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: history: x: invalid argument\n");
+    }
+}
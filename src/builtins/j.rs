@@ -0,0 +1,45 @@
+use super::cd::change_dir;
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::path::abbreviate_home;
+use crate::ExitStatus;
+
+use tracing::debug;
+
+/// `j PATTERN...` jumps to the most frecently visited directory matching all
+/// of the patterns. `j` without arguments lists directories with their scores.
+pub struct J;
+
+impl BuiltinCommand for J {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("j: argv={:?}", ctx.argv);
+        let patterns = ctx.argv[1..].to_vec();
+        if patterns.is_empty() {
+            for (score, dir) in ctx.shell.dir_database().ranked() {
+                writeln!(ctx.stdout, "{:>8.2}  {}", score, abbreviate_home(dir)).ok();
+            }
+
+            return ExitStatus::ExitedWith(0);
+        }
+
+        let current_dir = match std::env::current_dir() {
+            Ok(dir) => dir,
+            Err(err) => {
+                ctx.error(format_args!("j: {}", err));
+                return ExitStatus::ExitedWith(1);
+            }
+        };
+        let dir = ctx
+            .shell
+            .dir_database()
+            .best_match(&patterns, &current_dir.to_string_lossy())
+            .map(str::to_owned);
+        match dir {
+            Some(dir) if change_dir(ctx, &dir) => ExitStatus::ExitedWith(0),
+            Some(_) => ExitStatus::ExitedWith(1),
+            None => {
+                ctx.error(format_args!("j: no match for `{}'", patterns.join(" ")));
+                ExitStatus::ExitedWith(1)
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::process::{destroy_job, wait_for_any_process, ExitStatus, Job};
+
+use std::rc::Rc;
+use tracing::debug;
+
+/// `jobs`
+pub struct Jobs;
+
+impl BuiltinCommand for Jobs {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("jobs: argv={:?}", ctx.argv);
+
+        // Update the process states without blocking.
+        while let Ok(Some(_)) = wait_for_any_process(ctx.shell, true) {}
+
+        let mut jobs: Vec<Rc<Job>> = ctx.shell.jobs().values().cloned().collect();
+        jobs.sort_by_key(|job| job.id());
+        for job in jobs {
+            let state = if job.completed(ctx.shell) {
+                // Like other shells, finished jobs are reported only once.
+                destroy_job(ctx.shell, &job);
+                "Done"
+            } else if job.stopped(ctx.shell) {
+                "Stopped"
+            } else {
+                "Running"
+            };
+
+            writeln!(ctx.stdout, "[{}]  {}  {}", job.id(), state, job.cmd).ok();
+        }
+
+        ExitStatus::ExitedWith(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::Shell;
+
+    #[test]
+    fn jobs() {
+        let mut shell = Shell::new_for_test();
+
+        shell.run_script("sleep 5 &");
+        let pid = shell.last_background_pid().unwrap();
+        let (_, stdout, _) = shell.run_capture("jobs");
+        assert_eq!(stdout, "[1]  Running  sleep 5\n");
+
+        nix::sys::signal::kill(pid, nix::sys::signal::Signal::SIGKILL).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let (_, stdout, _) = shell.run_capture("jobs; jobs");
+        assert_eq!(stdout, "[1]  Done  sleep 5\n");
+    }
+}
@@ -0,0 +1,137 @@
+use super::fg::find_job;
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::process::{signal_job, ExitStatus};
+
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use std::str::FromStr;
+use tracing::debug;
+
+/// `kill [-SIGNAL] pid|%id...`
+pub struct Kill;
+
+impl BuiltinCommand for Kill {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("kill: argv={:?}", ctx.argv);
+        let mut args = &ctx.argv[1..];
+        let mut signal = Some(Signal::SIGTERM);
+        if let Some(spec) = args.first().and_then(|arg| arg.strip_prefix('-')) {
+            signal = match parse_signal(spec) {
+                Some(signal) => signal,
+                None => {
+                    ctx.error(format_args!("kill: {}: invalid signal specification", spec));
+                    return ExitStatus::ExitedWith(1);
+                }
+            };
+            args = &args[1..];
+        }
+
+        if args.is_empty() {
+            ctx.error("kill: usage: kill [-SIGNAL] pid | %job ...");
+            return ExitStatus::ExitedWith(2);
+        }
+
+        let mut status = 0;
+        for arg in args {
+            let result = if arg.starts_with('%') {
+                match find_job(ctx.shell, Some(arg.as_str())) {
+                    Some(job) => signal_job(ctx.shell, &job, signal),
+                    None => {
+                        ctx.error(format_args!("kill: {}: no such job", arg));
+                        status = 1;
+                        continue;
+                    }
+                }
+            } else {
+                match arg.parse() {
+                    Ok(pid) => kill(Pid::from_raw(pid), signal),
+                    Err(_) => {
+                        ctx.error(format_args!(
+                            "kill: {}: arguments must be process or job IDs",
+                            arg
+                        ));
+                        status = 1;
+                        continue;
+                    }
+                }
+            };
+
+            if let Err(err) = result {
+                ctx.error(format_args!("kill: {}: {}", arg, err.desc()));
+                status = 1;
+            }
+        }
+
+        ExitStatus::ExitedWith(status)
+    }
+}
+
+/// Parses a signal name (e.g. `TERM` or `SIGTERM`) or number (e.g. `15`).
+/// Signal 0 is `Some(None)`: it sends nothing but checks if the process
+/// exists.
+fn parse_signal(spec: &str) -> Option<Option<Signal>> {
+    if let Ok(num) = spec.parse::<i32>() {
+        return match num {
+            0 => Some(None),
+            _ => Signal::try_from(num).ok().map(Some),
+        };
+    }
+
+    let name = spec.to_ascii_uppercase();
+    let signal = if name.starts_with("SIG") {
+        Signal::from_str(&name).ok()
+    } else {
+        Signal::from_str(&format!("SIG{}", name)).ok()
+    };
+    signal.map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::Shell;
+
+    #[test]
+    fn signal_names() {
+        assert_eq!(parse_signal("9"), Some(Some(Signal::SIGKILL)));
+        assert_eq!(parse_signal("HUP"), Some(Some(Signal::SIGHUP)));
+        assert_eq!(parse_signal("sigint"), Some(Some(Signal::SIGINT)));
+        assert_eq!(parse_signal("FOO"), None);
+        assert_eq!(parse_signal("0"), Some(None));
+        assert_eq!(parse_signal("-1"), None);
+    }
+
+    #[test]
+    fn kill() {
+        let mut shell = Shell::new_for_test();
+
+        shell.run_script("sleep 5 &");
+        let (status, _, _) = shell.run_capture("kill %1");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let (_, stdout, _) = shell.run_capture("jobs");
+        assert_eq!(stdout, "[1]  Done  sleep 5\n");
+
+        shell.run_script("sleep 5 &");
+        let (status, _, _) = shell.run_capture("kill -KILL $!");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let (_, stdout, _) = shell.run_capture("jobs");
+        assert_eq!(stdout, "[1]  Done  sleep 5\n");
+
+        // `kill -0` only checks if the process exists.
+        let (status, _, _) = shell.run_capture("kill -0 $$");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        let (status, _, stderr) = shell.run_capture("kill -0 %9");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: kill: %9: no such job\n");
+
+        let (status, _, stderr) = shell.run_capture("kill -FOO 1");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: kill: FOO: invalid signal specification\n");
+
+        let (status, _, stderr) = shell.run_capture("kill %9");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: kill: %9: no such job\n");
+    }
+}
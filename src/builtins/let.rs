@@ -0,0 +1,67 @@
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::arith::eval_arith;
+use crate::parser::parse_arith;
+use crate::ExitStatus;
+
+use tracing::debug;
+
+/// `let EXPR...`
+///
+/// Like other shells, it fails if the last expression is evaluated to 0.
+pub struct Let;
+
+impl BuiltinCommand for Let {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("let: argv={:?}", ctx.argv);
+        if ctx.argv.len() < 2 {
+            ctx.error("let: expression expected");
+            return ExitStatus::ExitedWith(1);
+        }
+
+        let mut last_value = 0;
+        for arg in &ctx.argv[1..] {
+            let expr = match parse_arith(arg) {
+                Ok(expr) => expr,
+                Err(_) => {
+                    ctx.error(format_args!("let: {}: syntax error", arg));
+                    return ExitStatus::ExitedWith(1);
+                }
+            };
+
+            last_value = match eval_arith(ctx.shell, &expr) {
+                Ok(value) => value,
+                Err(err) => {
+                    ctx.error(format_args!("let: {}: {}", arg, err));
+                    return ExitStatus::ExitedWith(1);
+                }
+            };
+        }
+
+        if last_value == 0 {
+            ExitStatus::ExitedWith(1)
+        } else {
+            ExitStatus::ExitedWith(0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::Shell;
+    use crate::ExitStatus;
+
+    #[test]
+    fn let_expressions() {
+        let mut shell = Shell::new_for_test();
+        let (status, stdout, _) = shell.run_capture("i=1; let i=i+1 'j = i * 3' i++; echo $i $j");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "3 6\n");
+
+        let (status, _, _) = shell.run_capture("let 'i - 3'");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+
+        let (status, _, stderr) = shell.run_capture("let '1 +'");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: let: 1 +: syntax error\n");
+    }
+}
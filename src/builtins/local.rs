@@ -0,0 +1,72 @@
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::variable::{is_valid_name, Value};
+use crate::ExitStatus;
+
+use tracing::debug;
+
+/// `local NAME[=VALUE]...`
+///
+/// Local variables are also visible from the functions called from the
+/// function unless they define their own ones.
+pub struct Local;
+
+impl BuiltinCommand for Local {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("local: argv={:?}", ctx.argv);
+        if !ctx.shell.in_function() {
+            ctx.error("local: can only be used in a function");
+            return ExitStatus::ExitedWith(1);
+        }
+
+        let mut status = 0;
+        for arg in &ctx.argv[1..] {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, value),
+                None => (arg.as_str(), ""),
+            };
+
+            if !is_valid_name(name) {
+                ctx.error(format_args!("local: `{}': not a valid identifier", arg));
+                status = 1;
+                continue;
+            }
+
+            ctx.shell.set(name, Value::String(value.to_owned()), true);
+        }
+
+        ExitStatus::ExitedWith(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::Shell;
+    use crate::ExitStatus;
+
+    #[test]
+    fn dynamic_scoping() {
+        let mut shell = Shell::new_for_test();
+        shell.run_script(concat!(
+            "x=global;",
+            "show() { echo $x; };",
+            "update() { x=updated; };",
+            "shadow() { local x=shadowed; show; };",
+            "outer() { local x=outer; show; shadow; show; update; show; }"
+        ));
+
+        // Callees see the caller's local variable and assignments in them
+        // update it instead of the global one.
+        let (_, stdout, _) = shell.run_capture("outer; echo $x");
+        assert_eq!(stdout, "outer\nshadowed\nouter\nupdated\nglobal\n");
+
+        let (status, _, stderr) = shell.run_capture("local x=1");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: local: can only be used in a function\n");
+
+        shell.run_script("f() { local 1x=2 y=3; echo $y; }");
+        let (status, stdout, stderr) = shell.run_capture("f");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "3\n");
+        assert_eq!(stderr, "smash: local: `1x=2': not a valid identifier\n");
+    }
+}
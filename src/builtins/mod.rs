@@ -1,15 +1,65 @@
 use crate::fd_file::FdFile;
+use crate::parser;
 use crate::shell::Shell;
 use crate::ExitStatus;
 
+use std::fmt;
+use std::io::Write;
+use std::os::unix::io::RawFd;
 use thiserror::Error;
 
 mod alias;
+mod bg;
 mod cd;
+mod cdh;
+mod compgen;
+mod declare;
+mod dirs;
+mod echo;
 mod eval;
+mod exec;
 mod exit;
 mod export;
+mod fg;
+mod getopts;
+mod history;
+mod j;
+mod jobs;
+mod kill;
+mod r#let;
+mod local;
+mod popd;
+mod pushd;
+mod pwd;
+mod read;
+mod repeat;
+mod set;
 mod source;
+mod suspend;
+mod trap;
+mod r#type;
+mod ulimit;
+mod unalias;
+mod unset;
+
+pub use alias::aliases_file;
+
+/// The names of builtin commands (for completion).
+pub const BUILTIN_COMMANDS: &[&str] = &[
+    "alias", "bg", "cd", "cdh", "compgen", "declare", "dirs", "echo", "eval", "exec", "exit",
+    "export", "fg", "getopts", "history", "j", "jobs", "kill", "let", "local", "popd", "pushd",
+    "pwd", "read", "repeat", "set", "source", "suspend", "trap", "type", "ulimit", "unalias",
+    "unset",
+];
+
+/// Returns the names which can be run as a command: aliases, builtins, and
+/// commands in `$PATH`.
+pub fn command_names(shell: &Shell) -> Vec<String> {
+    let mut names: Vec<String> = shell.aliases().map(|(name, _)| name.clone()).collect();
+    names.extend(BUILTIN_COMMANDS.iter().map(|name| name.to_string()));
+    names.extend(shell.path_table().to_vec());
+    names
+}
 
 pub trait BuiltinCommand {
     fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus;
@@ -19,8 +69,22 @@ pub struct BuiltinCommandContext<'a> {
     pub argv: &'a [String],
     pub shell: &'a mut Shell,
     pub stdin: FdFile,
-    pub stdout: FdFile,
-    pub stderr: FdFile,
+    /// Where the output goes. It's usually an `FdFile` but can be anything
+    /// (e.g. a `Vec<u8>` in tests).
+    pub stdout: &'a mut dyn Write,
+    pub stderr: &'a mut dyn Write,
+    /// The underlying stdin/stdout/stderr file descriptors. Used to run other
+    /// commands (e.g. `repeat`).
+    pub stdio: [RawFd; 3],
+    pub redirects: &'a [parser::Redirection],
+}
+
+impl BuiltinCommandContext<'_> {
+    /// Prints an error message to stderr. It's prefixed by the shell name,
+    /// or the script name and the line number (e.g. `dev.sh: line 12: `).
+    pub fn error(&mut self, msg: impl fmt::Display) {
+        writeln!(self.stderr, "{}: {}", self.shell.error_prefix(), msg).ok();
+    }
 }
 
 #[derive(Debug, Error)]
@@ -31,12 +95,86 @@ pub enum BuiltinCommandError {
 
 pub fn builtin_command(name: &str) -> Option<Box<dyn BuiltinCommand>> {
     match name {
+        "bg" => Some(Box::new(bg::Bg)),
         "cd" => Some(Box::new(cd::Cd)),
+        "cdh" => Some(Box::new(cdh::Cdh)),
+        "compgen" => Some(Box::new(compgen::Compgen)),
+        "declare" => Some(Box::new(declare::Declare)),
+        "echo" => Some(Box::new(echo::Echo)),
         "eval" => Some(Box::new(eval::Eval)),
+        "exec" => Some(Box::new(exec::Exec)),
         "exit" => Some(Box::new(exit::Exit)),
         "export" => Some(Box::new(export::Export)),
+        "fg" => Some(Box::new(fg::Fg)),
+        "getopts" => Some(Box::new(getopts::Getopts)),
+        "history" => Some(Box::new(history::History)),
+        "j" => Some(Box::new(j::J)),
+        "jobs" => Some(Box::new(jobs::Jobs)),
+        "kill" => Some(Box::new(kill::Kill)),
+        "let" => Some(Box::new(r#let::Let)),
+        "local" => Some(Box::new(local::Local)),
         "source" => Some(Box::new(source::Source)),
         "alias" => Some(Box::new(alias::Alias)),
+        "set" => Some(Box::new(set::Set)),
+        "dirs" => Some(Box::new(dirs::Dirs)),
+        "pushd" => Some(Box::new(pushd::Pushd)),
+        "popd" => Some(Box::new(popd::Popd)),
+        "pwd" => Some(Box::new(pwd::Pwd)),
+        "read" => Some(Box::new(read::Read)),
+        "repeat" => Some(Box::new(repeat::Repeat)),
+        "suspend" => Some(Box::new(suspend::Suspend)),
+        "trap" => Some(Box::new(trap::Trap)),
+        "type" => Some(Box::new(r#type::Type)),
+        "ulimit" => Some(Box::new(ulimit::Ulimit)),
+        "unalias" => Some(Box::new(unalias::Unalias)),
+        "unset" => Some(Box::new(unset::Unset)),
         _ => None,
     }
 }
+
+/// Runs a builtin command with in-memory stdout and stderr and returns its
+/// exit status and output.
+#[cfg(test)]
+pub fn run_builtin(shell: &mut Shell, argv: &[&str]) -> (ExitStatus, String, String) {
+    let argv: Vec<String> = argv.iter().map(|arg| arg.to_string()).collect();
+    let command = builtin_command(&argv[0]).expect("not a builtin command");
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let status = command.run(&mut BuiltinCommandContext {
+        argv: &argv,
+        shell,
+        stdin: FdFile::new(0),
+        stdout: &mut stdout,
+        stderr: &mut stderr,
+        stdio: [0, 1, 2],
+        redirects: &[],
+    });
+
+    (
+        status,
+        String::from_utf8(stdout).unwrap(),
+        String::from_utf8(stderr).unwrap(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn in_memory_output() {
+        let mut shell = Shell::new_for_test();
+        let (status, stdout, stderr) = run_builtin(&mut shell, &["echo", "-n", "a", "b"]);
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!((stdout.as_str(), stderr.as_str()), ("a b", ""));
+
+        let current_dir = std::env::current_dir().unwrap();
+        let (_, stdout, _) = run_builtin(&mut shell, &["pwd"]);
+        assert_eq!(stdout, format!("{}\n", current_dir.display()));
+
+        let (status, stdout, stderr) = run_builtin(&mut shell, &["pwd", "-x"]);
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stdout, "");
+        assert_eq!(stderr, "smash: pwd: -x: invalid option\n");
+    }
+}
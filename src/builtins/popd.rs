@@ -0,0 +1,50 @@
+use super::cd::change_dir;
+use super::dirs::{is_stack_index, print_dirs, stack_index};
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::ExitStatus;
+
+use tracing::debug;
+
+pub struct Popd;
+
+impl BuiltinCommand for Popd {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("popd: argv={:?}", ctx.argv);
+        // Remove the N-th directory from the stack.
+        if let Some(arg) = ctx.argv.get(1).filter(|arg| is_stack_index(arg)) {
+            let num_dirs = ctx.shell.dir_stack().len() + 1;
+            match stack_index(arg, num_dirs) {
+                // `popd +0` is same as `popd`.
+                Some(0) => (),
+                Some(n) => {
+                    ctx.shell.remove_dir_stack_entry(n);
+                    print_dirs(ctx);
+                    return ExitStatus::ExitedWith(0);
+                }
+                None => {
+                    ctx.error(format_args!(
+                        "popd: {}: directory stack index out of range",
+                        arg
+                    ));
+                    return ExitStatus::ExitedWith(1);
+                }
+            }
+        }
+
+        match ctx.shell.popd() {
+            Some(dir) => {
+                if !change_dir(ctx, &dir) {
+                    ctx.shell.pushd(dir);
+                    return ExitStatus::ExitedWith(1);
+                }
+            }
+            None => {
+                ctx.error("popd: directory stack empty");
+                return ExitStatus::ExitedWith(1);
+            }
+        }
+
+        print_dirs(ctx);
+        ExitStatus::ExitedWith(0)
+    }
+}
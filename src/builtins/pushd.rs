@@ -0,0 +1,66 @@
+use super::cd::change_dir;
+use super::dirs::{is_stack_index, print_dirs, stack_index};
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::ExitStatus;
+
+use tracing::debug;
+
+pub struct Pushd;
+
+impl BuiltinCommand for Pushd {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("pushd: argv={:?}", ctx.argv);
+        let current_dir = std::env::current_dir().expect("failed to getcwd()");
+        let current_dir = current_dir.to_string_lossy().into_owned();
+        match ctx.argv.get(1) {
+            // Rotate the stack so that the N-th directory becomes the top.
+            Some(arg) if is_stack_index(arg) => {
+                let num_dirs = ctx.shell.dir_stack().len() + 1;
+                let n = match stack_index(arg, num_dirs) {
+                    Some(n) => n,
+                    None => {
+                        ctx.error(format_args!(
+                            "pushd: {}: directory stack index out of range",
+                            arg
+                        ));
+                        return ExitStatus::ExitedWith(1);
+                    }
+                };
+
+                if n > 0 {
+                    let dir = ctx.shell.dir_stack()[num_dirs - 1 - n].clone();
+                    if !change_dir(ctx, &dir) {
+                        return ExitStatus::ExitedWith(1);
+                    }
+
+                    ctx.shell.rotate_dir_stack(current_dir, n);
+                }
+
+                print_dirs(ctx);
+                return ExitStatus::ExitedWith(0);
+            }
+            Some(dir) => {
+                if !change_dir(ctx, &dir.clone()) {
+                    return ExitStatus::ExitedWith(1);
+                }
+            }
+            // Exchange the top two directories.
+            None => match ctx.shell.popd() {
+                Some(dir) => {
+                    if !change_dir(ctx, &dir) {
+                        ctx.shell.pushd(dir);
+                        return ExitStatus::ExitedWith(1);
+                    }
+                }
+                None => {
+                    ctx.error("pushd: no other directory");
+                    return ExitStatus::ExitedWith(1);
+                }
+            },
+        }
+
+        ctx.shell.pushd(current_dir);
+        print_dirs(ctx);
+        ExitStatus::ExitedWith(0)
+    }
+}
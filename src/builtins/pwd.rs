@@ -0,0 +1,77 @@
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::ExitStatus;
+
+use std::path::Path;
+use tracing::debug;
+
+/// `pwd [-L|-P]` prints the current directory.
+pub struct Pwd;
+
+impl BuiltinCommand for Pwd {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("pwd: argv={:?}", ctx.argv);
+        let mut physical = false;
+        for arg in &ctx.argv[1..] {
+            match arg.as_str() {
+                "-L" => physical = false,
+                "-P" => physical = true,
+                _ => {
+                    ctx.error(format_args!("pwd: {}: invalid option", arg));
+                    return ExitStatus::ExitedWith(1);
+                }
+            }
+        }
+
+        let current_dir = match std::env::current_dir() {
+            Ok(dir) => dir,
+            Err(err) => {
+                ctx.error(format_args!("pwd: {}", err));
+                return ExitStatus::ExitedWith(1);
+            }
+        };
+
+        // The logical path keeps symbolic links in `$PWD` as long as it still
+        // refers to the current directory.
+        let logical_dir = ctx
+            .shell
+            .get_str("PWD")
+            .filter(|pwd| !physical && is_same_dir(Path::new(pwd), &current_dir));
+        match logical_dir {
+            Some(dir) => writeln!(ctx.stdout, "{}", dir).ok(),
+            None => writeln!(ctx.stdout, "{}", current_dir.display()).ok(),
+        };
+
+        ExitStatus::ExitedWith(0)
+    }
+}
+
+fn is_same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(canonical_a), Ok(canonical_b)) => a.is_absolute() && canonical_a == canonical_b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::process::ExitStatus;
+    use crate::shell::Shell;
+
+    #[test]
+    fn pwd() {
+        let current_dir = std::env::current_dir().unwrap();
+        let mut shell = Shell::new_for_test();
+        let (status, stdout, _) = shell.run_capture("pwd");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, format!("{}\n", current_dir.display()));
+
+        // A stale `$PWD` is ignored.
+        shell.set_string("PWD", "/nonexistent");
+        let (_, stdout, _) = shell.run_capture("pwd -L");
+        assert_eq!(stdout, format!("{}\n", current_dir.display()));
+
+        let (status, _, stderr) = shell.run_capture("pwd -x");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: pwd: -x: invalid option\n");
+    }
+}
@@ -0,0 +1,294 @@
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::variable::Value;
+use crate::ExitStatus;
+
+use nix::errno::Errno;
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::sys::termios::{tcgetattr, tcsetattr, LocalFlags, SetArg, SpecialCharacterIndices};
+use nix::unistd;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// `read [-r] [-a ARRAY] [-n NCHARS] [-t TIMEOUT] [NAME...]`
+pub struct Read;
+
+/// Why `read_line` has stopped reading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReadEnd {
+    Newline,
+    /// Read the number of characters specified by `-n`.
+    Count,
+    Eof,
+    /// No input within the time specified by `-t`.
+    Timeout,
+}
+
+impl BuiltinCommand for Read {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("read: argv={:?}", ctx.argv);
+        let mut raw = false;
+        let mut array = None;
+        let mut nchars = None;
+        let mut timeout = None;
+        let mut args = ctx.argv.iter().skip(1).peekable();
+        while let Some(&arg) = args.peek() {
+            match arg.as_str() {
+                "-r" => raw = true,
+                "-a" | "-n" | "-t" => {
+                    args.next();
+                    let value = match args.peek() {
+                        Some(value) => value.as_str(),
+                        None => {
+                            ctx.error(format_args!("read: {}: option requires an argument", arg));
+                            return ExitStatus::ExitedWith(2);
+                        }
+                    };
+
+                    match arg.as_str() {
+                        "-a" => array = Some(value),
+                        "-n" => match value.parse::<usize>() {
+                            Ok(n) => nchars = Some(n),
+                            Err(_) => {
+                                ctx.error(format_args!("read: {}: invalid number", value));
+                                return ExitStatus::ExitedWith(2);
+                            }
+                        },
+                        _ => match value.parse::<f64>() {
+                            Ok(secs) if secs.is_finite() && secs >= 0.0 => {
+                                timeout = Some(Duration::from_secs_f64(secs));
+                            }
+                            _ => {
+                                ctx.error(format_args!(
+                                    "read: {}: invalid timeout specification",
+                                    value
+                                ));
+                                return ExitStatus::ExitedWith(2);
+                            }
+                        },
+                    }
+                }
+                "--" => {
+                    args.next();
+                    break;
+                }
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    ctx.error(format_args!("read: {}: invalid option", arg));
+                    return ExitStatus::ExitedWith(2);
+                }
+                _ => break,
+            }
+
+            args.next();
+        }
+
+        let names: Vec<&String> = args.collect();
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let (line, end) = read_line(ctx.stdin.as_raw_fd(), raw, nchars, deadline);
+        let ifs = ctx.shell.ifs();
+        if let Some(array) = array {
+            let fields = split_fields(&line, &ifs)
+                .into_iter()
+                .map(|(_, field)| field)
+                .collect();
+            ctx.shell.set(array, Value::Array(fields), false);
+        } else if names.is_empty() {
+            ctx.shell.set_string("REPLY", line);
+        } else {
+            let fields = split_fields(&line, &ifs);
+            for (i, name) in names.iter().enumerate() {
+                let value = match fields.get(i) {
+                    // The last name takes the rest of the line.
+                    Some((start, _)) if i == names.len() - 1 => line[*start..]
+                        .trim_end_matches(|c: char| c.is_whitespace() && ifs.contains(c)),
+                    Some((_, field)) => field.as_str(),
+                    None => "",
+                };
+
+                ctx.shell.set_string(name, value);
+            }
+        }
+
+        // Like other shells, it fails if the input ends without a newline
+        // even though the variables are set.
+        match end {
+            ReadEnd::Newline | ReadEnd::Count => ExitStatus::ExitedWith(0),
+            ReadEnd::Eof => ExitStatus::ExitedWith(1),
+            // 128 + SIGALRM, like bash.
+            ReadEnd::Timeout => ExitStatus::ExitedWith(142),
+        }
+    }
+}
+
+/// Reads a line byte by byte so that the rest of the input is left for the
+/// following commands. Returns the line and why it has stopped reading.
+/// Unless `raw` is true, a backslash escapes the next character and a
+/// backslash-newline continues the line.
+///
+/// If `nchars` is given, it returns after reading the number of characters
+/// without waiting for a newline. If `deadline` is given, it gives up
+/// reading when no input arrives until then.
+fn read_line(
+    fd: RawFd,
+    raw: bool,
+    nchars: Option<usize>,
+    deadline: Option<Instant>,
+) -> (String, ReadEnd) {
+    // Disable the line buffering of the terminal to read characters as soon
+    // as they are typed.
+    let saved_termios = nchars.and_then(|_| tcgetattr(fd).ok());
+    if let Some(termios) = &saved_termios {
+        let mut termios = termios.clone();
+        termios.local_flags.remove(LocalFlags::ICANON);
+        termios.control_chars[SpecialCharacterIndices::VMIN as usize] = 1;
+        termios.control_chars[SpecialCharacterIndices::VTIME as usize] = 0;
+        tcsetattr(fd, SetArg::TCSANOW, &termios).ok();
+    }
+
+    let mut bytes = Vec::new();
+    let mut escaped = false;
+    let mut buf = [0; 1];
+    let end = loop {
+        if let Some(nchars) = nchars {
+            // Don't count an incomplete UTF-8 sequence.
+            if std::str::from_utf8(&bytes).is_ok_and(|s| s.chars().count() >= nchars) {
+                break ReadEnd::Count;
+            }
+        }
+
+        if let Some(deadline) = deadline {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let mut fds = [PollFd::new(fd, PollFlags::POLLIN)];
+            match poll(&mut fds, timeout.as_millis().try_into().unwrap_or(i32::MAX)) {
+                Ok(0) => break ReadEnd::Timeout,
+                Err(Errno::EINTR) => continue,
+                _ => (),
+            }
+        }
+
+        match unistd::read(fd, &mut buf) {
+            Ok(1) => (),
+            Err(Errno::EINTR) => continue,
+            _ => break ReadEnd::Eof,
+        }
+
+        match buf[0] {
+            b'\n' if escaped => escaped = false,
+            byte if escaped => {
+                bytes.push(byte);
+                escaped = false;
+            }
+            b'\\' if !raw => escaped = true,
+            b'\n' => break ReadEnd::Newline,
+            byte => bytes.push(byte),
+        }
+    };
+
+    if let Some(termios) = saved_termios {
+        tcsetattr(fd, SetArg::TCSANOW, &termios).ok();
+    }
+
+    (String::from_utf8_lossy(&bytes).into_owned(), end)
+}
+
+/// Splits `line` by `$IFS` characters and returns the fields with their byte
+/// offsets. Like other shells, whitespace in `$IFS` around fields is ignored
+/// and consecutive whitespace delimits only once.
+fn split_fields(line: &str, ifs: &str) -> Vec<(usize, String)> {
+    let is_ifs_whitespace = |c: char| c.is_whitespace() && ifs.contains(c);
+    let mut fields = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    while chars.next_if(|(_, c)| is_ifs_whitespace(*c)).is_some() {}
+
+    while let Some(&(start, _)) = chars.peek() {
+        let mut field = String::new();
+        let mut delimiter = None;
+        for (_, c) in chars.by_ref() {
+            if ifs.contains(c) {
+                delimiter = Some(c);
+                break;
+            }
+
+            field.push(c);
+        }
+
+        fields.push((start, field));
+        while chars.next_if(|(_, c)| is_ifs_whitespace(*c)).is_some() {}
+
+        // `a , b` with IFS=" ,": the comma and whitespace around it make up
+        // a single delimiter.
+        if delimiter.is_some_and(is_ifs_whitespace)
+            && chars.next_if(|(_, c)| ifs.contains(*c)).is_some()
+        {
+            while chars.next_if(|(_, c)| is_ifs_whitespace(*c)).is_some() {}
+        }
+    }
+
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::Shell;
+
+    #[test]
+    fn split_by_ifs() {
+        let fields = |line: &str, ifs: &str| -> Vec<String> {
+            split_fields(line, ifs)
+                .into_iter()
+                .map(|(_, field)| field)
+                .collect()
+        };
+
+        assert_eq!(fields("  a  b c ", " \t\n"), vec!["a", "b", "c"]);
+        assert_eq!(fields("a,,b,", ","), vec!["a", "", "b"]);
+        assert_eq!(fields("a , b", " ,"), vec!["a", "b"]);
+        assert!(fields("   ", " ").is_empty());
+    }
+
+    #[test]
+    fn read() {
+        let mut shell = Shell::new_for_test();
+        let (status, stdout, _) = shell.run_capture(
+            "echo 'a b  c' | read -a parts; echo ${parts[1]} ${#parts[@]} ${parts[-1]}",
+        );
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "b 3 c\n");
+
+        let (_, stdout, _) =
+            shell.run_capture("echo '  x  y z  ' | read first rest; echo \"[$first][$rest]\"");
+        assert_eq!(stdout, "[x][y z]\n");
+
+        let (_, stdout, _) =
+            shell.run_capture("echo 'a\\b' | read x; echo $x; echo 'a\\b' | read -r; echo $REPLY");
+        assert_eq!(stdout, "ab\na\\b\n");
+
+        let (_, stdout, _) =
+            shell.run_capture("IFS=,; echo a,,b | read -a parts; echo ${#parts[@]}");
+        assert_eq!(stdout, "3\n");
+    }
+
+    #[test]
+    fn read_nchars_and_timeout() {
+        let mut shell = Shell::new_for_test();
+
+        let (status, stdout, _) = shell.run_capture("echo abcdé | read -n 5 x; echo $x");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "abcdé\n");
+
+        // A newline ends the input before the count.
+        let (_, stdout, _) = shell.run_capture("echo ab | read -n 5 x; echo $? $x");
+        assert_eq!(stdout, "0 ab\n");
+
+        let (_, stdout, _) = shell.run_capture("sleep 0.3 | read -t 0.1 x; echo $?");
+        assert_eq!(stdout, "142\n");
+
+        let (_, stdout, _) = shell.run_capture("echo hi | read -t 1 x; echo $? $x");
+        assert_eq!(stdout, "0 hi\n");
+
+        let (status, _, stderr) = shell.run_capture("read -t soon x");
+        assert_eq!(status, ExitStatus::ExitedWith(2));
+        assert_eq!(stderr, "smash: read: soon: invalid timeout specification\n");
+    }
+}
@@ -0,0 +1,70 @@
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::expand::quote;
+use crate::ExitStatus;
+
+use tracing::debug;
+
+/// `repeat N COMMAND...` runs the command N times.
+pub struct Repeat;
+
+impl BuiltinCommand for Repeat {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("repeat: argv={:?}", ctx.argv);
+        let count = match ctx.argv.get(1).map(|arg| arg.parse::<usize>()) {
+            Some(Ok(count)) => count,
+            Some(Err(_)) => {
+                ctx.error(format_args!("repeat: {}: invalid count", ctx.argv[1]));
+                return ExitStatus::ExitedWith(1);
+            }
+            None => {
+                ctx.error("repeat: usage: repeat N command");
+                return ExitStatus::ExitedWith(1);
+            }
+        };
+
+        if ctx.argv.len() < 3 {
+            ctx.error("repeat: usage: repeat N command");
+            return ExitStatus::ExitedWith(1);
+        }
+
+        // The arguments are already expanded. Quote them not to expand again.
+        let script: Vec<String> = ctx.argv[2..].iter().map(|arg| quote(arg)).collect();
+        let script = script.join(" ");
+
+        let mut status = ExitStatus::ExitedWith(0);
+        for _ in 0..count {
+            let [stdin, stdout, stderr] = ctx.stdio;
+            status = ctx
+                .shell
+                .run_script_with_stdio(&script, stdin, stdout, stderr);
+
+            if status == ExitStatus::Return {
+                break;
+            }
+        }
+
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::Shell;
+    use crate::ExitStatus;
+
+    #[test]
+    fn repeat() {
+        let mut shell = Shell::new_for_test();
+        let (status, stdout, _) = shell.run_capture("repeat 3 echo 'hi $x'");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "hi $x\nhi $x\nhi $x\n");
+
+        let (status, stdout, _) = shell.run_capture("repeat 0 echo hi");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "");
+
+        let (status, _, stderr) = shell.run_capture("repeat x echo hi");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: repeat: x: invalid count\n");
+    }
+}
@@ -0,0 +1,82 @@
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::shell::SHELL_OPTIONS;
+use crate::ExitStatus;
+
+use tracing::debug;
+
+pub struct Set;
+
+impl BuiltinCommand for Set {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("set: argv={:?}", ctx.argv);
+        let mut args = ctx.argv.iter().skip(1);
+        while let Some(arg) = args.next() {
+            let enabled = match arg.as_str() {
+                "-o" => true,
+                "+o" => false,
+                // `set -f` disables pathname expansion.
+                "-f" | "+f" => {
+                    ctx.shell.set_option("noglob", arg == "-f");
+                    continue;
+                }
+                // `set -n` reads commands without running them.
+                "-n" | "+n" => {
+                    ctx.shell.set_option("noexec", arg == "-n");
+                    continue;
+                }
+                _ => {
+                    ctx.error(format_args!("set: unsupported option `{}'", arg));
+                    return ExitStatus::ExitedWith(1);
+                }
+            };
+
+            match args.next() {
+                Some(name) if SHELL_OPTIONS.contains(&name.as_str()) => {
+                    ctx.shell.set_option(name, enabled);
+                }
+                Some(name) => {
+                    ctx.error(format_args!("set: {}: invalid option name", name));
+                    return ExitStatus::ExitedWith(1);
+                }
+                // `set -o`: a human-readable table.
+                None if enabled => {
+                    for name in SHELL_OPTIONS {
+                        let state = if ctx.shell.option(name) { "on" } else { "off" };
+                        writeln!(ctx.stdout, "{:<20}{}", name, state).ok();
+                    }
+                }
+                // `set +o`: commands to restore the current options.
+                None => {
+                    for name in SHELL_OPTIONS {
+                        let flag = if ctx.shell.option(name) { "-o" } else { "+o" };
+                        writeln!(ctx.stdout, "set {} {}", flag, name).ok();
+                    }
+                }
+            }
+        }
+
+        ExitStatus::ExitedWith(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::Shell;
+
+    #[test]
+    fn set_plus_o_is_sourceable() {
+        let mut shell = Shell::new_for_test();
+        shell.set_option("pushd_ignore_dups", true);
+        let (_, snapshot, _) = shell.run_capture("set +o");
+        assert_eq!(
+            snapshot,
+            "set +o auto_pushd\nset +o noexec\nset +o noglob\nset -o pushd_ignore_dups\n"
+        );
+
+        let mut shell = Shell::new_for_test();
+        shell.set_option("auto_pushd", true);
+        shell.run_script(&snapshot);
+        assert!(!shell.option("auto_pushd"));
+        assert!(shell.option("pushd_ignore_dups"));
+    }
+}
@@ -1,24 +1,74 @@
 use super::{BuiltinCommand, BuiltinCommandContext};
 use crate::ExitStatus;
 
-use std::io::Write;
-
 pub struct Source;
 
 impl BuiltinCommand for Source {
     fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
         if let Some(filepath) = ctx.argv.get(1) {
-            match ctx.shell.run_file(std::path::PathBuf::from(&filepath)) {
+            // `source file args...` replaces the positional parameters while
+            // running the file.
+            let saved_args = if ctx.argv.len() > 2 {
+                let args = ctx.argv[2..].to_vec();
+                Some(ctx.shell.current_frame_mut().replace_args(args))
+            } else {
+                None
+            };
+
+            let result = ctx
+                .shell
+                .run_file_with_stdio(std::path::PathBuf::from(&filepath), ctx.stdio);
+            if let Some(args) = saved_args {
+                ctx.shell.current_frame_mut().replace_args(args);
+            }
+
+            match result {
+                // `return` in the file stops it with the given status.
+                Ok(ExitStatus::Return) => ExitStatus::ExitedWith(ctx.shell.last_status()),
                 Ok(status) => status,
                 Err(err) => {
-                    smash_err!("smash: failed open the file: {:?}", err);
+                    ctx.error(format_args!("source: {}: {}", filepath, err));
                     ExitStatus::ExitedWith(1)
                 }
             }
         } else {
-            writeln!(ctx.stderr, "nsh: source: filename argument required").ok();
+            ctx.error("source: filename argument required");
             ctx.stderr.flush().ok();
-            ExitStatus::ExitedWith(0)
+            ExitStatus::ExitedWith(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::{temp_path, Shell};
+    use crate::ExitStatus;
+
+    #[test]
+    fn source_stops_at_return() {
+        let script = temp_path("return.sh");
+        std::fs::write(&script, "export BEFORE=1\nreturn 3\nexport AFTER=1\n").unwrap();
+
+        let mut shell = Shell::new_for_test();
+        let status = shell.run_script(&format!("source {}", script.display()));
+        assert_eq!(status, ExitStatus::ExitedWith(3));
+        assert_eq!(shell.last_status(), 3);
+        assert_eq!(shell.get_str("BEFORE"), Some("1".to_owned()));
+        assert_eq!(shell.get_str("AFTER"), None);
+
+        std::fs::remove_file(&script).unwrap();
+    }
+
+    #[test]
+    fn return_status_wraps_around() {
+        let script = temp_path("wrap.sh");
+        let mut shell = Shell::new_for_test();
+        for (code, expected) in [("256", 0), ("99999999999", 255)] {
+            std::fs::write(&script, format!("return {}\n", code)).unwrap();
+            let status = shell.run_script(&format!("source {}", script.display()));
+            assert_eq!(status, ExitStatus::ExitedWith(expected));
         }
+
+        std::fs::remove_file(&script).unwrap();
     }
 }
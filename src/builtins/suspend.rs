@@ -0,0 +1,40 @@
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::ExitStatus;
+
+use nix::sys::signal::{killpg, Signal};
+use nix::unistd::{getpgrp, getpid, getsid};
+
+/// `suspend [-f]`: stops the shell until it receives `SIGCONT`, e.g. `fg` in
+/// the parent shell.
+pub struct Suspend;
+
+impl BuiltinCommand for Suspend {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        let force = match ctx.argv.get(1).map(String::as_str) {
+            None => false,
+            Some("-f") => true,
+            Some(arg) => {
+                ctx.error(format_args!("suspend: {}: invalid option", arg));
+                return ExitStatus::ExitedWith(2);
+            }
+        };
+
+        // Nobody would resume a login shell or a session leader (e.g. the
+        // shell started by a terminal emulator).
+        let is_login_shell = std::env::args()
+            .next()
+            .is_some_and(|arg0| arg0.starts_with('-'));
+        let is_session_leader = getsid(None).is_ok_and(|sid| sid == getpid());
+        if !force && (is_login_shell || is_session_leader) {
+            ctx.error("suspend: can't suspend a login shell");
+            return ExitStatus::ExitedWith(1);
+        }
+
+        if let Err(err) = killpg(getpgrp(), Signal::SIGSTOP) {
+            ctx.error(format_args!("suspend: {}", err));
+            return ExitStatus::ExitedWith(1);
+        }
+
+        ExitStatus::ExitedWith(0)
+    }
+}
@@ -0,0 +1,110 @@
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::expand::quote;
+use crate::ExitStatus;
+
+use tracing::debug;
+
+/// Pseudo signals supported by `trap`.
+const TRAP_NAMES: &[&str] = &["DEBUG", "ERR"];
+
+/// `trap [-p] [ACTION] [NAME...]`
+pub struct Trap;
+
+impl BuiltinCommand for Trap {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("trap: argv={:?}", ctx.argv);
+        let mut args = &ctx.argv[1..];
+        match args.first().map(String::as_str) {
+            Some("-p") => return print_traps(ctx, &args[1..]),
+            Some("--") => args = &args[1..],
+            _ => {}
+        }
+
+        if args.is_empty() {
+            return print_traps(ctx, &[]);
+        }
+
+        // `trap NAME` resets the trap like `trap - NAME`.
+        let (action, names) = if args.len() == 1 || args[0] == "-" {
+            let skip = usize::from(args[0] == "-");
+            (None, &args[skip..])
+        } else {
+            (Some(args[0].clone()), &args[1..])
+        };
+
+        let mut status = ExitStatus::ExitedWith(0);
+        for name in names {
+            if !TRAP_NAMES.contains(&name.as_str()) {
+                ctx.error(format_args!("trap: {}: not supported yet", name));
+                status = ExitStatus::ExitedWith(1);
+                continue;
+            }
+
+            ctx.shell.set_trap(name, action.clone());
+        }
+
+        status
+    }
+}
+
+/// `trap -p [NAME...]`: prints the traps for `names`, or all of them if
+/// `names` is empty.
+fn print_traps(ctx: &mut BuiltinCommandContext, names: &[String]) -> ExitStatus {
+    let mut traps: Vec<(&String, &String)> = ctx
+        .shell
+        .traps()
+        .filter(|(name, _)| names.is_empty() || names.contains(name))
+        .collect();
+    traps.sort();
+    for (name, command) in traps {
+        writeln!(ctx.stdout, "trap -- {} {}", quote(command), name).ok();
+    }
+
+    let mut status = ExitStatus::ExitedWith(0);
+    for name in names {
+        if !TRAP_NAMES.contains(&name.as_str()) {
+            ctx.error(format_args!("trap: {}: not supported yet", name));
+            status = ExitStatus::ExitedWith(1);
+        }
+    }
+
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::Shell;
+    use crate::ExitStatus;
+
+    #[test]
+    fn trap() {
+        let mut shell = Shell::new_for_test();
+
+        let (_, stdout, _) = shell.run_capture("trap 'echo debug' DEBUG; echo a; echo b");
+        assert_eq!(stdout, "debug\na\ndebug\nb\n");
+        let (_, stdout, _) = shell.run_capture("x=1; $empty; echo $x");
+        assert_eq!(stdout, "debug\ndebug\ndebug\n1\n");
+        shell.run_script("trap - DEBUG");
+
+        // Failures in conditions and in the left side of `&&`/`||` are
+        // ignored. A failure in a function is reported once.
+        let (_, stdout, _) = shell.run_capture(concat!(
+            "trap 'echo err $?' ERR; false; true; false || true;",
+            "if false; then echo x; fi; f() { false; }; f; echo end"
+        ));
+        assert_eq!(stdout, "err 1\nerr 1\nend\n");
+
+        let (_, stdout, _) = shell.run_capture("trap; trap ERR; trap -p; echo reset");
+        assert_eq!(stdout, "trap -- 'echo err $?' ERR\nreset\n");
+
+        // `trap -p NAME` prints only the given traps and keeps them.
+        shell.run_script("trap 'echo err' ERR");
+        let (_, stdout, _) = shell.run_capture("trap -p DEBUG; trap -p ERR DEBUG; trap -p");
+        assert_eq!(stdout, "trap -- 'echo err' ERR\ntrap -- 'echo err' ERR\n");
+        shell.run_script("trap - ERR");
+
+        let (status, _, stderr) = shell.run_capture("trap 'echo int' INT");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: trap: INT: not supported yet\n");
+    }
+}
@@ -0,0 +1,58 @@
+use super::{builtin_command, BuiltinCommand, BuiltinCommandContext};
+use crate::ExitStatus;
+
+use tracing::debug;
+
+/// `type name...`: prints how each name would be run as a command.
+pub struct Type;
+
+impl BuiltinCommand for Type {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("type: argv={:?}", ctx.argv);
+        let mut status = 0;
+        for name in &ctx.argv[1..] {
+            // The same order as the shell looks up a command.
+            if let Some(body) = ctx.shell.lookup_alias(name) {
+                writeln!(ctx.stdout, "{} is aliased to `{}'", name, body).ok();
+            } else if ctx.shell.lookup_function(name).is_some() {
+                writeln!(ctx.stdout, "{} is a function", name).ok();
+            } else if builtin_command(name).is_some() {
+                writeln!(ctx.stdout, "{} is a shell builtin", name).ok();
+            } else if let Some(path) = ctx.shell.path_table().lookup(name) {
+                writeln!(ctx.stdout, "{} is {}", name, path).ok();
+            } else {
+                ctx.error(format_args!("type: {}: not found", name));
+                status = 1;
+            }
+        }
+
+        ExitStatus::ExitedWith(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::Shell;
+    use crate::ExitStatus;
+
+    #[test]
+    fn r#type() {
+        let mut shell = Shell::new_for_test();
+        shell.run_script("alias ll='ls -l'; f() { return 0; }");
+        let sh = shell.path_table().lookup("sh").unwrap().to_owned();
+
+        let (status, stdout, _) = shell.run_capture("type ll f cd sh");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(
+            stdout,
+            format!(
+                "ll is aliased to `ls -l'\nf is a function\ncd is a shell builtin\nsh is {}\n",
+                sh
+            )
+        );
+
+        let (status, _, stderr) = shell.run_capture("type no-such-command");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: type: no-such-command: not found\n");
+    }
+}
@@ -0,0 +1,266 @@
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::ExitStatus;
+
+use tracing::debug;
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+type Resource = libc::c_int;
+
+struct Limit {
+    option: char,
+    resource: Resource,
+    description: &'static str,
+    /// The unit and its size in bytes (or 1 for counts).
+    unit: Option<(&'static str, u64)>,
+}
+
+const LIMITS: &[Limit] = &[
+    Limit {
+        option: 'c',
+        resource: libc::RLIMIT_CORE,
+        description: "core file size",
+        unit: Some(("blocks", 1024)),
+    },
+    Limit {
+        option: 'd',
+        resource: libc::RLIMIT_DATA,
+        description: "data seg size",
+        unit: Some(("kbytes", 1024)),
+    },
+    Limit {
+        option: 'f',
+        resource: libc::RLIMIT_FSIZE,
+        description: "file size",
+        unit: Some(("blocks", 1024)),
+    },
+    Limit {
+        option: 'l',
+        resource: libc::RLIMIT_MEMLOCK,
+        description: "max locked memory",
+        unit: Some(("kbytes", 1024)),
+    },
+    Limit {
+        option: 'm',
+        resource: libc::RLIMIT_RSS,
+        description: "max memory size",
+        unit: Some(("kbytes", 1024)),
+    },
+    Limit {
+        option: 'n',
+        resource: libc::RLIMIT_NOFILE,
+        description: "open files",
+        unit: None,
+    },
+    Limit {
+        option: 's',
+        resource: libc::RLIMIT_STACK,
+        description: "stack size",
+        unit: Some(("kbytes", 1024)),
+    },
+    Limit {
+        option: 't',
+        resource: libc::RLIMIT_CPU,
+        description: "cpu time",
+        unit: Some(("seconds", 1)),
+    },
+    Limit {
+        option: 'u',
+        resource: libc::RLIMIT_NPROC,
+        description: "max user processes",
+        unit: None,
+    },
+    Limit {
+        option: 'v',
+        resource: libc::RLIMIT_AS,
+        description: "virtual memory",
+        unit: Some(("kbytes", 1024)),
+    },
+];
+
+impl Limit {
+    fn get(&self) -> std::io::Result<libc::rlimit> {
+        let mut rlimit: libc::rlimit = unsafe { std::mem::zeroed() };
+        if unsafe { libc::getrlimit(self.resource, &mut rlimit) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(rlimit)
+    }
+
+    fn set(&self, rlimit: &libc::rlimit) -> std::io::Result<()> {
+        if unsafe { libc::setrlimit(self.resource, rlimit) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    fn unit_size(&self) -> u64 {
+        self.unit.map(|(_, size)| size).unwrap_or(1)
+    }
+
+    fn format(&self, value: libc::rlim_t) -> String {
+        if value == libc::RLIM_INFINITY {
+            "unlimited".to_owned()
+        } else {
+            (value / self.unit_size()).to_string()
+        }
+    }
+
+    /// Parses a new limit like `1024` or `unlimited`.
+    fn parse(&self, value: &str) -> Option<libc::rlim_t> {
+        if value == "unlimited" {
+            return Some(libc::RLIM_INFINITY);
+        }
+
+        let value: u64 = value.parse().ok()?;
+        value.checked_mul(self.unit_size())
+    }
+
+    /// `open files  (-n)` or `stack size  (kbytes, -s)`.
+    fn label(&self) -> String {
+        let flag = match self.unit {
+            Some((unit, _)) => format!("({}, -{})", unit, self.option),
+            None => format!("(-{})", self.option),
+        };
+
+        format!("{:<24}{:>16}", self.description, flag)
+    }
+}
+
+/// `ulimit [-SHa] [-cdflmnstuv] [LIMIT]`
+pub struct Ulimit;
+
+impl BuiltinCommand for Ulimit {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("ulimit: argv={:?}", ctx.argv);
+        let mut soft = false;
+        let mut hard = false;
+        let mut all = false;
+        let mut limits = Vec::new();
+        let mut value = None;
+        for arg in &ctx.argv[1..] {
+            match arg.strip_prefix('-') {
+                Some(flags) if !flags.is_empty() => {
+                    for flag in flags.chars() {
+                        match flag {
+                            'S' => soft = true,
+                            'H' => hard = true,
+                            'a' => all = true,
+                            _ => match LIMITS.iter().find(|limit| limit.option == flag) {
+                                Some(limit) => limits.push(limit),
+                                None => {
+                                    ctx.error(format_args!("ulimit: -{}: invalid option", flag));
+                                    return ExitStatus::ExitedWith(2);
+                                }
+                            },
+                        }
+                    }
+                }
+                _ if value.is_none() => value = Some(arg.as_str()),
+                _ => {
+                    ctx.error("ulimit: too many arguments");
+                    return ExitStatus::ExitedWith(2);
+                }
+            }
+        }
+
+        if all {
+            limits = LIMITS.iter().collect();
+        } else if limits.is_empty() {
+            // Like other shells, the file size is the default.
+            limits.push(LIMITS.iter().find(|limit| limit.option == 'f').unwrap());
+        }
+
+        if let Some(value) = value {
+            if all || limits.len() > 1 {
+                ctx.error("ulimit: only one limit can be set at a time");
+                return ExitStatus::ExitedWith(2);
+            }
+
+            let limit = limits[0];
+            let new_value = match limit.parse(value) {
+                Some(new_value) => new_value,
+                None => {
+                    ctx.error(format_args!("ulimit: {}: invalid number", value));
+                    return ExitStatus::ExitedWith(1);
+                }
+            };
+
+            // Both the soft and hard limits are changed unless specified.
+            let result = limit.get().and_then(|mut rlimit| {
+                if soft || !hard {
+                    rlimit.rlim_cur = new_value;
+                }
+                if hard || !soft {
+                    rlimit.rlim_max = new_value;
+                }
+                limit.set(&rlimit)
+            });
+
+            if let Err(err) = result {
+                ctx.error(format_args!(
+                    "ulimit: {}: cannot modify limit: {}",
+                    limit.description, err
+                ));
+                return ExitStatus::ExitedWith(1);
+            }
+
+            return ExitStatus::ExitedWith(0);
+        }
+
+        // Soft limits are shown unless `-H` is given.
+        for limit in &limits {
+            let rlimit = match limit.get() {
+                Ok(rlimit) => rlimit,
+                Err(err) => {
+                    ctx.error(format_args!("ulimit: {}: {}", limit.description, err));
+                    return ExitStatus::ExitedWith(1);
+                }
+            };
+
+            let current = if hard && !soft {
+                rlimit.rlim_max
+            } else {
+                rlimit.rlim_cur
+            };
+
+            if limits.len() > 1 {
+                writeln!(ctx.stdout, "{} {}", limit.label(), limit.format(current)).ok();
+            } else {
+                writeln!(ctx.stdout, "{}", limit.format(current)).ok();
+            }
+        }
+
+        ExitStatus::ExitedWith(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::Shell;
+    use crate::ExitStatus;
+
+    #[test]
+    fn ulimit() {
+        let mut shell = Shell::new_for_test();
+
+        let (status, stdout, _) = shell.run_capture("ulimit -n");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert!(stdout.trim_end().parse::<u64>().is_ok() || stdout == "unlimited\n");
+
+        let (_, stdout, _) = shell.run_capture("ulimit -a");
+        assert!(stdout.lines().any(|line| line.starts_with("open files")));
+
+        // Disabling core dumps is always allowed and is inherited by children.
+        let (status, stdout, _) = shell.run_capture("ulimit -S -c 0; ulimit -c; sh -c 'ulimit -c'");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "0\n0\n");
+
+        let (status, _, stderr) = shell.run_capture("ulimit -c lots");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: ulimit: lots: invalid number\n");
+    }
+}
@@ -0,0 +1,65 @@
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::ExitStatus;
+
+use tracing::debug;
+
+/// `unalias [-a] name...`
+pub struct Unalias;
+
+impl BuiltinCommand for Unalias {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("unalias: argv={:?}", ctx.argv);
+        let mut args = &ctx.argv[1..];
+        match args.first().map(String::as_str) {
+            Some("-a") => {
+                ctx.shell.remove_all_aliases();
+                return ExitStatus::ExitedWith(0);
+            }
+            Some("--") => args = &args[1..],
+            Some(arg) if arg.starts_with('-') && arg.len() > 1 => {
+                ctx.error(format_args!("unalias: {}: invalid option", arg));
+                return ExitStatus::ExitedWith(2);
+            }
+            Some(_) => (),
+            None => {
+                ctx.error("unalias: usage: unalias [-a] name [name ...]");
+                return ExitStatus::ExitedWith(2);
+            }
+        }
+
+        let mut status = 0;
+        for name in args {
+            if !ctx.shell.remove_alias(name) {
+                ctx.error(format_args!("unalias: {}: not found", name));
+                status = 1;
+            }
+        }
+
+        ExitStatus::ExitedWith(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::Shell;
+    use crate::ExitStatus;
+
+    #[test]
+    fn unalias() {
+        let mut shell = Shell::new_for_test();
+        shell.run_script("alias ll='ls -l'; alias la='ls -a'; alias l='ls'");
+
+        let (status, _, stderr) = shell.run_capture("unalias ll no-such-alias");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: unalias: no-such-alias: not found\n");
+        assert_eq!(shell.lookup_alias("ll"), None);
+        assert_eq!(shell.lookup_alias("la"), Some("ls -a".to_owned()));
+
+        let (status, _, _) = shell.run_capture("unalias -a");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(shell.aliases().count(), 0);
+
+        let (status, _, _) = shell.run_capture("unalias");
+        assert_eq!(status, ExitStatus::ExitedWith(2));
+    }
+}
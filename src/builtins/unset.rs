@@ -0,0 +1,100 @@
+use super::{BuiltinCommand, BuiltinCommandContext};
+use crate::ExitStatus;
+
+use tracing::debug;
+
+pub struct Unset;
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum Target {
+    /// Variables first, then functions (the default).
+    Any,
+    /// `-v`
+    Variable,
+    /// `-f`
+    Function,
+}
+
+impl BuiltinCommand for Unset {
+    fn run(&self, ctx: &mut BuiltinCommandContext) -> ExitStatus {
+        debug!("unset: argv={:?}", ctx.argv);
+        let mut target = Target::Any;
+        let mut args = ctx.argv.iter().skip(1).peekable();
+        while let Some(arg) = args.peek() {
+            match arg.as_str() {
+                "-v" => target = Target::Variable,
+                "-f" => target = Target::Function,
+                "--" => {
+                    args.next();
+                    break;
+                }
+                _ if arg.starts_with('-') => {
+                    ctx.error(format_args!("unset: {}: invalid option", arg));
+                    return ExitStatus::ExitedWith(1);
+                }
+                _ => break,
+            }
+
+            args.next();
+        }
+
+        let names: Vec<String> = args.cloned().collect();
+        for name in names {
+            match target {
+                Target::Variable => {
+                    ctx.shell.unset(&name);
+                }
+                Target::Function => {
+                    ctx.shell.undefine_function(&name);
+                }
+                Target::Any => {
+                    if !ctx.shell.unset(&name) {
+                        ctx.shell.undefine_function(&name);
+                    }
+                }
+            }
+        }
+
+        ExitStatus::ExitedWith(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::Shell;
+
+    #[test]
+    fn unset_variables_and_functions() {
+        let mut shell = Shell::new_for_test();
+        shell.run_script("x=1; x() { return 0; }");
+        assert_eq!(shell.get_str("x"), Some("1".to_owned()));
+        assert!(shell.lookup_function("x").is_some());
+
+        // Without flags, the variable is removed first.
+        shell.run_script("unset x");
+        assert!(shell.get("x").is_none());
+        assert!(shell.lookup_function("x").is_some());
+        shell.run_script("unset x");
+        assert!(shell.lookup_function("x").is_none());
+
+        shell.run_script("y=1; y() { return 0; }; unset -f y");
+        assert_eq!(shell.get_str("y"), Some("1".to_owned()));
+        assert!(shell.lookup_function("y").is_none());
+
+        shell.run_script("y() { return 0; }; unset -v y");
+        assert!(shell.get("y").is_none());
+        assert!(shell.lookup_function("y").is_some());
+    }
+
+    #[test]
+    fn unset_path_clears_path_table() {
+        let mut shell = Shell::new_for_test();
+        shell.export("PATH");
+
+        shell.run_script("unset PATH");
+        assert!(shell.get("PATH").is_none());
+        assert!(!shell.is_exported("PATH"));
+        assert!(shell.path_table().to_vec().is_empty());
+        assert_eq!(shell.path_table().lookup("sh"), None);
+    }
+}
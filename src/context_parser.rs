@@ -111,6 +111,51 @@ pub struct InputContext {
     pub current_span: Option<usize>,
 }
 
+impl InputContext {
+    /// Returns the text in `current_literal` with quotes and escapes removed,
+    /// i.e. the string the word will be expanded into.
+    pub fn current_text(&self) -> Option<String> {
+        let range = self.current_literal.as_ref()?;
+        let raw: String = self
+            .input
+            .chars()
+            .skip(range.start)
+            .take(range.end - range.start)
+            .collect();
+        Some(unquote(&raw))
+    }
+}
+
+/// Removes quotes and backslashes in the same way as the main parser does.
+fn unquote(raw: &str) -> String {
+    let mut text = String::new();
+    let mut in_quote = None;
+    let mut chars = raw.chars();
+    while let Some(ch) = chars.next() {
+        match (ch, in_quote) {
+            ('"', None) => in_quote = Some(QuoteType::Double),
+            ('\'', None) => in_quote = Some(QuoteType::Single),
+            ('"', Some(QuoteType::Double)) | ('\'', Some(QuoteType::Single)) => in_quote = None,
+            ('\\', None) => text.push(chars.next().unwrap_or('\\')),
+            ('\\', Some(_)) => {
+                // Quoted backslashes are kept except for the ones before
+                // `"`, `` ` ``, and `$` in double quotes.
+                if let Some(escaped) = chars.next() {
+                    if in_quote == Some(QuoteType::Single) || !"\"`$".contains(escaped) {
+                        text.push('\\');
+                    }
+                    text.push(escaped);
+                } else {
+                    text.push('\\');
+                }
+            }
+            (ch, _) => text.push(ch),
+        }
+    }
+
+    text
+}
+
 struct ContextParser {
     state: State,
     input: String,
@@ -371,6 +416,7 @@ impl ContextParser {
     pub fn parse(mut self) -> InputContext {
         let mut words = Vec::new();
         let mut spans = Vec::new();
+        let mut span_ranges = Vec::new();
         let mut current_word = String::new();
         let mut current_word_index = 0;
         let mut current_literal = None;
@@ -388,15 +434,17 @@ impl ContextParser {
 
                     if current_literal.is_none() {
                         match &span {
-                            Span::Literal(lit) | Span::Argv0(lit) if !lit.is_empty() => {
-                                if prev_index <= self.cursor && self.cursor <= self.index {
-                                    if !freeze_words {
-                                        current_word_index = words.len();
-                                    }
-
-                                    current_literal = Some(prev_index..self.index);
-                                    current_span = Some(spans.len());
+                            Span::Literal(lit) | Span::Argv0(lit)
+                                if !lit.is_empty()
+                                    && prev_index <= self.cursor
+                                    && self.cursor <= self.index =>
+                            {
+                                if !freeze_words {
+                                    current_word_index = words.len();
                                 }
+
+                                current_literal = Some(prev_index..self.index);
+                                current_span = Some(spans.len());
                             }
                             _ => (),
                         }
@@ -431,6 +479,7 @@ impl ContextParser {
 
                     if new_state != State::Eof {
                         spans.push(span);
+                        span_ranges.push(prev_index..self.index);
                     }
 
                     new_state
@@ -463,12 +512,24 @@ impl ContextParser {
                     } else {
                         spans.push(Span::Literal("".to_owned()));
                     }
+                    span_ranges.push(self.cursor..self.cursor);
                     current_literal = Some(self.cursor..self.cursor);
                     current_span = Some(spans.len() - 1);
                 }
             }
         }
 
+        // Extend `current_literal` over the surrounding quotes so that
+        // completion replaces the whole quoted string, e.g. `"my dir/`.
+        if let (Some(index), Some(range)) = (current_span, current_literal.as_mut()) {
+            if let Some(Span::QuoteStart(quote)) = index.checked_sub(1).map(|i| &spans[i]) {
+                range.start = span_ranges[index - 1].start;
+                if spans.get(index + 1) == Some(&Span::QuoteEnd(*quote)) {
+                    range.end = span_ranges[index + 1].end;
+                }
+            }
+        }
+
         InputContext {
             words,
             current_word: current_word_index,
@@ -770,4 +831,31 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn quoted_and_escaped_literals() {
+        let input = "ls my\\ di".to_owned();
+        let ctx = parse(&input, input.len());
+        assert_eq!(ctx.current_literal, Some(3..9));
+        assert_eq!(ctx.current_text(), Some("my di".to_owned()));
+
+        let input = "cd \"my dir/".to_owned();
+        let ctx = parse(&input, input.len());
+        assert_eq!(
+            ctx.spans,
+            vec![
+                Span::Argv0("cd".to_owned()),
+                Span::Space(" ".to_owned()),
+                Span::QuoteStart(QuoteType::Double),
+                Span::Literal("my dir/".to_owned()),
+            ]
+        );
+        assert_eq!(ctx.current_literal, Some(3..11));
+        assert_eq!(ctx.current_text(), Some("my dir/".to_owned()));
+
+        let input = "cat 'a\\b c' x".to_owned();
+        let ctx = parse(&input, 6);
+        assert_eq!(ctx.current_literal, Some(4..11));
+        assert_eq!(ctx.current_text(), Some("a\\b c".to_owned()));
+    }
 }
@@ -1,26 +1,42 @@
-use crate::builtins::BuiltinCommandError;
-use crate::expand::{expand_word_into_string, expand_words};
+use crate::builtins::{builtin_command, BuiltinCommandError};
+use crate::expand::{expand_word_into_pattern, expand_word_into_string, expand_words};
+use crate::fd_file::FdFile;
 use crate::parser::{self, Ast, Initializer, RunIf, Term};
+use crate::pattern::match_pattern;
 use crate::process::{
-    run_external_command, run_in_foreground, run_internal_command, wait_child, wait_for_job,
-    Context, ProcessState,
+    interrupted, join_process_group, reap_job_processes, run_external_command, run_in_foreground,
+    run_internal_command, wait_child, wait_for_job, Context, ProcessState,
 };
 use crate::resolve::resolve_alias;
 use crate::shell::Shell;
+use crate::timing::{format_times, CpuTimes, DEFAULT_TIMEFORMAT};
 use crate::variable::Value;
 use crate::ExitStatus;
 
 use nix::unistd::{close, fork, pipe, setpgid, ForkResult, Pid};
+use std::io::Write;
 use std::os::unix::io::RawFd;
+use std::time::Instant;
+use thiserror::Error;
 use tracing::debug;
 
+#[derive(Debug, Error)]
+pub enum EvalError {
+    #[error("{0}: not supported yet")]
+    UnsupportedCommand(&'static str),
+    #[error("unexpected waitpid event: {0}")]
+    UnexpectedWaitStatus(String),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
 pub fn eval(
     shell: &mut Shell,
     ast: &Ast,
     stdin: RawFd,
     stdout: RawFd,
     stderr: RawFd,
-) -> ExitStatus {
+) -> Result<ExitStatus, EvalError> {
     debug!("ast: {:#?}", ast);
     run_terms(shell, &ast.terms, stdin, stdout, stderr)
 }
@@ -31,10 +47,21 @@ pub fn run_terms(
     stdin: RawFd,
     stdout: RawFd,
     stderr: RawFd,
-) -> ExitStatus {
+) -> Result<ExitStatus, EvalError> {
     let mut last_status = ExitStatus::ExitedWith(0);
     for term in terms {
-        for pipeline in &term.pipelines {
+        // `set -n`: the script has been parsed but nothing is run.
+        if shell.noexec() {
+            break;
+        }
+
+        // The interactive shell reaps jobs before printing a prompt.
+        if !shell.interactive() {
+            reap_job_processes(shell);
+        }
+
+        shell.set_current_line(term.line);
+        for (i, pipeline) in term.pipelines.iter().enumerate() {
             match (last_status, &pipeline.run_if) {
                 (ExitStatus::ExitedWith(0), RunIf::Success) => (),
                 (ExitStatus::ExitedWith(_), RunIf::Failure) => (),
@@ -42,6 +69,9 @@ pub fn run_terms(
                 _ => continue,
             }
 
+            let started_at = pipeline.time.then(|| (Instant::now(), CpuTimes::now()));
+
+            shell.set_err_trapped(false);
             last_status = run_pipeline(
                 shell,
                 &term.code,
@@ -50,11 +80,42 @@ pub fn run_terms(
                 stdout,
                 stderr,
                 term.background,
-            );
+            )?;
+
+            if let Some((real, cpu)) = started_at {
+                let format = shell
+                    .get_str("TIMEFORMAT")
+                    .unwrap_or_else(|| DEFAULT_TIMEFORMAT.to_owned());
+                // An empty `$TIMEFORMAT` disables the report.
+                if !format.is_empty() {
+                    let cpu = CpuTimes::now().elapsed_since(&cpu);
+                    let report = format_times(&format, real.elapsed(), &cpu);
+                    writeln!(FdFile::new(stderr), "{}", report).ok();
+                }
+            }
+
+            // Like other shells, a failure in conditions or in the left
+            // side of `&&`/`||` doesn't trigger the `ERR` trap. A failure
+            // in a function triggers it only once.
+            if matches!(last_status, ExitStatus::ExitedWith(status) if status != 0)
+                && i == term.pipelines.len() - 1
+                && !shell.in_condition()
+                && !shell.err_trapped()
+            {
+                shell.run_trap("ERR", stdin, stdout, stderr);
+                shell.set_err_trapped(true);
+            }
+
+            if matches!(
+                last_status,
+                ExitStatus::Return | ExitStatus::Break(_) | ExitStatus::Continue(_)
+            ) {
+                return Ok(last_status);
+            }
         }
     }
 
-    last_status
+    Ok(last_status)
 }
 
 /// Runs commands in a subshell (`$()` or `<()`).
@@ -62,9 +123,9 @@ pub fn eval_in_subshell(shell: &mut Shell, terms: &[parser::Term]) -> anyhow::Re
     let (pipe_out, pipe_in) = pipe().expect("failed to create a pipe");
 
     let ctx = Context {
-        stdin: 0,
+        stdin: shell.stdio(0),
         stdout: pipe_in,
-        stderr: 2,
+        stderr: shell.stdio(2),
         pgid: None,
         background: false,
         interactive: false,
@@ -81,11 +142,50 @@ fn spawn_subshell(shell: &mut Shell, terms: &[parser::Term], ctx: &Context) -> a
         ForkResult::Parent { child } => Ok(child),
         ForkResult::Child => {
             let status = match run_terms(shell, terms, ctx.stdin, ctx.stdout, ctx.stderr) {
-                ExitStatus::ExitedWith(status) => status,
-                _ => 1,
+                Ok(ExitStatus::ExitedWith(status)) => status,
+                Ok(ExitStatus::Return) => shell.last_status(),
+                Ok(_) => 1,
+                Err(err) => {
+                    writeln!(FdFile::new(ctx.stderr), "{}: {}", shell.error_prefix(), err).ok();
+                    1
+                }
             };
 
-            std::process::exit(status);
+            // Don't run the shell's exit handlers or flush its buffers.
+            unsafe { libc::_exit(status) };
+        }
+    }
+}
+
+/// Forks a subshell which runs a command in background, e.g. a function in
+/// `f &`. The commands in it run in the foreground of the subshell.
+fn spawn_background<F>(shell: &mut Shell, ctx: &Context, run: F) -> anyhow::Result<ExitStatus>
+where
+    F: FnOnce(&mut Shell, &Context) -> anyhow::Result<ExitStatus>,
+{
+    match unsafe { fork() }? {
+        ForkResult::Parent { child } => Ok(ExitStatus::Running(child)),
+        ForkResult::Child => {
+            join_process_group(shell, ctx);
+            shell.interactive = false;
+            let ctx = Context {
+                pgid: None,
+                background: false,
+                interactive: false,
+                ..*ctx
+            };
+
+            let status = match run(shell, &ctx) {
+                Ok(ExitStatus::ExitedWith(status)) => status,
+                Ok(_) => shell.last_status(),
+                Err(err) => {
+                    writeln!(FdFile::new(ctx.stderr), "{}: {}", shell.error_prefix(), err).ok();
+                    1
+                }
+            };
+
+            // Don't run the shell's exit handlers or flush its buffers.
+            unsafe { libc::_exit(status) };
         }
     }
 }
@@ -98,7 +198,7 @@ fn run_pipeline(
     pipeline_stdout: RawFd,
     stderr: RawFd,
     background: bool,
-) -> ExitStatus {
+) -> Result<ExitStatus, EvalError> {
     // Invoke commands in a pipeline.
     let mut last_result = None;
     let mut iter = pipeline.commands.iter().peekable();
@@ -132,6 +232,12 @@ fn run_pipeline(
             },
         );
 
+        // The read end of the previous pipe is used by the command and is no
+        // longer needed in the shell.
+        if stdin != pipeline_stdin {
+            close(stdin).expect("failed to close pipe_out");
+        }
+
         if let Some((pipe_out, pipe_in)) = pipes {
             stdin = pipe_out;
             // `pipe_in` is used by a child process and is no longer needed.
@@ -146,31 +252,50 @@ fn run_pipeline(
                 }
 
                 if shell.interactive {
-                    setpgid(pid, pgid.unwrap()).expect("failed to setpgid");
+                    // The child calls setpgid too. It fails here if the child
+                    // has already exec'd or exited, which is fine.
+                    setpgid(pid, pgid.unwrap()).ok();
                 }
 
                 childs.push(pid);
                 Some(ExitStatus::Running(pid))
             }
             Ok(ExitStatus::ExitedWith(status)) => Some(ExitStatus::ExitedWith(status)),
+            Ok(status @ (ExitStatus::Return | ExitStatus::Break(_) | ExitStatus::Continue(_))) => {
+                Some(status)
+            }
             Err(err) => {
-                unimplemented!("error: {}", err);
+                // Don't abort the whole pipeline: the preceding commands
+                // are already running and need to be waited for.
+                writeln!(FdFile::new(stderr), "{}: {}", shell.error_prefix(), err).ok();
+                Some(ExitStatus::ExitedWith(1))
             }
         };
     }
 
     // Wait for the last command in the pipeline.
-    match last_result {
+    let status = match last_result {
         Some(ExitStatus::ExitedWith(status)) => {
             shell.set_last_status(status);
             ExitStatus::ExitedWith(status)
         }
         Some(ExitStatus::Running(_)) => {
+            let last_child = *childs.last().unwrap();
             let cmd_name = code.to_owned();
             let job = shell.create_job(cmd_name, pgid.unwrap(), childs);
 
-            if !shell.interactive {
-                match wait_for_job(shell, &job) {
+            if background {
+                // Don't wait for the job. It's reaped later (e.g. before
+                // printing the next prompt).
+                shell.set_last_background_pid(last_child);
+                if shell.interactive {
+                    writeln!(FdFile::new(stderr), "[{}] {}", job.id(), job.pgid).ok();
+                }
+
+                shell.set_last_status(0);
+                ExitStatus::ExitedWith(0)
+            } else if !shell.interactive {
+                match wait_for_job(shell, &job)? {
                     ProcessState::Completed(status) => {
                         shell.set_last_status(status);
                         ExitStatus::ExitedWith(status)
@@ -179,33 +304,96 @@ fn run_pipeline(
                     _ => unreachable!(),
                 }
             } else {
-                match run_in_foreground(shell, &job) {
-                    ProcessState::Completed(status) => ExitStatus::ExitedWith(status),
+                match run_in_foreground(shell, &job)? {
+                    ProcessState::Completed(status) => {
+                        shell.set_last_status(status);
+                        ExitStatus::ExitedWith(status)
+                    }
                     ProcessState::Stopped(_) => ExitStatus::Running(pgid.unwrap()),
                     _ => unreachable!(),
                 }
             }
         }
+        // `return`, `break`, or `continue` is the last command in the
+        // pipeline. `$?` has already been set by the command.
+        Some(status @ (ExitStatus::Return | ExitStatus::Break(_) | ExitStatus::Continue(_))) => {
+            status
+        }
         None => {
             debug!("nothing to execute");
             ExitStatus::ExitedWith(0)
         }
-    }
+    };
+
+    Ok(status)
 }
 
 fn run_command(
     shell: &mut Shell,
     command: &parser::Command,
     ctx: &Context,
-) -> anyhow::Result<ExitStatus> {
+) -> Result<ExitStatus, EvalError> {
     debug!("run_command: {:?}", command);
+    // Compound commands run in the shell process. Run them in a subshell so
+    // that the shell doesn't wait for them (e.g. `while true; do ...; done &`).
+    if ctx.background && !matches!(command, parser::Command::SimpleCommand { .. }) {
+        return Ok(spawn_background(shell, ctx, |shell, ctx| {
+            Ok(run_command(shell, command, ctx)?)
+        })?);
+    }
+
     let result = match command {
         parser::Command::SimpleCommand {
             argv,
             redirects,
             assignments,
         } => run_simple_command(shell, ctx, argv, redirects, assignments)?,
-        _ => unimplemented!("command: {:?}", command),
+        parser::Command::Group { terms } => {
+            run_terms(shell, terms, ctx.stdin, ctx.stdout, ctx.stderr)?
+        }
+        parser::Command::If {
+            condition,
+            then_part,
+            elif_parts,
+            else_part,
+        } => run_if_command(shell, ctx, condition, then_part, elif_parts, else_part)?,
+        parser::Command::While {
+            condition,
+            body,
+            until,
+        } => run_while_command(shell, ctx, condition, body, *until)?,
+        parser::Command::Case { word, arms } => run_case_command(shell, ctx, word, arms)?,
+        parser::Command::FunctionDefinition { name, body } => {
+            shell.define_function(name, body.clone());
+            ExitStatus::ExitedWith(0)
+        }
+        parser::Command::Return { status } => {
+            if let Some(status) = status {
+                shell.set_last_status(*status);
+            }
+
+            ExitStatus::Return
+        }
+        parser::Command::Break { count } => {
+            run_loop_control(shell, ctx, "break", *count, ExitStatus::Break)
+        }
+        parser::Command::Continue { count } => {
+            run_loop_control(shell, ctx, "continue", *count, ExitStatus::Continue)
+        }
+        parser::Command::Assignment { assignments } => {
+            shell.run_trap("DEBUG", ctx.stdin, ctx.stdout, ctx.stderr);
+            for assignment in assignments {
+                if assignment.index.is_some() {
+                    return Err(EvalError::UnsupportedCommand("array element assignment"));
+                }
+
+                let value = evaluate_initializer(shell, &assignment.initializer)?;
+                // Updates the local variable if it's visible from the function.
+                shell.set(&assignment.name, value, false);
+            }
+
+            ExitStatus::ExitedWith(0)
+        }
     };
 
     Ok(result)
@@ -218,12 +406,55 @@ fn run_simple_command(
     redirects: &[parser::Redirection],
     assignments: &[parser::Assignment],
 ) -> anyhow::Result<ExitStatus> {
-    let argv = expand_words(shell, &resolve_alias(shell, argv))?;
+    let argv = resolve_alias(shell, argv);
+    let argv = match argv.split_first() {
+        // `noglob cmd *.txt` passes arguments without pathname expansion.
+        Some((first, rest))
+            if !rest.is_empty()
+                && matches!(first.spans(), [parser::Span::Literal(s)] if s == "noglob") =>
+        {
+            let noglob = shell.option("noglob");
+            shell.set_option("noglob", true);
+            let result = expand_words(shell, rest);
+            shell.set_option("noglob", noglob);
+            result?
+        }
+        _ => expand_words(shell, &argv)?,
+    };
+
+    shell.run_trap("DEBUG", ctx.stdin, ctx.stdout, ctx.stderr);
     if argv.is_empty() {
         return Ok(ExitStatus::ExitedWith(0));
     }
 
-    // TODO: support functions
+    // Unlike `$0`, `$_` is the last argument of the previous command.
+    shell.set_last_arg(argv.last().unwrap());
+
+    // Functions and builtins run in the shell process. Like compound
+    // commands, run them in a subshell in background.
+    if ctx.background
+        && (shell.lookup_function(&argv[0]).is_some() || builtin_command(&argv[0]).is_some())
+    {
+        return spawn_background(shell, ctx, |shell, ctx| {
+            run_expanded_command(shell, ctx, argv, redirects, assignments)
+        });
+    }
+
+    run_expanded_command(shell, ctx, argv, redirects, assignments)
+}
+
+/// Runs a function, a builtin, or an external command with expanded `argv`.
+fn run_expanded_command(
+    shell: &mut Shell,
+    ctx: &Context,
+    argv: Vec<String>,
+    redirects: &[parser::Redirection],
+    assignments: &[parser::Assignment],
+) -> anyhow::Result<ExitStatus> {
+    // Functions
+    if let Some(body) = shell.lookup_function(&argv[0]) {
+        return call_function(shell, &argv[0], &body, argv[1..].to_vec(), ctx);
+    }
 
     // Internal commands
     let result = run_internal_command(shell, &argv, ctx.stdin, ctx.stdout, ctx.stderr, redirects);
@@ -240,12 +471,205 @@ fn run_simple_command(
     run_external_command(shell, ctx, argv, redirects, assignments)
 }
 
+/// Runs the condition of `if` or `while`.
+fn run_condition(
+    shell: &mut Shell,
+    ctx: &Context,
+    condition: &[Term],
+) -> Result<ExitStatus, EvalError> {
+    shell.enter_condition();
+    let result = run_terms(shell, condition, ctx.stdin, ctx.stdout, ctx.stderr);
+    shell.leave_condition();
+    result
+}
+
+fn run_if_command(
+    shell: &mut Shell,
+    ctx: &Context,
+    condition: &[Term],
+    then_part: &[Term],
+    elif_parts: &[(Vec<Term>, Vec<Term>)],
+    else_part: &Option<Vec<Term>>,
+) -> Result<ExitStatus, EvalError> {
+    let branches = std::iter::once((condition, then_part)).chain(
+        elif_parts
+            .iter()
+            .map(|(condition, then_part)| (condition.as_slice(), then_part.as_slice())),
+    );
+
+    for (condition, then_part) in branches {
+        match run_condition(shell, ctx, condition)? {
+            ExitStatus::ExitedWith(0) => {
+                return run_terms(shell, then_part, ctx.stdin, ctx.stdout, ctx.stderr);
+            }
+            ExitStatus::Return => return Ok(ExitStatus::Return),
+            _ => (),
+        }
+    }
+
+    match else_part {
+        Some(else_part) => run_terms(shell, else_part, ctx.stdin, ctx.stdout, ctx.stderr),
+        None => Ok(ExitStatus::ExitedWith(0)),
+    }
+}
+
+fn run_while_command(
+    shell: &mut Shell,
+    ctx: &Context,
+    condition: &[Term],
+    body: &[Term],
+    until: bool,
+) -> Result<ExitStatus, EvalError> {
+    shell.enter_loop();
+    let result = run_while_loop(shell, ctx, condition, body, until);
+    shell.leave_loop();
+    result
+}
+
+fn run_while_loop(
+    shell: &mut Shell,
+    ctx: &Context,
+    condition: &[Term],
+    body: &[Term],
+    until: bool,
+) -> Result<ExitStatus, EvalError> {
+    let mut last_status = ExitStatus::ExitedWith(0);
+    loop {
+        // The interactive shell doesn't die on Ctrl-C. Check it here
+        // since a loop of builtins never gets killed by a signal.
+        if interrupted() {
+            shell.set_last_status(130);
+            return Ok(ExitStatus::ExitedWith(130));
+        }
+
+        let status = match run_condition(shell, ctx, condition)? {
+            // Killed by a signal (e.g. Ctrl-C).
+            ExitStatus::ExitedWith(-1) => break,
+            status @ ExitStatus::ExitedWith(_) => status,
+            status => match exit_loop(shell, status) {
+                Some(status) => return Ok(status),
+                None => continue,
+            },
+        };
+
+        if (status == ExitStatus::ExitedWith(0)) == until {
+            break;
+        }
+
+        last_status = run_terms(shell, body, ctx.stdin, ctx.stdout, ctx.stderr)?;
+        match last_status {
+            ExitStatus::ExitedWith(-1) => break,
+            ExitStatus::Return | ExitStatus::Break(_) | ExitStatus::Continue(_) => {
+                match exit_loop(shell, last_status) {
+                    Some(status) => return Ok(status),
+                    None => last_status = ExitStatus::ExitedWith(0),
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(last_status)
+}
+
+/// Decides what a loop does on `return`, `break N`, or `continue N` in it.
+/// Returns the status to return from the loop, or `None` to go on to the
+/// next iteration.
+fn exit_loop(shell: &Shell, status: ExitStatus) -> Option<ExitStatus> {
+    match status {
+        ExitStatus::Break(1) => Some(ExitStatus::ExitedWith(shell.last_status())),
+        ExitStatus::Break(n) => Some(ExitStatus::Break(n - 1)),
+        ExitStatus::Continue(1) => None,
+        ExitStatus::Continue(n) => Some(ExitStatus::Continue(n - 1)),
+        status => Some(status),
+    }
+}
+
+/// Runs `break N` or `continue N`. Like other shells, `N` larger than the
+/// number of loops exits all of them, and `0` is an error which exits the
+/// innermost loop.
+fn run_loop_control(
+    shell: &mut Shell,
+    ctx: &Context,
+    name: &str,
+    count: usize,
+    exit: fn(usize) -> ExitStatus,
+) -> ExitStatus {
+    let msg = match (shell.loop_depth(), count) {
+        (0, _) => "only meaningful in a loop",
+        (_, 0) => "0: loop count out of range",
+        (depth, count) => {
+            shell.set_last_status(0);
+            return exit(count.min(depth));
+        }
+    };
+
+    writeln!(
+        FdFile::new(ctx.stderr),
+        "{}: {}: {}",
+        shell.error_prefix(),
+        name,
+        msg
+    )
+    .ok();
+
+    if shell.loop_depth() == 0 {
+        shell.set_last_status(0);
+        ExitStatus::ExitedWith(0)
+    } else {
+        shell.set_last_status(1);
+        ExitStatus::Break(1)
+    }
+}
+
+/// Runs the first arm whose pattern matches `word`.
+fn run_case_command(
+    shell: &mut Shell,
+    ctx: &Context,
+    word: &parser::Word,
+    arms: &[(Vec<parser::Word>, Vec<Term>)],
+) -> Result<ExitStatus, EvalError> {
+    let word = expand_word_into_string(shell, word)?;
+    for (patterns, body) in arms {
+        for pattern in patterns {
+            if match_pattern(&expand_word_into_pattern(shell, pattern)?, &word) {
+                return run_terms(shell, body, ctx.stdin, ctx.stdout, ctx.stderr);
+            }
+        }
+    }
+
+    Ok(ExitStatus::ExitedWith(0))
+}
+
+/// Calls a function with `args` as its positional parameters.
+pub fn call_function(
+    shell: &mut Shell,
+    name: &str,
+    body: &parser::Command,
+    args: Vec<String>,
+    ctx: &Context,
+) -> anyhow::Result<ExitStatus> {
+    // Like bash, `break` and `continue` in a function don't exit loops in
+    // the caller.
+    let loop_depth = shell.loop_depth();
+    shell.set_loop_depth(0);
+    shell.enter_frame(name, args);
+    let result = run_command(shell, body, ctx);
+    shell.leave_frame();
+    shell.set_loop_depth(loop_depth);
+
+    match result? {
+        ExitStatus::Return => Ok(ExitStatus::ExitedWith(shell.last_status())),
+        status => Ok(status),
+    }
+}
+
 pub fn evaluate_initializer(shell: &mut Shell, initializer: &Initializer) -> anyhow::Result<Value> {
     match initializer {
         Initializer::String(ref word) => Ok(Value::String(expand_word_into_string(shell, word)?)),
         Initializer::Array(ref words) => {
             let elems = expand_words(shell, words)?;
-            match (elems.len(), elems.get(0)) {
+            match (elems.len(), elems.first()) {
                 (1, Some(body)) if body.is_empty() => {
                     // Make `foo=()' an empty array.
                     Ok(Value::Array(vec![]))
@@ -255,3 +679,412 @@ pub fn evaluate_initializer(shell: &mut Shell, initializer: &Initializer) -> any
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::temp_path;
+
+    fn open_fds() -> Vec<String> {
+        let mut fds: Vec<String> = std::fs::read_dir("/proc/self/fd")
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        fds.sort();
+        fds
+    }
+
+    #[test]
+    fn pipelines_do_not_leak_fds() {
+        use nix::sys::wait::{waitpid, WaitStatus};
+
+        // Run pipelines in a forked process: no other test threads exist
+        // there, so the set of open fds must stay exactly the same.
+        match unsafe { fork() }.unwrap() {
+            ForkResult::Child => {
+                let leaked = std::panic::catch_unwind(|| {
+                    let mut shell = Shell::new_for_test();
+                    let before = open_fds();
+                    for _ in 0..100 {
+                        shell.run_script("export A=1 | export B=1 | export C=1");
+                    }
+
+                    open_fds() != before
+                });
+
+                unsafe { libc::_exit(if matches!(leaked, Ok(false)) { 0 } else { 1 }) };
+            }
+            ForkResult::Parent { child } => {
+                assert_eq!(waitpid(child, None).unwrap(), WaitStatus::Exited(child, 0));
+            }
+        }
+    }
+
+    #[test]
+    fn assignments() {
+        let mut shell = Shell::new_for_test();
+        shell.run_script("x=1 y=\"$x two\"; arr=(a b); set_x() { x=2; }; set_x");
+        assert_eq!(shell.get_str("x"), Some("2".to_owned()));
+        assert_eq!(shell.get_str("y"), Some("1 two".to_owned()));
+        assert!(matches!(
+            shell.get("arr").unwrap().value(),
+            Some(Value::Array(elems)) if elems == &["a", "b"]
+        ));
+        assert_eq!(shell.last_status(), 0);
+    }
+
+    #[test]
+    fn assignment_prefixes_do_not_leak() {
+        let mut shell = Shell::new_for_test();
+        let (status, stdout, _) = shell.run_capture("SMASH_PREFIX=1 env | grep SMASH_PREFIX");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "SMASH_PREFIX=1\n");
+        assert!(shell.get("SMASH_PREFIX").is_none());
+    }
+
+    #[test]
+    fn if_command() {
+        let mut shell = Shell::new_for_test();
+        let (_, stdout, _) = shell.run_capture("if true; then echo yes; else echo no; fi");
+        assert_eq!(stdout, "yes\n");
+
+        let script = concat!(
+            "if false; then\n",
+            "    echo one\n",
+            "elif true; then\n",
+            "    echo two\n",
+            "else\n",
+            "    echo other\n",
+            "fi\n",
+        );
+        let (status, stdout, _) = shell.run_capture(script);
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "two\n");
+
+        let (status, stdout, _) = shell.run_capture("if false; then echo yes; fi");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "");
+    }
+
+    #[test]
+    fn while_command() {
+        let mut shell = Shell::new_for_test();
+        let (status, stdout, _) = shell.run_capture("while false; do echo never; done");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "");
+
+        let script = concat!(
+            "x=_\n",
+            "while test $x != xxx_; do\n",
+            "    x=x$x\n",
+            "    echo $x\n",
+            "done\n",
+            "until test $x = _; do x=_; echo until; done\n",
+        );
+        let (status, stdout, _) = shell.run_capture(script);
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "x_\nxx_\nxxx_\nuntil\n");
+    }
+
+    #[test]
+    fn break_and_continue() {
+        let mut shell = Shell::new_for_test();
+        let script = concat!(
+            "i=0\n",
+            "while true; do\n",
+            "    i=$((i+1))\n",
+            "    if test $i = 2; then continue; fi\n",
+            "    j=0\n",
+            "    while true; do\n",
+            "        j=$((j+1))\n",
+            "        if test $j = 2; then continue 2; fi\n",
+            "        if test $i = 4; then break 2; fi\n",
+            "        echo $i $j\n",
+            "    done\n",
+            "done\n",
+            "echo done $i\n",
+        );
+        let (status, stdout, _) = shell.run_capture(script);
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "1 1\n3 1\ndone 4\n");
+
+        // `break` in a function doesn't exit the caller's loop.
+        let script = "f() { break; }; k=0; until test $k = 2; do k=$((k+1)); f; done; echo $k";
+        let (_, stdout, stderr) = shell.run_capture(script);
+        assert_eq!(stdout, "2\n");
+        assert_eq!(
+            stderr,
+            "smash: break: only meaningful in a loop\n".repeat(2)
+        );
+
+        let (status, stdout, stderr) = shell.run_capture("while true; do break 0; done; echo $?");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "1\n");
+        assert_eq!(stderr, "smash: break: 0: loop count out of range\n");
+    }
+
+    #[test]
+    fn pathname_expansion() {
+        let dir = temp_path("noglob");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.txt"), "").unwrap();
+        std::fs::write(dir.join("b.txt"), "").unwrap();
+
+        let mut shell = Shell::new_for_test();
+        let pattern = format!("{}/*.txt", dir.display());
+        let (_, stdout, _) = shell.run_capture(&format!("echo {}", pattern));
+        assert_eq!(stdout, format!("{0}/a.txt {0}/b.txt\n", dir.display()));
+
+        // No matches: the pattern is left as is.
+        let (_, stdout, _) = shell.run_capture(&format!("echo {}/*.md '*'", dir.display()));
+        assert_eq!(stdout, format!("{}/*.md *\n", dir.display()));
+
+        let (_, stdout, _) = shell.run_capture(&format!("set -f; echo {}; set +f", pattern));
+        assert_eq!(stdout, format!("{}\n", pattern));
+        assert!(!shell.option("noglob"));
+
+        let (_, stdout, _) = shell.run_capture(&format!("noglob echo {}", pattern));
+        assert_eq!(stdout, format!("{}\n", pattern));
+        assert!(!shell.option("noglob"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn case_command() {
+        let mut shell = Shell::new_for_test();
+        let script = concat!(
+            "for_case() {\n",
+            "    case $1 in\n",
+            "        start | run) echo start;;\n",
+            "        *.tar.gz) echo tarball;;\n",
+            "        [0-9]) echo digit;;\n",
+            "        '*') echo star;;\n",
+            "        *) echo other;;\n",
+            "    esac\n",
+            "}\n",
+        );
+        shell.run_script(script);
+
+        let (_, stdout, _) =
+            shell.run_capture("for_case run; for_case a.tar.gz; for_case 7; for_case x");
+        assert_eq!(stdout, "start\ntarball\ndigit\nother\n");
+
+        let (status, stdout, _) = shell.run_capture("case '*' in '*') echo star;; esac");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "star\n");
+
+        let (status, stdout, _) = shell.run_capture("case x in y) echo y;; esac");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "");
+    }
+
+    #[test]
+    fn functions() {
+        let mut shell = Shell::new_for_test();
+        shell.run_script("greet() { echo hi $1; }");
+        let (status, stdout, _) = shell.run_capture("greet; greet world");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "hi\nhi world\n");
+
+        // Positional parameters are restored after the call.
+        shell.run_script("outer() { greet inner; echo $1; }");
+        let (_, stdout, _) = shell.run_capture("outer outer");
+        assert_eq!(stdout, "hi inner\nouter\n");
+    }
+
+    #[test]
+    fn unsupported_command_does_not_panic() {
+        let mut shell = Shell::new_for_test();
+        let (status, _, stderr) = shell.run_capture("foo[0]=1");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(
+            stderr,
+            "smash: array element assignment: not supported yet\n"
+        );
+        assert_eq!(shell.last_status(), 1);
+    }
+
+    #[test]
+    fn output_redirection() {
+        let out = temp_path("redirect.txt");
+        std::fs::write(&out, "old contents which should be truncated\n").unwrap();
+
+        let mut shell = Shell::new_for_test();
+        let (status, stdout, _) = shell.run_capture(&format!("echo hello > {}", out.display()));
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "");
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "hello\n");
+
+        let (status, _, stderr) = shell.run_capture("echo hello > /nonexistent/file");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(
+            stderr,
+            "smash: /nonexistent/file: No such file or directory\n"
+        );
+
+        std::fs::remove_file(&out).unwrap();
+    }
+
+    #[test]
+    fn append_redirection() {
+        let out = temp_path("append.txt");
+
+        let mut shell = Shell::new_for_test();
+        let script = format!("echo a > {0}; echo b >> {0}", out.display());
+        let (status, _, _) = shell.run_capture(&script);
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "a\nb\n");
+
+        std::fs::remove_file(&out).unwrap();
+    }
+
+    #[test]
+    fn redirection_target_expansion() {
+        let home_dir = temp_path("home");
+        std::fs::create_dir_all(&home_dir).unwrap();
+
+        let mut shell = Shell::new_for_test();
+        shell.set_string("HOME", home_dir.to_str().unwrap());
+        shell.set_string("OUT", "out.txt");
+        let (status, _, _) = shell.run_capture("echo hello > ~/$OUT");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(
+            std::fs::read_to_string(home_dir.join("out.txt")).unwrap(),
+            "hello\n"
+        );
+        std::fs::remove_dir_all(&home_dir).unwrap();
+
+        shell.set_string("OUT", "a b");
+        let (status, _, stderr) = shell.run_capture("echo hello > $OUT");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: ambiguous redirect\n");
+    }
+
+    #[test]
+    fn fd_redirection() {
+        let mut shell = Shell::new_for_test();
+        let (status, stdout, stderr) =
+            shell.run_capture("sh -c 'echo out; echo err >&2' 2>&1 | cat");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "out\nerr\n");
+        assert_eq!(stderr, "");
+
+        let (status, stdout, stderr) = shell.run_capture("cd /nonexistent 2>&1");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert!(stdout.starts_with("smash: cd: "));
+        assert_eq!(stderr, "");
+    }
+
+    #[test]
+    fn heredoc() {
+        let mut shell = Shell::new_for_test();
+        shell.set_string("name", "world");
+
+        let (status, stdout, _) = shell.run_capture("cat <<EOF\nhello $name\nEOF\n");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "hello world\n");
+
+        let (_, stdout, _) = shell.run_capture("cat <<'EOF'\nhello $name\nEOF");
+        assert_eq!(stdout, "hello $name\n");
+
+        let (_, stdout, _) = shell.run_capture("cat <<-EOF\n\t\thello\n\tEOF\necho done");
+        assert_eq!(stdout, "hello\ndone\n");
+
+        // Only `<<-` allows tabs before the end marker.
+        let (_, stdout, _) = shell.run_capture("cat <<EOF\n\tEOF\nEOF\necho done");
+        assert_eq!(stdout, "\tEOF\ndone\n");
+
+        // A body which doesn't fit in a pipe buffer.
+        let body = "x".repeat(100 * 1024);
+        let (_, stdout, _) = shell.run_capture(&format!("cat <<EOF\n{}\nEOF\n", body));
+        assert_eq!(stdout, format!("{}\n", body));
+    }
+
+    #[test]
+    fn input_redirection() {
+        use std::io::Read;
+        use std::os::unix::io::FromRawFd;
+
+        let input = temp_path("input.txt");
+        std::fs::write(&input, "line 1\nline 2\n").unwrap();
+
+        let mut shell = Shell::new_for_test();
+
+        let (stdout_out, stdout_in) = pipe().unwrap();
+        let script = format!("cat < {}", input.display());
+        let status = shell.run_script_with_stdio(&script, 0, stdout_in, 2);
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        close(stdout_in).unwrap();
+
+        let mut output = String::new();
+        unsafe { std::fs::File::from_raw_fd(stdout_out) }
+            .read_to_string(&mut output)
+            .unwrap();
+        assert_eq!(output, "line 1\nline 2\n");
+
+        let (status, _, stderr) = shell.run_capture("cat < /nonexistent");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(stderr, "smash: /nonexistent: No such file or directory\n");
+
+        std::fs::remove_file(&input).unwrap();
+    }
+
+    #[test]
+    fn positional_parameters() {
+        let mut shell = Shell::new_for_test();
+        shell.run_script("count() { echo $0 $# \"$1\" $*; }");
+        shell.run_script("each() { printf '[%s]' \"$@\"; echo; }");
+        let (_, stdout, _) = shell.run_capture("count a 'b c'; each a 'b c'");
+        assert_eq!(stdout, "smash 2 a a b c\n[a][b c]\n");
+
+        let script = temp_path("args.sh");
+        std::fs::write(&script, "echo $# $2\n").unwrap();
+        let (_, stdout, _) =
+            shell.run_capture(&format!("source {} x y; echo $#", script.display()));
+        assert_eq!(stdout, "2 y\n0\n");
+        std::fs::remove_file(&script).unwrap();
+
+        shell.run_script("join() { IFS=,; echo \"$*\"; }");
+        let (_, stdout, _) = shell.run_capture("join a b c");
+        assert_eq!(stdout, "a,b,c\n");
+    }
+
+    #[test]
+    fn background_job() {
+        let mut shell = Shell::new_for_test();
+
+        let started_at = Instant::now();
+        let status = shell.run_script("sleep 5 &");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert!(started_at.elapsed().as_secs() < 5);
+
+        let pid = shell.last_background_pid().unwrap();
+        assert_eq!(shell.jobs().len(), 1);
+        assert_eq!(shell.jobs().values().next().unwrap().processes, vec![pid]);
+
+        nix::sys::signal::kill(pid, nix::sys::signal::Signal::SIGKILL).unwrap();
+        nix::sys::wait::waitpid(pid, None).ok();
+    }
+
+    #[test]
+    fn background_function() {
+        let mut shell = Shell::new_for_test();
+
+        // They run in subshells: the shell doesn't wait for them and they
+        // don't change the shell's state.
+        let script = "f() { sleep 0.2; echo f; }; f & x=1 & echo \"[$x]\"";
+        let (status, stdout, _) = shell.run_capture(script);
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "[]\nf\n");
+        assert_eq!(shell.jobs().len(), 2);
+
+        // Finished jobs are reaped in a non-interactive shell too.
+        let pid = shell.last_background_pid().unwrap();
+        shell.run_script("sleep 0.1; true");
+        assert_eq!(
+            shell.get_process_state(pid),
+            Some(&ProcessState::Completed(0))
+        );
+    }
+}
@@ -1,5 +1,5 @@
 use crate::context_parser::{self, InputContext};
-use crate::highlight::highlight;
+use crate::highlight::{colors_enabled, highlight};
 use crossterm::cursor;
 use crossterm::event::{Event as TermEvent, KeyCode, KeyEvent, KeyModifiers};
 use crossterm::style::{Attribute, Color, Print, SetAttribute, SetForegroundColor};
@@ -8,6 +8,7 @@ use crossterm::{execute, queue};
 use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
 use signal_hook::{self, iterator::Signals};
 use std::cmp::{max, min};
+use std::collections::BinaryHeap;
 use std::io::Write;
 use std::ops::Range;
 use std::path::PathBuf;
@@ -15,14 +16,30 @@ use std::sync::mpsc;
 use std::time::Duration;
 use tracing::debug;
 
+use crate::builtins::command_names;
 use crate::history::HistorySelector;
-use crate::process::ExitStatus;
+use crate::path::{abbreviate_home, DirReader, FsDirReader};
+use crate::pattern::{has_glob, match_pattern};
+use crate::process::{catch_sigint, reap_jobs, reset_interrupted, ExitStatus};
 use crate::shell::Shell;
 
+/// The default number of completions above which the user is asked before
+/// showing them (`$COMPLETION_QUERY_ITEMS`).
+const DEFAULT_COMPLETION_QUERY_ITEMS: usize = 100;
+
+/// The maximum number of entries listed by `path_completion`.
+const PATH_COMPLETION_MAX: usize = 1000;
+
+/// The maximum number of characters in the user input. Characters beyond it
+/// (e.g. in a huge paste) are dropped.
+const MAX_INPUT_LEN: usize = 64 * 1024;
+
 pub enum Event {
     Input(TermEvent),
     ScreenResized,
-    Completion(Vec<String>),
+    /// A child process has exited or stopped (`SIGCHLD`).
+    ChildChanged,
+    Completion(Completions),
 }
 
 #[derive(Clone, Debug)]
@@ -31,6 +48,7 @@ struct UserInput {
     input: String,
     indices: Vec<usize>,
     word_split: &'static str,
+    max_len: usize,
 }
 
 fn truncate(s: &str, len: usize) -> String {
@@ -44,6 +62,7 @@ impl UserInput {
             input: String::with_capacity(256),
             indices: Vec::with_capacity(256),
             word_split: " \t/",
+            max_len: MAX_INPUT_LEN,
         }
     }
 
@@ -80,30 +99,68 @@ impl UserInput {
     }
 
     pub fn insert(&mut self, ch: char) {
-        self.input.insert(self.byte_index(), ch);
-        self.update_indices();
-        self.cursor += 1;
+        if self.len() >= self.max_len {
+            return;
+        }
+
+        let mut buf = [0; 4];
+        self.insert_str(ch.encode_utf8(&mut buf));
     }
 
     pub fn delete(&mut self) {
         if self.cursor < self.len() {
-            self.input.remove(self.byte_index());
-            self.update_indices();
+            self.remove_at_cursor();
         }
     }
 
     pub fn backspace(&mut self) {
         if self.cursor > 0 {
             self.cursor -= 1;
-            self.input.remove(self.byte_index());
-            self.update_indices();
+            self.remove_at_cursor();
         }
     }
 
     pub fn insert_str(&mut self, string: &str) {
-        self.input.insert_str(self.byte_index(), string);
-        self.update_indices();
-        self.cursor += string.chars().count();
+        // Drop characters exceeding `max_len`.
+        let remaining = self.max_len.saturating_sub(self.len());
+        let string = match string.char_indices().nth(remaining) {
+            Some((end, _)) => &string[..end],
+            None => string,
+        };
+
+        // Update `indices` from the cursor instead of rebuilding the whole
+        // vector; it's slow for a long input.
+        let at = self.byte_index();
+        self.input.insert_str(at, string);
+        for index in &mut self.indices[self.cursor..] {
+            *index += string.len();
+        }
+
+        let new_indices: Vec<usize> = string.char_indices().map(|(i, _)| at + i).collect();
+        let num_chars = new_indices.len();
+        self.indices.splice(self.cursor..self.cursor, new_indices);
+        self.cursor += num_chars;
+        self.debug_assert_indices();
+    }
+
+    fn remove_at_cursor(&mut self) {
+        let ch = self.input.remove(self.byte_index());
+        self.indices.remove(self.cursor);
+        for index in &mut self.indices[self.cursor..] {
+            *index -= ch.len_utf8();
+        }
+
+        self.debug_assert_indices();
+    }
+
+    /// Checks that the incrementally updated `indices` are the same as the
+    /// ones rebuilt from scratch.
+    fn debug_assert_indices(&self) {
+        debug_assert!(self
+            .input
+            .char_indices()
+            .map(|(i, _)| i)
+            .eq(self.indices.iter().copied()));
     }
 
     fn byte_index(&self) -> usize {
@@ -196,6 +253,86 @@ impl UserInput {
     }
 }
 
+/// Where the input is drawn in the terminal. Rows are relative to the row of
+/// the prompt.
+#[derive(Debug, PartialEq, Eq)]
+struct InputLayout {
+    /// The row where the printed input (including the history suggestion) ends.
+    height: usize,
+    cursor_x: usize,
+    cursor_y: usize,
+}
+
+impl InputLayout {
+    /// `printed_len` is the number of characters printed after the prompt and
+    /// `cursor` is the cursor position in them.
+    fn new(prompt_len: usize, printed_len: usize, cursor: usize, columns: usize) -> InputLayout {
+        let columns = max(columns, 1);
+        InputLayout {
+            // The cursor is moved to the next row when the input fills the
+            // last row.
+            height: (prompt_len + printed_len) / columns,
+            cursor_x: (prompt_len + cursor) % columns,
+            cursor_y: (prompt_len + cursor) / columns,
+        }
+    }
+
+    /// The number of rows above the cursor to be cleared on the next redraw.
+    fn clear_above(&self) -> usize {
+        self.cursor_y
+    }
+
+    /// The number of rows below the cursor to be cleared on the next redraw.
+    fn clear_below(&self, completions_height: usize) -> usize {
+        self.height - self.cursor_y + completions_height
+    }
+}
+
+/// The columns of completions. No row exceeds the screen width even if
+/// entries are longer than it.
+struct CompletionLayout {
+    columns: usize,
+    num_columns: usize,
+    column_width: usize,
+}
+
+impl CompletionLayout {
+    fn new(completions: &[String], columns: usize) -> CompletionLayout {
+        let columns = max(columns, 1);
+        // Leave a space between entries.
+        let longest = completions
+            .iter()
+            .map(|comp| comp.chars().count() + 1)
+            .max()
+            .unwrap_or(1);
+        let num_columns = max(1, columns / longest);
+        CompletionLayout {
+            columns,
+            num_columns,
+            column_width: columns / num_columns,
+        }
+    }
+
+    /// Returns the (truncated) entry to be printed and the width of the
+    /// padding after it.
+    fn entry(&self, comp: &str) -> (String, usize) {
+        let entry = truncate(comp, max(self.column_width, 2) - 1);
+        let margin = self.column_width.saturating_sub(entry.chars().count());
+        (entry, margin)
+    }
+
+    /// Returns the footer like ` 123 more ` or ` 123 more (45 not listed) `.
+    fn footer(&self, remaining: usize, omitted: usize) -> String {
+        let footer = if omitted > 0 {
+            format!(" {} more ({} not listed) ", remaining, omitted)
+        } else {
+            format!(" {} more ", remaining)
+        };
+
+        truncate(&footer, self.columns)
+    }
+}
+
 pub struct SmashState {
     columns: usize,
     shell: Shell,
@@ -208,14 +345,25 @@ pub struct SmashState {
     do_complete: bool,
     input_ctx: InputContext,
     completions: Vec<String>,
+    /// The number of completions dropped since there are too many of them.
+    completions_omitted: usize,
     filtered_completions: Vec<String>,
     selected_completion: usize,
     completions_show_from: usize,
     completions_height: usize,
     completions_per_line: usize,
+    /// Whether the completions are history entries (Ctrl-R) instead of words.
+    history_completion: bool,
     lines: usize,
     // history
     history_selector: HistorySelector,
+    /// Job notifications (e.g. `[1] Done  sleep 5`) to be printed before the
+    /// next prompt. They're deferred so as not to be mixed into the output of
+    /// a running command.
+    pending_notifications: Vec<String>,
+    /// Asks the user whether to show that many completions. It reads the
+    /// answer from the terminal and so is replaced in tests.
+    confirm_completions: fn(usize) -> bool,
 }
 
 impl Drop for SmashState {
@@ -238,13 +386,17 @@ impl SmashState {
             do_complete: false,
             input_ctx: context_parser::parse("", 0),
             completions: Vec::new(),
+            completions_omitted: 0,
             filtered_completions: Vec::new(),
             selected_completion: 0,
             completions_show_from: 0,
             completions_height: 0,
             completions_per_line: 0,
+            history_completion: false,
             lines: 0,
             history_selector: HistorySelector::new(),
+            pending_notifications: Vec::new(),
+            confirm_completions: ask_to_display_completions,
         }
     }
 
@@ -252,12 +404,15 @@ impl SmashState {
         let (tx, rx) = mpsc::channel();
         let tx2 = tx.clone();
         std::thread::spawn(move || {
-            let signals = Signals::new(&[signal_hook::SIGWINCH]).unwrap();
+            let signals = Signals::new([signal_hook::SIGWINCH, signal_hook::SIGCHLD]).unwrap();
             for signal in signals {
                 match signal {
                     signal_hook::SIGWINCH => {
                         tx2.send(Event::ScreenResized).ok();
                     }
+                    signal_hook::SIGCHLD => {
+                        tx2.send(Event::ChildChanged).ok();
+                    }
                     _ => {
                         tracing::warn!("unhandled signal: {}", signal);
                     }
@@ -267,21 +422,24 @@ impl SmashState {
             unreachable!();
         });
 
+        self.run_precmd();
         enable_raw_mode().ok();
         self.render_prompt();
 
         let action = SigAction::new(SigHandler::SigIgn, SaFlags::empty(), SigSet::empty());
         unsafe {
-            sigaction(Signal::SIGINT, &action).expect("failed to sigaction");
             sigaction(Signal::SIGQUIT, &action).expect("failed to sigaction");
             sigaction(Signal::SIGTSTP, &action).expect("failed to sigaction");
             sigaction(Signal::SIGTTIN, &action).expect("failed to sigaction");
             sigaction(Signal::SIGTTOU, &action).expect("failed to sigaction");
         }
+        catch_sigint();
 
         loop {
             let mut started_at = None;
 
+            self.notify_completed_jobs();
+
             match crossterm::event::poll(Duration::from_millis(100)) {
                 Ok(true) => loop {
                     if let Ok(ev) = crossterm::event::read() {
@@ -320,15 +478,22 @@ impl SmashState {
                         || argv0.starts_with('.')
                         || argv0.starts_with('~')
                     {
-                        path_completion(argv0, false)
+                        path_completion(&FsDirReader, &argv0, false)
                     } else {
-                        self.shell.path_table().to_vec()
+                        self.shell
+                            .path_table()
+                            .to_vec()
+                            .into_iter()
+                            .filter(|cmd| cmd.starts_with(argv0.as_str()))
+                            .collect::<Vec<_>>()
+                            .into()
                     };
                     tx.send(Event::Completion(comps)).ok();
                 } else {
-                    let pattern = self.current_span_text().unwrap_or("");
-                    let entries = path_completion(pattern, self.input_ctx.words[0] == "cd");
-                    tx.send(Event::Completion(entries)).ok();
+                    let command = self.input_ctx.words.first().map(String::as_str);
+                    let pattern = self.current_span_text().unwrap_or_default();
+                    let comps = self.argument_completion(command.unwrap_or(""), &pattern);
+                    tx.send(Event::Completion(comps)).ok();
                 }
 
                 self.do_complete = false;
@@ -343,11 +508,36 @@ impl SmashState {
         }
     }
 
-    fn current_span_text(&self) -> Option<&str> {
+    /// Returns candidates for an argument of `command` which start with `pattern`.
+    fn argument_completion(&self, command: &str, pattern: &str) -> Completions {
+        let mut entries: Vec<String> = match command {
+            "cd" | "pushd" => return path_completion(&FsDirReader, pattern, true),
+            // Job specs and pids.
+            "kill" => self
+                .shell
+                .jobs()
+                .values()
+                .flat_map(|job| {
+                    let pids = job.processes.iter().map(|pid| pid.to_string());
+                    std::iter::once(format!("%{}", job.id())).chain(pids)
+                })
+                .collect(),
+            "unalias" => self.shell.aliases().map(|(name, _)| name.clone()).collect(),
+            "type" | "which" => command_names(&self.shell),
+            _ => return path_completion(&FsDirReader, pattern, false),
+        };
+
+        entries.retain(|entry| entry.starts_with(pattern));
+        entries.sort();
+        entries.into()
+    }
+
+    /// Returns the unquoted text of the word at the cursor.
+    fn current_span_text(&self) -> Option<String> {
         if let Some(current_span_index) = self.input_ctx.current_span {
             match &self.input_ctx.spans[current_span_index] {
-                context_parser::Span::Literal(literal) | context_parser::Span::Argv0(literal) => {
-                    return Some(literal);
+                context_parser::Span::Literal(_) | context_parser::Span::Argv0(_) => {
+                    return self.input_ctx.current_text();
                 }
                 _ => {}
             };
@@ -357,6 +547,16 @@ impl SmashState {
     }
 
     fn select_completion(&mut self) {
+        if self.history_completion {
+            // Replace the whole input line with the selected history entry.
+            if let Some(selected) = self.filtered_completions.get(self.selected_completion) {
+                self.input.reset(selected.clone());
+            }
+
+            self.clear_completions();
+            return;
+        }
+
         if let Some(current_span) = &self.input_ctx.current_literal {
             if let Some(selected) = self.filtered_completions.get(self.selected_completion) {
                 self.input.replace_range(current_span.clone(), selected);
@@ -397,13 +597,22 @@ impl SmashState {
 
     fn clear_completions(&mut self) {
         self.completions.clear();
+        self.history_completion = false;
     }
 
-    fn update_completion_entries(&mut self, entries: Vec<String>) {
-        self.completions = entries;
+    fn update_completion_entries(&mut self, comps: Completions) {
+        self.completions = comps.entries;
+        self.completions_omitted = comps.omitted;
         self.completions_show_from = 0;
         self.filter_completion_entries();
 
+        let num_comps = self.filtered_completions.len();
+        if num_comps > self.completion_query_items() && !(self.confirm_completions)(num_comps) {
+            self.clear_completions();
+            self.print_user_input();
+            return;
+        }
+
         if self.filtered_completions.len() == 1 {
             self.select_completion();
             self.reparse_input_ctx();
@@ -412,14 +621,36 @@ impl SmashState {
         self.print_user_input();
     }
 
+    fn completion_query_items(&self) -> usize {
+        self.shell
+            .get_int("COMPLETION_QUERY_ITEMS")
+            .and_then(|items| usize::try_from(items).ok())
+            .unwrap_or(DEFAULT_COMPLETION_QUERY_ITEMS)
+    }
+
     fn filter_completion_entries(&mut self) {
+        if self.history_completion {
+            self.filtered_completions = self
+                .completions
+                .iter()
+                .filter(|entry| entry.contains(self.input.as_str()))
+                .cloned()
+                .collect();
+            self.selected_completion = min(
+                self.selected_completion,
+                self.filtered_completions.len().saturating_sub(1),
+            );
+            return;
+        }
+
+        let current_text = self.current_span_text();
         self.filtered_completions = self
             .completions
             .iter()
             .filter(|comp| {
-                self.current_span_text().map_or(false, |text| {
-                    !self.input.is_empty() && comp.starts_with(text)
-                })
+                current_text
+                    .as_ref()
+                    .is_some_and(|text| !self.input.is_empty() && matches_prefix(text, comp))
             })
             .map(|s| s.to_string().replace(' ', "\\ "))
             .collect();
@@ -447,8 +678,19 @@ impl SmashState {
                 self.columns = screen_size.0 as usize;
                 self.lines = screen_size.1 as usize;
             }
+            Event::ChildChanged => {
+                // A background job may have finished while waiting for
+                // input: print the notification above the prompt.
+                self.notify_completed_jobs();
+                if !self.pending_notifications.is_empty() {
+                    let mut stdout = std::io::stdout();
+                    queue!(stdout, Print("\r"), Clear(ClearType::CurrentLine)).ok();
+                    self.render_prompt();
+                    self.print_user_input();
+                }
+            }
             Event::Completion(comps) => {
-                if comps.is_empty() {
+                if comps.entries.is_empty() {
                     debug!("empty completions")
                 } else {
                     debug!(?comps);
@@ -508,15 +750,33 @@ impl SmashState {
             (KeyCode::Tab, KeyModifiers::NONE) => {
                 self.do_complete = true;
             }
+            (KeyCode::Char('r'), KeyModifiers::CONTROL) => {
+                // Show history entries containing the input in the completion menu.
+                let entries = self.shell.history().search(self.input.as_str());
+                if !entries.is_empty() {
+                    self.history_completion = true;
+                    self.selected_completion = 0;
+                    self.update_completion_entries(entries.into());
+                }
+            }
+            // Move between rows in a long input which wraps.
+            (KeyCode::Up, KeyModifiers::NONE) if self.input_layout().cursor_y > 0 => {
+                self.input.move_by(-(self.columns as isize));
+            }
+            (KeyCode::Down, KeyModifiers::NONE)
+                if self.input_layout().cursor_y < self.input_layout().height =>
+            {
+                self.input.move_by(self.columns as isize);
+            }
             // history
-            (KeyCode::Up, KeyModifiers::NONE) => {
+            (KeyCode::Up, KeyModifiers::NONE) | (KeyCode::Char('p'), KeyModifiers::CONTROL) => {
                 self.history_selector
                     .prev(self.shell.history(), self.input.as_str());
                 if let Some(line) = self.history_selector.current(self.shell.history()) {
                     self.input.reset(line);
                 }
             }
-            (KeyCode::Down, KeyModifiers::NONE) => {
+            (KeyCode::Down, KeyModifiers::NONE) | (KeyCode::Char('n'), KeyModifiers::CONTROL) => {
                 self.history_selector.next();
                 debug!(?self.input, "down");
                 if let Some(line) = self.history_selector.current(self.shell.history()) {
@@ -524,11 +784,11 @@ impl SmashState {
                 }
             }
             // cursor
-            (KeyCode::Char('a'), KeyModifiers::CONTROL) => {
+            (KeyCode::Char('a'), KeyModifiers::CONTROL) | (KeyCode::Home, KeyModifiers::NONE) => {
                 self.clear_completions();
                 self.input.move_to_begin();
             }
-            (KeyCode::Char('e'), KeyModifiers::CONTROL) => {
+            (KeyCode::Char('e'), KeyModifiers::CONTROL) | (KeyCode::End, KeyModifiers::NONE) => {
                 self.clear_completions();
                 self.input.move_to_end();
             }
@@ -614,13 +874,26 @@ impl SmashState {
 
         tracing::debug!(?self.columns);
 
+        let colors = colors_enabled(&self.shell);
         let mut stdout = std::io::stdout();
+        for notification in self.pending_notifications.drain(..) {
+            queue!(stdout, Print(notification), Print("\r\n")).ok();
+        }
+
+        if colors {
+            queue!(
+                stdout,
+                SetAttribute(Attribute::Bold),
+                SetAttribute(Attribute::Reverse),
+                Print("$"),
+                SetAttribute(Attribute::Reset),
+            )
+            .ok();
+        } else {
+            queue!(stdout, Print("$")).ok();
+        }
         queue!(
             stdout,
-            SetAttribute(Attribute::Bold),
-            SetAttribute(Attribute::Reverse),
-            Print("$"),
-            SetAttribute(Attribute::Reset),
             Print(&format!(
                 "{space:>width$}\r",
                 space = " ",
@@ -631,26 +904,45 @@ impl SmashState {
 
         let (mut prompt_str, mut prompt_len) = (String::new(), 0);
         if let Ok(current_dir) = std::env::current_dir() {
-            let mut path = current_dir.to_str().unwrap().to_string();
-
-            // "/Users/username/path/to" -> "~/path/to"
-            if let Some(home_dir) = dirs::home_dir() {
-                let home_dir = home_dir.to_str().unwrap();
-                if path.starts_with(&home_dir) {
-                    path = path.replace(home_dir, "~");
-                }
-            }
-
-            prompt_str.push_str(&path);
+            prompt_str.push_str(&abbreviate_home(&current_dir.to_string_lossy()));
         }
-        prompt_str.push_str(" $ ");
         queue!(stdout, Print(prompt_str.replace('\n', "\r\n"))).ok();
-        prompt_len += prompt_str.len();
+        prompt_len += prompt_str.chars().count();
+
+        // Make `$` red if the last command failed.
+        if colors && self.shell.last_status() != 0 {
+            queue!(
+                stdout,
+                SetForegroundColor(Color::Red),
+                Print(" $"),
+                SetAttribute(Attribute::Reset),
+                Print(" ")
+            )
+            .ok();
+        } else {
+            queue!(stdout, Print(" $ ")).ok();
+        }
+        prompt_len += 3;
         stdout.flush().unwrap();
 
         self.prompt_len = prompt_len;
     }
 
+    /// Reaps finished jobs and queues notifications for them. They're printed
+    /// when a fresh prompt is rendered.
+    fn notify_completed_jobs(&mut self) {
+        for job in reap_jobs(&mut self.shell) {
+            self.pending_notifications
+                .push(format!("[{}] Done  {}", job.id(), job.cmd));
+        }
+    }
+
+    /// Runs the `precmd` hook and `$PROMPT_COMMAND` before printing a prompt.
+    fn run_precmd(&mut self) {
+        self.shell.run_hook("precmd", &[]);
+        self.shell.run_prompt_command();
+    }
+
     fn run_command(&mut self) {
         self.history_selector.clear_similary_named_history();
         self.history_selector.reset();
@@ -660,10 +952,22 @@ impl SmashState {
 
         execute!(std::io::stdout(), Print("\r\n")).ok();
         disable_raw_mode().ok();
-        self.shell.run_script(self.input.as_str());
+        // An empty line runs nothing. It just refreshes the prompt and
+        // reports finished jobs below.
+        let empty = self.input.as_str().trim().is_empty();
+        if !empty {
+            reset_interrupted();
+            self.shell
+                .run_hook("preexec", &[self.input.as_str().to_owned()]);
+            self.shell.run_script(self.input.as_str());
+        }
+        self.run_precmd();
         enable_raw_mode().ok();
+        self.notify_completed_jobs();
 
-        self.shell.history_mut().append(self.input.as_str());
+        if !empty {
+            self.shell.history_mut().append(self.input.as_str());
+        }
         self.input.clear();
         self.clear_above = 0;
         self.clear_below = 0;
@@ -703,16 +1007,12 @@ impl SmashState {
             queue!(stdout, cursor::MoveUp(self.clear_below as u16)).ok();
         }
 
+        // Clear the rows of the wrapped input. The row of the prompt is
+        // cleared after the prompt below.
         for _ in 0..self.clear_above {
             queue!(stdout, Clear(ClearType::CurrentLine), cursor::MoveUp(1)).ok();
         }
 
-        if self.clear_above > 0 {
-            // Redraw the prompt since it has been cleared.
-            let (prompt_str, _) = (String::new(), 0);
-            queue!(stdout, Print("\r"), Print(prompt_str.replace('\n', "\r\n"))).ok();
-        }
-
         // Print the highlighted input.
         let h = highlight(&self.input_ctx, &mut self.shell);
         queue!(
@@ -724,10 +1024,18 @@ impl SmashState {
         )
         .ok();
 
-        // Print the first command in history
-        if let Some(history) = self.similary_named_history() {
+        // Print the first command in history. It's hard to distinguish from the
+        // user input without colors.
+        let suggestion = if colors_enabled(&self.shell) {
+            self.similary_named_history()
+        } else {
+            None
+        };
+        let mut suggestion_len = 0;
+        if let Some(history) = suggestion {
             debug!(?history, ?self.input_ctx.input);
             if let Some(suffix) = history.strip_prefix(&self.input_ctx.input) {
+                suggestion_len = suffix.chars().count();
                 queue!(
                     stdout,
                     SetForegroundColor(Color::DarkGrey),
@@ -738,27 +1046,50 @@ impl SmashState {
             }
         }
 
+        let layout = InputLayout::new(
+            self.prompt_len,
+            self.input.len() + suggestion_len,
+            self.input.cursor(),
+            self.columns,
+        );
+
         // Handle the case when the cursor is at the end of a line.
-        let current_x = self.prompt_len + self.input.len();
-        if current_x % self.columns == 0 {
+        if (self.prompt_len + self.input.len() + suggestion_len).is_multiple_of(self.columns) {
             queue!(stdout, Print("\r\n")).ok();
         }
 
-        let input_height = current_x / self.columns;
+        let input_height = layout.height;
 
         let mut completions_height = 0;
-        if self.completion_mode() {
-            // Determine the number of columns and its width of completions.
-            let mut longest = 0;
-            for comp in self.completions.iter() {
-                longest = max(longest, comp.len() + 1);
+        // The number of rows available for completions (including the footer).
+        let completions_height_max = self.lines.saturating_sub(input_height + 1);
+        if self.completion_mode() && completions_height_max < 2 {
+            // The input fills the screen. Show the number of completions
+            // instead of the menu if there's a row left.
+            if completions_height_max == 1 {
+                completions_height = 1;
+                let message = format!(
+                    " {} matches, screen too small ",
+                    self.filtered_completions.len()
+                );
+                queue!(
+                    stdout,
+                    Clear(ClearType::UntilNewLine),
+                    Print("\r\n"),
+                    SetAttribute(Attribute::Reverse),
+                    Print(truncate(&message, self.columns)),
+                    SetAttribute(Attribute::Reset),
+                )
+                .ok();
             }
 
-            let num_columns = max(1, self.columns / longest);
-            let column_width = self.columns / num_columns;
+            self.completions_per_line = 1;
+        } else if self.completion_mode() {
+            // Determine the number of columns and its width of completions.
+            let comp_layout = CompletionLayout::new(&self.completions, self.columns);
+            let num_columns = comp_layout.num_columns;
 
             // Move `self.completions_show_from`.
-            let completions_height_max = self.lines - input_height - 1;
             let num_comps_max = (completions_height_max - 1) * num_columns;
             if self.selected_completion < self.completions_show_from {
                 self.completions_show_from = (self.selected_completion / num_columns) * num_columns;
@@ -785,12 +1116,12 @@ impl SmashState {
                     completions_height += 1;
                 }
 
-                let margin = column_width - min(comp.len(), column_width);
+                let (entry, margin) = comp_layout.entry(comp);
                 if self.completions_show_from + i == self.selected_completion {
                     queue!(
                         stdout,
                         SetAttribute(Attribute::Reverse),
-                        Print(truncate(comp, self.columns)),
+                        Print(entry),
                         SetAttribute(Attribute::NoReverse),
                         cursor::MoveRight(margin as u16),
                     )
@@ -798,7 +1129,7 @@ impl SmashState {
                 } else {
                     queue!(
                         stdout,
-                        Print(truncate(comp, self.columns)),
+                        Print(entry),
                         SetAttribute(Attribute::Reset),
                         cursor::MoveRight(margin as u16)
                     )
@@ -808,16 +1139,14 @@ impl SmashState {
                 remaining -= 1;
             }
 
-            if remaining > 0 {
+            if remaining > 0 || self.completions_omitted > 0 {
                 completions_height += 2;
                 queue!(
                     stdout,
                     Clear(ClearType::UntilNewLine),
                     Print("\r\n"),
                     SetAttribute(Attribute::Reverse),
-                    Print(" "),
-                    Print(remaining),
-                    Print(" more "),
+                    Print(comp_layout.footer(remaining, self.completions_omitted)),
                     SetAttribute(Attribute::Reset),
                 )
                 .ok();
@@ -827,44 +1156,122 @@ impl SmashState {
         }
 
         // Move the cursor to the correct position.
-        let cursor_y = (self.prompt_len + self.input.cursor()) / self.columns;
-        let cursor_x = (self.prompt_len + self.input.cursor()) % self.columns;
-        let cursor_y_diff = (input_height - cursor_y) + completions_height;
+        let cursor_y_diff = layout.clear_below(completions_height);
         if cursor_y_diff > 0 {
             queue!(stdout, cursor::MoveUp(cursor_y_diff as u16),).ok();
         }
 
         queue!(stdout, Print("\r")).ok();
-        if cursor_x > 0 {
-            queue!(stdout, cursor::MoveRight(cursor_x as u16),).ok();
+        if layout.cursor_x > 0 {
+            queue!(stdout, cursor::MoveRight(layout.cursor_x as u16),).ok();
         }
 
         queue!(stdout, cursor::Show).ok();
-        self.clear_above = cursor_y;
-        self.clear_below = input_height - cursor_y + completions_height;
+        self.clear_above = layout.clear_above();
+        self.clear_below = layout.clear_below(completions_height);
         self.completions_height = completions_height;
         stdout.flush().ok();
     }
 
+    /// The layout of the input without the history suggestion.
+    fn input_layout(&self) -> InputLayout {
+        InputLayout::new(
+            self.prompt_len,
+            self.input.len(),
+            self.input.cursor(),
+            self.columns,
+        )
+    }
+
     pub fn similary_named_history(&self) -> Option<String> {
         self.history_selector
             .similary_named_history(self.shell.history())
     }
 }
 
-fn path_completion(pattern: &str, only_dirs: bool) -> Vec<String> {
+/// Returns `true` if `comp` starts with `text`. If `text` contains a glob
+/// (e.g. `src/*.r`), it's matched as a pattern instead.
+fn matches_prefix(text: &str, comp: &str) -> bool {
+    if has_glob(text) {
+        match_pattern(&format!("{}*", text), comp)
+    } else {
+        comp.starts_with(text)
+    }
+}
+
+/// Asks the user whether to show all of `num_comps` completions like bash.
+fn ask_to_display_completions(num_comps: usize) -> bool {
+    let mut stdout = std::io::stdout();
+    queue!(
+        stdout,
+        Print("\r\n"),
+        Print(format!("Display all {} possibilities? (y or n)", num_comps)),
+    )
+    .ok();
+    stdout.flush().ok();
+
+    let answer = loop {
+        match crossterm::event::read() {
+            Ok(TermEvent::Key(key)) => match (key.code, key.modifiers) {
+                (KeyCode::Char('y' | 'Y' | ' '), _) => break true,
+                (KeyCode::Char('n' | 'N'), _)
+                | (KeyCode::Esc, _)
+                | (KeyCode::Char('c'), KeyModifiers::CONTROL) => break false,
+                _ => (),
+            },
+            Ok(_) => (),
+            Err(_) => break false,
+        }
+    };
+
+    queue!(stdout, Clear(ClearType::CurrentLine), cursor::MoveUp(1)).ok();
+    stdout.flush().ok();
+    answer
+}
+
+/// Completion candidates.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Completions {
+    /// Candidates in the sorted order.
+    pub entries: Vec<String>,
+    /// The number of candidates dropped since there are too many of them.
+    pub omitted: usize,
+}
+
+impl From<Vec<String>> for Completions {
+    fn from(entries: Vec<String>) -> Completions {
+        Completions {
+            entries,
+            omitted: 0,
+        }
+    }
+}
+
+/// Returns paths which match `pattern`. In a huge directory, only the first
+/// `PATH_COMPLETION_MAX` ones in the sorted order are returned.
+pub fn path_completion(reader: &dyn DirReader, pattern: &str, only_dirs: bool) -> Completions {
     let home_dir = dirs::home_dir().unwrap();
     let current_dir = std::env::current_dir().unwrap();
-    let mut dir = if pattern.is_empty() {
+
+    // "src/*.r" -> "src/": scan the directory before the glob.
+    let literal_prefix = match pattern.find(['*', '?', '[']) {
+        Some(glob_start) if has_glob(pattern) => match pattern[..glob_start].rfind('/') {
+            Some(slash) => &pattern[..=slash],
+            None => "",
+        },
+        _ => pattern,
+    };
+
+    let mut dir = if literal_prefix.is_empty() {
         current_dir.clone()
-    } else if let Some(pattern) = pattern.strip_prefix('~') {
-        home_dir.join(&pattern.trim_start_matches('/'))
+    } else if let Some(pattern) = literal_prefix.strip_prefix('~') {
+        home_dir.join(pattern.trim_start_matches('/'))
     } else {
-        PathBuf::from(pattern)
+        PathBuf::from(literal_prefix)
     };
 
     // "/usr/loca" -> "/usr"
-    dir = if dir.is_dir() {
+    dir = if reader.is_dir(&dir) {
         dir
     } else {
         dir.pop();
@@ -881,17 +1288,30 @@ fn path_completion(pattern: &str, only_dirs: bool) -> Vec<String> {
         pattern,
         only_dirs
     );
-    match std::fs::read_dir(&dir) {
+    match reader.read_dir(&dir) {
         Ok(files) => {
-            let mut entries = Vec::new();
-            for file in files {
-                let file = file.unwrap();
-                if only_dirs && !file.file_type().unwrap().is_dir() {
+            // Keep the smallest entries so that the result doesn't depend on
+            // the order of readdir(3).
+            let mut entries = BinaryHeap::new();
+            let mut omitted = 0;
+            // Skip entries which can't be read (e.g. removed meanwhile).
+            for file in files.flatten() {
+                let path = file.path;
+                let (prefix, relpath) = if pattern.starts_with('~') {
+                    ("~/", path.strip_prefix(&home_dir).unwrap())
+                } else if pattern.starts_with('/') {
+                    ("/", path.strip_prefix("/").unwrap())
+                } else {
+                    ("", path.strip_prefix(&current_dir).unwrap_or(&path))
+                };
+
+                // Filter entries here instead of collecting everything in a
+                // huge directory; completions are filtered by the prefix anyway.
+                let comp = format!("{}{}", prefix, relpath.to_str().unwrap());
+                if !matches_prefix(pattern, &comp) {
                     continue;
                 }
 
-                let path = file.path();
-
                 // Ignore dotfiles unless the pattern contains ".".
                 if !pattern.starts_with('.') && !pattern.contains("/.") {
                     if let Some(filename) = path.file_name() {
@@ -903,24 +1323,442 @@ fn path_completion(pattern: &str, only_dirs: bool) -> Vec<String> {
                     }
                 }
 
-                let (prefix, relpath) = if pattern.starts_with('~') {
-                    ("~/", path.strip_prefix(&home_dir).unwrap())
-                } else if pattern.starts_with('/') {
-                    ("/", path.strip_prefix("/").unwrap())
-                } else {
-                    ("", path.strip_prefix(&current_dir).unwrap_or(&path))
-                };
+                if only_dirs && !file.is_dir {
+                    continue;
+                }
 
-                let comp = format!("{}{}", prefix, relpath.to_str().unwrap());
                 entries.push(comp);
+                if entries.len() > PATH_COMPLETION_MAX {
+                    entries.pop();
+                    omitted += 1;
+                }
+            }
+
+            if omitted > 0 {
+                debug!(
+                    "path_completion: omitted {} entries in '{}'",
+                    omitted,
+                    dir.display()
+                );
             }
 
-            entries.sort();
-            entries
+            Completions {
+                entries: entries.into_sorted_vec(),
+                omitted,
+            }
         }
         Err(err) => {
             debug!("failed to readdir '{}': {}", dir.display(), err);
-            vec![]
+            Completions::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::{DirEntries, DirEntry};
+    use crate::shell::temp_path;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::Path;
+
+    /// An in-memory directory tree.
+    struct MemDirReader {
+        dirs: HashMap<PathBuf, Vec<DirEntry>>,
+    }
+
+    impl MemDirReader {
+        fn new(paths: &[&str]) -> MemDirReader {
+            let mut dirs: HashMap<PathBuf, Vec<DirEntry>> = HashMap::new();
+            for path in paths {
+                let is_dir = path.ends_with('/');
+                let path = PathBuf::from(path.trim_end_matches('/'));
+                if is_dir {
+                    dirs.entry(path.clone()).or_default();
+                }
+
+                let parent = path.parent().unwrap().to_owned();
+                dirs.entry(parent)
+                    .or_default()
+                    .push(DirEntry { path, is_dir });
+            }
+
+            MemDirReader { dirs }
+        }
+    }
+
+    impl DirReader for MemDirReader {
+        fn read_dir(&self, dir: &Path) -> std::io::Result<DirEntries<'_>> {
+            match self.dirs.get(dir) {
+                Some(entries) => Ok(Box::new(entries.iter().cloned().map(Ok))),
+                None => Err(std::io::Error::from(std::io::ErrorKind::NotFound)),
+            }
+        }
+
+        fn is_dir(&self, path: &Path) -> bool {
+            self.dirs.contains_key(path)
+        }
+    }
+
+    #[test]
+    fn history_completion_replaces_input() {
+        let history = temp_path("hist-comp");
+        fs::write(
+            &history,
+            "1\t/\tgit status\n2\t/\tls -l\n3\t/\tgit log\n4\t/\tgit status\n",
+        )
+        .unwrap();
+
+        let mut state = SmashState::new(Shell::new(&history));
+        state.columns = 80;
+        state.lines = 24;
+        state.input.insert_str("log");
+        state.handle_key_event(&KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL));
+        assert!(!state.completion_mode());
+        assert_eq!(state.input.as_str(), "git log");
+
+        state.input.clear();
+        state.input.insert_str("git");
+        state.handle_key_event(&KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL));
+        assert_eq!(state.filtered_completions, vec!["git status", "git log"]);
+        state.handle_key_event(&KeyEvent::new(KeyCode::Tab, KeyModifiers::NONE));
+        state.handle_key_event(&KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        assert!(!state.completion_mode());
+        assert_eq!(state.input.as_str(), "git log");
+
+        fs::remove_file(&history).unwrap();
+    }
+
+    #[test]
+    fn confirm_many_completions() {
+        let mut shell = Shell::new_for_test();
+        shell.set_string("COMPLETION_QUERY_ITEMS", "2");
+        let mut state = SmashState::new(shell);
+        state.columns = 80;
+        state.lines = 24;
+        let entries = vec!["a".to_owned(), "b".to_owned(), "c".to_owned()];
+
+        state.history_completion = true;
+        state.confirm_completions = |_| false;
+        state.update_completion_entries(entries.clone().into());
+        assert!(!state.completion_mode());
+
+        state.history_completion = true;
+        state.confirm_completions = |num_comps| num_comps == 3;
+        state.update_completion_entries(entries.into());
+        assert_eq!(state.filtered_completions, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn argument_completion_for_known_commands() {
+        let mut shell = Shell::new_for_test();
+        shell.add_alias("gs", "git status".to_owned());
+        shell.add_alias("gl", "git log".to_owned());
+        shell.add_alias("ll", "ls -l".to_owned());
+        let state = SmashState::new(shell);
+
+        assert_eq!(
+            state.argument_completion("unalias", "g").entries,
+            vec!["gl", "gs"]
+        );
+        assert_eq!(
+            state.argument_completion("type", "unal").entries,
+            vec!["unalias"]
+        );
+        assert!(state.argument_completion("kill", "").entries.is_empty());
+    }
+
+    #[test]
+    fn path_completion_filters_by_prefix() {
+        let dir = temp_path("path-comp");
+        fs::create_dir_all(dir.join("apps")).unwrap();
+        for name in &["apple", "apricot", "banana", ".apache"] {
+            fs::File::create(dir.join(name)).unwrap();
         }
+
+        let prefix = dir.to_str().unwrap();
+        assert_eq!(
+            path_completion(&FsDirReader, &format!("{}/ap", prefix), false).entries,
+            vec![
+                format!("{}/apple", prefix),
+                format!("{}/apps", prefix),
+                format!("{}/apricot", prefix),
+            ]
+        );
+        assert_eq!(
+            path_completion(&FsDirReader, &format!("{}/ap", prefix), true).entries,
+            vec![format!("{}/apps", prefix)]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn path_completion_with_virtual_filesystem() {
+        let reader = MemDirReader::new(&[
+            "/virtual/",
+            "/virtual/my dir/",
+            "/virtual/my dir/notes.txt",
+            "/virtual/my file",
+            "/virtual/.hidden",
+        ]);
+
+        assert_eq!(
+            path_completion(&reader, "/virtual/my", false).entries,
+            vec!["/virtual/my dir", "/virtual/my file"]
+        );
+        assert_eq!(
+            path_completion(&reader, "/virtual/my", true).entries,
+            vec!["/virtual/my dir"]
+        );
+        assert_eq!(
+            path_completion(&reader, "/virtual/my dir/", false).entries,
+            vec!["/virtual/my dir/notes.txt"]
+        );
+        assert_eq!(
+            path_completion(&reader, "/virtual/.h", false).entries,
+            vec!["/virtual/.hidden"]
+        );
+        assert!(path_completion(&reader, "/nonexistent/", false)
+            .entries
+            .is_empty());
+
+        // Globs.
+        assert_eq!(
+            path_completion(&reader, "/virtual/*fi", false).entries,
+            vec!["/virtual/my file"]
+        );
+        assert_eq!(
+            path_completion(&reader, "/virtual/my dir/*.t", false).entries,
+            vec!["/virtual/my dir/notes.txt"]
+        );
+        assert_eq!(
+            path_completion(&reader, "/virtual/my?", true).entries,
+            vec!["/virtual/my dir"]
+        );
+    }
+
+    #[test]
+    fn path_completion_in_huge_directory() {
+        let mut paths = vec!["/huge/".to_owned()];
+        // Added in the reverse order to make sure that the result doesn't
+        // depend on the order of entries.
+        for i in (0..PATH_COMPLETION_MAX + 10).rev() {
+            paths.push(format!("/huge/file{:05}", i));
+        }
+        let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+        let reader = MemDirReader::new(&paths);
+
+        let comps = path_completion(&reader, "/huge/f", false);
+        assert_eq!(comps.entries.len(), PATH_COMPLETION_MAX);
+        assert_eq!(comps.entries[0], "/huge/file00000");
+        assert_eq!(comps.omitted, 10);
+
+        // Narrowing down the prefix lists everything.
+        let comps = path_completion(&reader, "/huge/file0100", false);
+        assert_eq!(comps.entries.len(), 10);
+        assert_eq!(comps.omitted, 0);
+    }
+
+    #[test]
+    fn path_completion_skips_unreadable_entries() {
+        struct FlakyDirReader;
+
+        impl DirReader for FlakyDirReader {
+            fn read_dir(&self, _dir: &Path) -> std::io::Result<DirEntries<'_>> {
+                let entry = |path: &str| -> std::io::Result<DirEntry> {
+                    Ok(DirEntry {
+                        path: PathBuf::from(path),
+                        is_dir: false,
+                    })
+                };
+                let entries = vec![
+                    entry("/dir/a"),
+                    Err(std::io::Error::from(std::io::ErrorKind::NotFound)),
+                    entry("/dir/b"),
+                ];
+                Ok(Box::new(entries.into_iter()))
+            }
+
+            fn is_dir(&self, path: &Path) -> bool {
+                path == Path::new("/dir")
+            }
+        }
+
+        let comps = path_completion(&FlakyDirReader, "/dir/", false);
+        assert_eq!(comps.entries, vec!["/dir/a", "/dir/b"]);
+    }
+
+    #[test]
+    fn input_layout_with_wrapped_input() {
+        // "/tmp $ " + 10 characters in 20 columns.
+        let layout = InputLayout::new(7, 10, 10, 20);
+        assert_eq!(
+            layout,
+            InputLayout {
+                height: 0,
+                cursor_x: 17,
+                cursor_y: 0,
+            }
+        );
+        assert_eq!((layout.clear_above(), layout.clear_below(0)), (0, 0));
+
+        // The input fills the first row; the cursor goes to the next row.
+        let layout = InputLayout::new(7, 13, 13, 20);
+        assert_eq!((layout.height, layout.cursor_x, layout.cursor_y), (1, 0, 1));
+        assert_eq!((layout.clear_above(), layout.clear_below(0)), (1, 0));
+
+        // Backspacing at the wrap boundary moves the cursor back to the first row.
+        let layout = InputLayout::new(7, 12, 12, 20);
+        assert_eq!(
+            (layout.height, layout.cursor_x, layout.cursor_y),
+            (0, 19, 0)
+        );
+
+        // The cursor at the beginning of an input which spans three rows.
+        let layout = InputLayout::new(7, 45, 0, 20);
+        assert_eq!((layout.height, layout.cursor_x, layout.cursor_y), (2, 7, 0));
+        assert_eq!((layout.clear_above(), layout.clear_below(3)), (0, 5));
+    }
+
+    #[test]
+    fn deferred_job_notifications() {
+        let mut shell = Shell::new_for_test();
+        shell.run_script("true &");
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        let mut state = SmashState::new(shell);
+        state.notify_completed_jobs();
+        assert_eq!(state.pending_notifications, vec!["[1] Done  true"]);
+        assert!(state.shell.jobs().is_empty());
+    }
+
+    #[test]
+    fn move_between_wrapped_rows() {
+        let mut state = SmashState::new(Shell::new_for_test());
+        state.columns = 20;
+        state.lines = 24;
+        state.prompt_len = 7;
+        state.input.insert_str(&"x".repeat(30));
+        assert_eq!(state.input.cursor(), 30);
+
+        state.handle_key_event(&KeyEvent::new(KeyCode::Up, KeyModifiers::NONE));
+        assert_eq!(state.input.cursor(), 10);
+        state.handle_key_event(&KeyEvent::new(KeyCode::Down, KeyModifiers::NONE));
+        assert_eq!(state.input.cursor(), 30);
+
+        // On the first row, Up selects a history entry instead.
+        state.input.move_to_begin();
+        state.handle_key_event(&KeyEvent::new(KeyCode::Up, KeyModifiers::NONE));
+        assert_eq!(state.input.as_str(), "x".repeat(30));
+
+        state.handle_key_event(&KeyEvent::new(KeyCode::Home, KeyModifiers::NONE));
+        assert_eq!(state.input_layout().cursor_x, 7);
+        state.handle_key_event(&KeyEvent::new(KeyCode::End, KeyModifiers::NONE));
+        assert_eq!(
+            (state.input_layout().cursor_x, state.input_layout().cursor_y),
+            (17, 1)
+        );
+    }
+
+    #[test]
+    fn user_input_editing() {
+        let mut input = UserInput::new();
+        input.insert_str("héllo");
+        input.move_by(-2);
+        input.insert('→');
+        input.insert_str("ü ");
+        assert_eq!(input.as_str(), "hél→ü lo");
+        assert_eq!(input.cursor(), 6);
+
+        input.backspace();
+        input.move_by(-3);
+        input.delete();
+        assert_eq!(input.as_str(), "hé→ülo");
+
+        // The indices are the same as the ones built from scratch.
+        let indices = input.indices.clone();
+        input.update_indices();
+        assert_eq!(input.indices, indices);
+
+        input.max_len = 8;
+        input.move_to_end();
+        input.insert_str("abcdef");
+        input.insert('x');
+        assert_eq!(input.as_str(), "hé→üloab");
+        assert_eq!(input.len(), 8);
+    }
+
+    #[test]
+    fn type_long_line() {
+        // Edit a long line in the middle and compare it with the same edits
+        // done on a plain `String`.
+        let mut input = UserInput::new();
+        let mut expected = Vec::new();
+        for i in 0..5000 {
+            let ch = if i % 2 == 0 { 'a' } else { 'é' };
+            input.insert(ch);
+            expected.push(ch);
+        }
+
+        input.move_by(-2000);
+        for _ in 0..100 {
+            input.backspace();
+        }
+        expected.drain(2900..3000);
+
+        input.insert_str("→ ");
+        expected.splice(2900..2900, "→ ".chars());
+
+        input.move_to_begin();
+        input.delete();
+        expected.remove(0);
+
+        let expected: String = expected.into_iter().collect();
+        assert_eq!(input.as_str(), expected);
+        assert_eq!(input.len(), expected.chars().count());
+        assert_eq!(input.cursor(), 0);
+
+        // Also checked in release builds, where `debug_assert_indices` is
+        // a no-op.
+        let indices = input.indices.clone();
+        input.update_indices();
+        assert_eq!(input.indices, indices);
+    }
+
+    #[test]
+    fn completion_layout_on_narrow_screen() {
+        let completions: Vec<String> = vec![
+            "a_very_long_file_name.txt".to_owned(),
+            "another_long_file_name.rs".to_owned(),
+            "Ωmega_ünïcode_file_name".to_owned(),
+            "x".to_owned(),
+        ];
+
+        for columns in [0, 1, 2, 7, 10, 30, 80] {
+            let layout = CompletionLayout::new(&completions, columns);
+            for row in completions.chunks(layout.num_columns) {
+                let width: usize = row
+                    .iter()
+                    .map(|comp| {
+                        let (entry, margin) = layout.entry(comp);
+                        entry.chars().count() + margin
+                    })
+                    .sum();
+                assert!(width <= max(columns, 1), "columns={}", columns);
+            }
+
+            assert!(layout.footer(12345, 0).chars().count() <= max(columns, 1));
+            assert!(layout.footer(12345, 678).chars().count() <= max(columns, 1));
+        }
+
+        let layout = CompletionLayout::new(&completions, 10);
+        assert_eq!(layout.num_columns, 1);
+        assert_eq!(
+            layout.entry("a_very_long_file_name.txt"),
+            ("a_very_lo".to_owned(), 1)
+        );
+        assert_eq!(layout.entry("x"), ("x".to_owned(), 9));
     }
 }
@@ -1,9 +1,18 @@
+use crate::arith::eval_arith;
 use crate::eval::eval_in_subshell;
+use crate::history::unix_time;
 use crate::parser::ExpansionOp;
+use crate::parser::ReplaceMode;
 use crate::parser::Span;
+use crate::parser::Transformation;
 use crate::parser::Word;
+use crate::path::{abbreviate_home, FsDirReader};
+use crate::pattern::{self, match_pattern};
 use crate::shell::Shell;
+use crate::variable::Value;
 
+use nix::unistd::{gethostname, getpid, getuid, User};
+use std::cmp::min;
 use std::fs::File;
 use std::io::Read;
 use std::os::unix::io::FromRawFd;
@@ -11,10 +20,11 @@ use tracing::debug;
 
 pub fn expand_words(shell: &mut Shell, words: &[Word]) -> anyhow::Result<Vec<String>> {
     debug!("expand_words: {:?}", words);
+    let glob = !shell.option("noglob");
     let mut evaluated = Vec::new();
-    for word in words {
+    for word in words.iter().flat_map(expand_braces) {
         let mut ws = Vec::new();
-        for w in expand_word_into_vec(shell, word, &shell.ifs())? {
+        for w in expand_word_into_vec(shell, &word, &shell.ifs(), glob)? {
             debug!("w: {:?}", w);
             ws.push(w);
         }
@@ -26,34 +36,217 @@ pub fn expand_words(shell: &mut Shell, words: &[Word]) -> anyhow::Result<Vec<Str
     Ok(evaluated)
 }
 
+/// Brace expansion: `a{b,c}d` becomes `abd` and `acd`. It's done before the
+/// other expansions.
+fn expand_braces(word: &Word) -> Vec<Word> {
+    let mut expanded: Vec<Vec<Span>> = vec![Vec::new()];
+    for span in word.spans() {
+        let alternatives: Vec<Word> = match span {
+            Span::Brace { words, .. } => words.iter().flat_map(expand_braces).collect(),
+            Span::BraceRange { from, to, step, .. } => brace_range(from, to, *step)
+                .into_iter()
+                .map(|word| Word(vec![Span::Literal(word)]))
+                .collect(),
+            _ => {
+                for spans in &mut expanded {
+                    spans.push(span.clone());
+                }
+                continue;
+            }
+        };
+
+        expanded = expanded
+            .into_iter()
+            .flat_map(|prefix| {
+                alternatives.iter().map(move |alternative| {
+                    let mut spans = prefix.clone();
+                    spans.extend(alternative.spans().iter().cloned());
+                    spans
+                })
+            })
+            .collect();
+    }
+
+    expanded.into_iter().map(Word).collect()
+}
+
+/// Generates words from `{1..10..2}` or `{a..e}`. Like other shells, numbers
+/// are zero-padded if either end is (e.g. `{01..10}`).
+fn brace_range(from: &str, to: &str, step: u64) -> Vec<String> {
+    let is_padded = |num: &str| {
+        let digits = num.trim_start_matches('-');
+        digits.len() > 1 && digits.starts_with('0')
+    };
+    match (from.parse::<i64>(), to.parse::<i64>()) {
+        (Ok(start), Ok(end)) => {
+            let width = if is_padded(from) || is_padded(to) {
+                from.len().max(to.len())
+            } else {
+                0
+            };
+
+            let nums: Vec<i64> = if start <= end {
+                (start..=end).step_by(step as usize).collect()
+            } else {
+                (end..=start).rev().step_by(step as usize).collect()
+            };
+            nums.iter()
+                .map(|num| format!("{:0width$}", num, width = width))
+                .collect()
+        }
+        _ => {
+            let (start, end) = (from.as_bytes()[0], to.as_bytes()[0]);
+            let chars: Vec<u8> = if start <= end {
+                (start..=end).step_by(step as usize).collect()
+            } else {
+                (end..=start).rev().step_by(step as usize).collect()
+            };
+            chars
+                .into_iter()
+                .map(|ch| char::from(ch).to_string())
+                .collect()
+        }
+    }
+}
+
+/// Expands a word without field splitting, e.g. the right-hand side of an
+/// assignment (`x=$(ls)`).
 pub fn expand_word_into_string(shell: &mut Shell, word: &Word) -> anyhow::Result<String> {
-    let ws: Vec<String> = expand_word_into_vec(shell, word, &shell.ifs())?;
+    let ws: Vec<String> = expand_word_into_vec(shell, word, "", false)?;
     Ok(ws.join(""))
 }
 
+/// Expands the target of a redirection (e.g. `> $LOGFILE` or `> ~/out.txt`).
+/// It's an error if the target is split into multiple words.
+pub fn expand_redirection_target(shell: &mut Shell, word: &Word) -> anyhow::Result<String> {
+    let glob = !shell.option("noglob");
+    let mut words = expand_word_into_vec(shell, word, &shell.ifs(), glob)?;
+    match (words.pop(), words.is_empty()) {
+        (Some(target), true) => Ok(target),
+        _ => anyhow::bail!("ambiguous redirect"),
+    }
+}
+
+/// Expands a word into a pattern (e.g. in `case`). Quoted characters are
+/// escaped so that they match literally.
+pub fn expand_word_into_pattern(shell: &mut Shell, word: &Word) -> anyhow::Result<String> {
+    let mut pattern = String::new();
+    for span in word.spans() {
+        match span {
+            Span::AnyString => pattern.push('*'),
+            Span::AnyChar => pattern.push('?'),
+            Span::Bracket(bracket) => pattern.push_str(bracket),
+            // Like other shells, unquoted parameters are patterns.
+            Span::Parameter { quoted: false, .. } => {
+                pattern.push_str(&expand_word_into_string(shell, &Word(vec![span.clone()]))?);
+            }
+            _ => {
+                let s = expand_word_into_string(shell, &Word(vec![span.clone()]))?;
+                pattern.push_str(&pattern::escape(&s));
+            }
+        }
+    }
+
+    Ok(pattern)
+}
+
+/// A word being expanded.
+#[derive(Default)]
+struct WordBuf {
+    frags: Vec<String>,
+    /// The word as a pattern for pathname expansion. Characters not from
+    /// `*` or `?` spans are escaped.
+    pattern: String,
+    has_glob: bool,
+}
+
+impl WordBuf {
+    fn is_empty(&self) -> bool {
+        self.frags.is_empty()
+    }
+
+    fn push(&mut self, frag: String) {
+        self.pattern.push_str(&pattern::escape(&frag));
+        self.frags.push(frag);
+    }
+
+    fn push_glob(&mut self, glob: &str) {
+        self.pattern.push_str(glob);
+        self.frags.push(glob.to_owned());
+        self.has_glob = true;
+    }
+
+    /// Moves the word into `words`. If `glob` is true, a pattern in it is
+    /// replaced with matching paths (if any).
+    fn flush(&mut self, words: &mut Vec<String>, glob: bool) {
+        let buf = std::mem::take(self);
+        if glob && buf.has_glob {
+            let paths = pattern::glob(&FsDirReader, &buf.pattern);
+            if !paths.is_empty() {
+                words.extend(paths);
+                return;
+            }
+        }
+
+        words.push(buf.frags.concat());
+    }
+}
+
 pub fn expand_word_into_vec(
     shell: &mut Shell,
     word: &Word,
     ifs: &str,
+    glob: bool,
 ) -> anyhow::Result<Vec<String>> {
     let mut words = Vec::new();
-    let mut current_word = Vec::new();
+    let mut current_word = WordBuf::default();
+    // An empty word is removed if it only consists of unquoted expansions
+    // (e.g. `$1` without arguments) like other shells.
+    let mut keep_empty = word.spans().is_empty();
     for span in word.spans() {
         let (frags, expand) = match span {
             Span::LiteralChars(..) => {
                 unreachable!()
             }
             Span::Literal(s) => (vec![s.clone()], false),
-            Span::Parameter { name, op, quoted } => {
+            Span::AnyString => {
+                current_word.push_glob("*");
+                continue;
+            }
+            Span::AnyChar => {
+                current_word.push_glob("?");
+                continue;
+            }
+            Span::Bracket(bracket) => {
+                current_word.push_glob(bracket);
+                continue;
+            }
+            Span::Parameter {
+                name,
+                index,
+                op,
+                quoted,
+            } => {
                 let mut frags = Vec::new();
-                for value in expand_param(shell, name, op)? {
+                for value in expand_param_at(shell, name, index.as_deref(), op)? {
                     let frag = value.unwrap_or_else(|| "".to_owned());
                     frags.push(frag);
                 }
                 (frags, !quoted)
             }
-            Span::Tilde(_) => {
-                let dir = dirs::home_dir().unwrap().to_str().unwrap().to_owned();
+            Span::Tilde(None) => {
+                // Like other shells, `$HOME` is preferred.
+                let dir = shell
+                    .get_str("HOME")
+                    .unwrap_or_else(|| dirs::home_dir().unwrap().to_str().unwrap().to_owned());
+                (vec![dir], false)
+            }
+            Span::Tilde(Some(username)) => {
+                // `~alice` is left as is if the user does not exist.
+                let dir = match User::from_name(username) {
+                    Ok(Some(user)) => user.dir.to_string_lossy().into_owned(),
+                    _ => format!("~{}", username),
+                };
                 (vec![dir], false)
             }
             Span::Command { body, quoted } => {
@@ -63,70 +256,878 @@ pub fn expand_word_into_vec(
                 unsafe { File::from_raw_fd(stdout).read_to_end(&mut raw_stdout).ok() };
 
                 let output = std::str::from_utf8(&raw_stdout)
-                    .map_err(|err| {
-                        smash_err!("binary in variable/expansion is not supported");
-                        err
+                    .inspect_err(|_| {
+                        shell.error("binary in variable/expansion is not supported");
                     })?
                     .trim_end_matches('\n')
                     .to_owned();
 
                 (vec![output], !quoted)
             }
+            Span::Arith(expr) => (vec![eval_arith(shell, expr)?.to_string()], false),
+            // Brace expansion is done in `expand_words`. Elsewhere (e.g. in
+            // assignments), it's left as is like other shells.
+            Span::Brace { source, .. } | Span::BraceRange { source, .. } => {
+                (vec![source.clone()], false)
+            }
         };
 
+        keep_empty |= !expand;
         let frags_len = frags.len();
         for frag in frags {
             if expand {
-                if !current_word.is_empty() {
-                    words.push(current_word.into_iter().collect::<String>());
-                    current_word = Vec::new();
-                }
+                // Split into fields. The first and the last ones are joined
+                // with the adjacent text (e.g. `~/$name`).
+                for (i, field) in frag.split(|c| ifs.contains(c)).enumerate() {
+                    if i > 0 && !current_word.is_empty() {
+                        current_word.flush(&mut words, glob);
+                    }
 
-                for word in frag.split(|c| ifs.contains(c)) {
-                    words.push(word.to_string());
+                    if !field.is_empty() {
+                        current_word.push(field.to_owned());
+                    }
                 }
             } else {
                 current_word.push(frag);
             }
 
             if frags_len > 1 && !current_word.is_empty() {
-                words.push(current_word.into_iter().collect::<String>());
-                current_word = Vec::new();
+                current_word.flush(&mut words, glob);
             }
         }
     }
 
     if !current_word.is_empty() {
-        words.push(current_word.into_iter().collect::<String>());
+        current_word.flush(&mut words, glob);
     }
 
-    if words.is_empty() {
+    if words.is_empty() && keep_empty {
         Ok(vec![String::new()])
     } else {
         Ok(words)
     }
 }
 
-pub fn expand_param(
+/// Expands `${name[index]}` with `op`.
+fn expand_param_at(
     shell: &mut Shell,
     name: &str,
-    _op: &ExpansionOp,
+    index: Option<&str>,
+    op: &ExpansionOp,
 ) -> anyhow::Result<Vec<Option<String>>> {
+    let values = match index {
+        Some(index) => get_elements(shell, name, index)?,
+        None => get_param(shell, name)?,
+    };
+    match op {
+        ExpansionOp::GetOrEmpty => Ok(values),
+        ExpansionOp::Transform(transformation) => Ok(values
+            .into_iter()
+            .map(|value| value.map(|value| transform(shell, &value, *transformation)))
+            .collect()),
+        ExpansionOp::Substring { offset, length } => Ok(values
+            .into_iter()
+            .map(|value| value.map(|value| substring(&value, *offset, *length)))
+            .collect()),
+        ExpansionOp::Length => {
+            let len = match name {
+                "@" | "*" => shell.current_frame().args().len(),
+                _ => values
+                    .iter()
+                    .flatten()
+                    .map(|value| value.chars().count())
+                    .sum(),
+            };
+            Ok(vec![Some(len.to_string())])
+        }
+        ExpansionOp::NumElements => {
+            let len = match shell
+                .get(name)
+                .as_ref()
+                .and_then(|var| var.value().as_ref())
+            {
+                Some(Value::Array(elems)) => elems.len(),
+                Some(Value::String(_)) => 1,
+                None => values.iter().flatten().count(),
+            };
+            Ok(vec![Some(len.to_string())])
+        }
+        ExpansionOp::RemovePrefix { pattern, longest } => {
+            let pattern = expand_word_into_pattern(shell, pattern)?;
+            Ok(values
+                .into_iter()
+                .map(|value| value.map(|value| remove_prefix(&value, &pattern, *longest)))
+                .collect())
+        }
+        ExpansionOp::RemoveSuffix { pattern, longest } => {
+            let pattern = expand_word_into_pattern(shell, pattern)?;
+            Ok(values
+                .into_iter()
+                .map(|value| value.map(|value| remove_suffix(&value, &pattern, *longest)))
+                .collect())
+        }
+        ExpansionOp::Replace {
+            pattern,
+            replacement,
+            mode,
+        } => {
+            let pattern = expand_word_into_pattern(shell, pattern)?;
+            let replacement = expand_word_into_string(shell, replacement)?;
+            Ok(values
+                .into_iter()
+                .map(|value| value.map(|value| replace(&value, &pattern, &replacement, *mode)))
+                .collect())
+        }
+        ExpansionOp::UseDefault { word, check_null } => {
+            if is_unset(&values, *check_null) {
+                Ok(vec![Some(expand_word_into_string(shell, word)?)])
+            } else {
+                Ok(values)
+            }
+        }
+        ExpansionOp::AssignDefault { word, check_null } => {
+            if !is_unset(&values, *check_null) {
+                return Ok(values);
+            }
+
+            // Special and positional parameters (e.g. `$1`) can't be assigned.
+            if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                anyhow::bail!("${}: cannot assign in this way", name);
+            }
+
+            let value = expand_word_into_string(shell, word)?;
+            shell.set(name, Value::String(value.clone()), false);
+            Ok(vec![Some(value)])
+        }
+        ExpansionOp::ErrorIfUnset { word, check_null } => {
+            if !is_unset(&values, *check_null) {
+                return Ok(values);
+            }
+
+            let message = expand_word_into_string(shell, word)?;
+            if !message.is_empty() {
+                anyhow::bail!("{}: {}", name, message);
+            } else if *check_null {
+                anyhow::bail!("{}: parameter null or not set", name);
+            } else {
+                anyhow::bail!("{}: parameter not set", name);
+            }
+        }
+        ExpansionOp::UseAlternative { word, check_null } => {
+            if is_unset(&values, *check_null) {
+                Ok(vec![None])
+            } else {
+                Ok(vec![Some(expand_word_into_string(shell, word)?)])
+            }
+        }
+    }
+}
+
+/// Returns `${value:offset:length}` in characters. A negative `offset` counts
+/// from the end and a negative `length` excludes that many characters from
+/// the end. An out-of-range or empty range results in an empty string.
+fn substring(value: &str, offset: isize, length: Option<isize>) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    let len = chars.len() as isize;
+    let start = if offset < 0 { len + offset } else { offset };
+    if start < 0 || start > len {
+        return String::new();
+    }
+
+    let end = match length {
+        Some(length) if length < 0 => len + length,
+        Some(length) => min(start.saturating_add(length), len),
+        None => len,
+    };
+
+    if end <= start {
+        return String::new();
+    }
+
+    chars[start as usize..end as usize].iter().collect()
+}
+
+/// Returns the byte offsets of the character boundaries in `value`,
+/// including the end of it.
+fn char_boundaries(value: &str) -> Vec<usize> {
+    value
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(value.len()))
+        .collect()
+}
+
+/// `${value#pattern}` and `${value##pattern}`.
+fn remove_prefix(value: &str, pattern: &str, longest: bool) -> String {
+    let mut ends = char_boundaries(value);
+    if longest {
+        ends.reverse();
+    }
+
+    match ends
+        .into_iter()
+        .find(|end| match_pattern(pattern, &value[..*end]))
+    {
+        Some(end) => value[end..].to_owned(),
+        None => value.to_owned(),
+    }
+}
+
+/// `${value%pattern}` and `${value%%pattern}`.
+fn remove_suffix(value: &str, pattern: &str, longest: bool) -> String {
+    let mut starts = char_boundaries(value);
+    if !longest {
+        starts.reverse();
+    }
+
+    match starts
+        .into_iter()
+        .find(|start| match_pattern(pattern, &value[*start..]))
+    {
+        Some(start) => value[..start].to_owned(),
+        None => value.to_owned(),
+    }
+}
+
+/// Returns the separator used to join `$*` and `${arr[*]}`: the first
+/// character of `$IFS`, or a space if it's unset.
+fn join_separator(shell: &Shell) -> String {
+    match shell.get_str("IFS") {
+        Some(ifs) => ifs.chars().next().map(String::from).unwrap_or_default(),
+        None => " ".to_owned(),
+    }
+}
+
+/// Returns `${name[index]}`. A string variable is treated as an array with
+/// one element.
+fn get_elements(shell: &Shell, name: &str, index: &str) -> anyhow::Result<Vec<Option<String>>> {
+    let elems = match shell.get(name).and_then(|var| var.value().clone()) {
+        _ if name == "FUNCNAME" => shell.call_stack(),
+        _ if name == "BASH_SOURCE" => shell.script_name().map(str::to_owned).into_iter().collect(),
+        Some(Value::Array(elems)) => elems,
+        Some(Value::String(value)) => vec![value],
+        None => Vec::new(),
+    };
+
+    match index {
+        "@" => Ok(elems.into_iter().map(Some).collect()),
+        "*" => Ok(vec![Some(elems.join(&join_separator(shell)))]),
+        _ => {
+            // TODO: support arithmetic expressions
+            let index: isize = match index.parse() {
+                Ok(index) => index,
+                Err(_) => anyhow::bail!("{}: bad array subscript", index),
+            };
+
+            // A negative index counts from the end.
+            let index = if index < 0 {
+                elems.len() as isize + index
+            } else {
+                index
+            };
+
+            let elem = usize::try_from(index)
+                .ok()
+                .and_then(|index| elems.get(index).cloned());
+            Ok(vec![elem])
+        }
+    }
+}
+
+/// `${value/pattern/replacement}` and the variants. Like other shells, the
+/// longest match is replaced. An empty pattern matches nothing.
+fn replace(value: &str, pattern: &str, replacement: &str, mode: ReplaceMode) -> String {
+    if pattern.is_empty() {
+        return value.to_owned();
+    }
+
+    let bounds = char_boundaries(value);
+    match mode {
+        ReplaceMode::Prefix => {
+            match bounds
+                .iter()
+                .rev()
+                .find(|end| match_pattern(pattern, &value[..**end]))
+            {
+                Some(end) => format!("{}{}", replacement, &value[*end..]),
+                None => value.to_owned(),
+            }
+        }
+        ReplaceMode::Suffix => {
+            match bounds
+                .iter()
+                .find(|start| match_pattern(pattern, &value[**start..]))
+            {
+                Some(start) => format!("{}{}", &value[..*start], replacement),
+                None => value.to_owned(),
+            }
+        }
+        ReplaceMode::First | ReplaceMode::All => {
+            let mut replaced = String::new();
+            let mut i = 0;
+            while i + 1 < bounds.len() {
+                let start = bounds[i];
+                // The longest non-empty match from `start`.
+                let end = (i + 1..bounds.len())
+                    .rev()
+                    .find(|end| match_pattern(pattern, &value[start..bounds[*end]]));
+
+                match end {
+                    Some(end) => {
+                        replaced.push_str(replacement);
+                        if mode == ReplaceMode::First {
+                            replaced.push_str(&value[bounds[end]..]);
+                            return replaced;
+                        }
+
+                        i = end;
+                    }
+                    None => {
+                        replaced.push_str(&value[start..bounds[i + 1]]);
+                        i += 1;
+                    }
+                }
+            }
+
+            replaced
+        }
+    }
+}
+
+fn get_param(shell: &mut Shell, name: &str) -> anyhow::Result<Vec<Option<String>>> {
     match name {
-        "?" => {
-            return Ok(vec![Some(shell.last_status().to_string())]);
+        "?" => Ok(vec![Some(shell.last_status().to_string())]),
+        "$" => Ok(vec![Some(shell.pid().to_string())]),
+        "!" => {
+            let pid = shell.last_background_pid().map(|pid| pid.to_string());
+            Ok(vec![pid])
+        }
+        "0" => Ok(vec![Some(shell.arg0().to_owned())]),
+        "_" => Ok(vec![Some(shell.last_arg().to_owned())]),
+        "#" => Ok(vec![Some(shell.current_frame().args().len().to_string())]),
+        "@" => {
+            // Each parameter becomes a separate word even in double quotes.
+            let args = shell.current_frame().args();
+            Ok(args.iter().map(|arg| Some(arg.clone())).collect())
+        }
+        "*" => {
+            let joined = shell.current_frame().args().join(&join_separator(shell));
+            Ok(vec![Some(joined)])
+        }
+        _ if name.chars().all(|c| c.is_ascii_digit()) => {
+            // A number too large for usize (e.g. `$99999999999999999999`)
+            // can't refer to an existing parameter.
+            let arg = name
+                .parse()
+                .ok()
+                .and_then(|n| shell.current_frame().get_nth_arg(n));
+            Ok(vec![arg.map(str::to_owned)])
+        }
+        "RANDOM" => Ok(vec![Some(shell.random().to_string())]),
+        "FUNCNAME" => {
+            // `$FUNCNAME` is the current function like `${FUNCNAME[0]}`.
+            Ok(vec![shell.call_stack().into_iter().next()])
+        }
+        "LINENO" => Ok(vec![Some(shell.current_line().to_string())]),
+        "BASH_SOURCE" => Ok(vec![shell.script_name().map(str::to_owned)]),
+        "EPOCHSECONDS" => Ok(vec![Some(unix_time().as_secs().to_string())]),
+        "BASHPID" => {
+            // The pid of the current process, which differs from `$$` in subshells.
+            Ok(vec![Some(getpid().to_string())])
+        }
+        "EPOCHREALTIME" => {
+            let time = unix_time();
+            let realtime = format!("{}.{:06}", time.as_secs(), time.subsec_micros());
+            Ok(vec![Some(realtime)])
         }
-        // TODO: support the other expansion ops
         _ => {
             debug!("{:?}={:?}", name, shell.get(name));
-            if let Some(var) = shell.get(name) {
-                return Ok(vec![Some(var.as_str().to_string())]);
+            // An unset variable is `None`, which is expanded into an empty
+            // string unless the expansion op says otherwise.
+            let value = shell.get(name).map(|var| var.as_str().to_string());
+            Ok(vec![value])
+        }
+    }
+}
+
+/// Returns `true` if the parameter is unset. If `check_null` is true, an
+/// empty value is also regarded as unset.
+fn is_unset(values: &[Option<String>], check_null: bool) -> bool {
+    values.iter().all(|value| match value {
+        Some(value) => check_null && value.is_empty(),
+        None => true,
+    })
+}
+
+fn transform(shell: &Shell, value: &str, transformation: Transformation) -> String {
+    match transformation {
+        Transformation::Quote => quote(value),
+        Transformation::Escape => expand_escapes(value),
+        Transformation::Prompt => expand_prompt(shell, value),
+    }
+}
+
+/// Quotes `s` with single quotes so that it can be reused as input.
+pub fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Expands backslash escape sequences like `$'...'` (e.g. `\n` and `\t`).
+/// Unknown sequences are left as is.
+pub fn expand_escapes(s: &str) -> String {
+    let mut expanded = String::new();
+    let mut chars = s.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            expanded.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some('a') => expanded.push('\x07'),
+            Some('b') => expanded.push('\x08'),
+            Some('e' | 'E') => expanded.push('\x1b'),
+            Some('f') => expanded.push('\x0c'),
+            Some('n') => expanded.push('\n'),
+            Some('r') => expanded.push('\r'),
+            Some('t') => expanded.push('\t'),
+            Some('v') => expanded.push('\x0b'),
+            Some(ch @ ('\\' | '\'' | '"')) => expanded.push(ch),
+            Some(ch) => {
+                expanded.push('\\');
+                expanded.push(ch);
             }
+            None => expanded.push('\\'),
         }
     }
 
-    smash_err!("undefined variable `{}`", name);
-    std::process::exit(1);
+    expanded
+}
+
+/// Expands prompt escape sequences: `\u` (user), `\h` (hostname), `\w` (the
+/// current directory), `\W` (its basename), `\$`, `\n`, and `\\`.
+fn expand_prompt(shell: &Shell, s: &str) -> String {
+    let mut expanded = String::new();
+    let mut chars = s.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            expanded.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some('u') => expanded.push_str(&shell.get_str("USER").unwrap_or_default()),
+            Some('h') => {
+                let mut buf = [0u8; 256];
+                if let Ok(hostname) = gethostname(&mut buf) {
+                    let hostname = hostname.to_string_lossy();
+                    expanded.push_str(hostname.split('.').next().unwrap_or(""));
+                }
+            }
+            Some('w') => {
+                let current_dir = std::env::current_dir().unwrap_or_default();
+                expanded.push_str(&abbreviate_home(&current_dir.to_string_lossy()));
+            }
+            Some('W') => {
+                let current_dir = std::env::current_dir().unwrap_or_default();
+                match current_dir.file_name() {
+                    Some(name) => expanded.push_str(&name.to_string_lossy()),
+                    None => expanded.push_str(&current_dir.to_string_lossy()),
+                }
+            }
+            Some('$') => expanded.push(if getuid().is_root() { '#' } else { '$' }),
+            Some('n') => expanded.push('\n'),
+            Some('\\') => expanded.push('\\'),
+            Some(ch) => {
+                expanded.push('\\');
+                expanded.push(ch);
+            }
+            None => expanded.push('\\'),
+        }
+    }
+
+    expanded
+}
 
-    // TODO: handling errors depending on the expansion op
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::evaluate_initializer;
+    use crate::parser::{self, Initializer};
+    use crate::shell::temp_path;
+    use crate::ExitStatus;
+
+    fn command_span_word(script: &str, quoted: bool) -> Word {
+        let body = parser::parse(script).unwrap().terms;
+        Word(vec![Span::Command { body, quoted }])
+    }
+
+    #[test]
+    fn parameter_transformations() {
+        let mut shell = Shell::new_for_test();
+        shell.set("x", Value::String("it's\\tme".to_owned()), false);
+        shell.set("USER", Value::String("smash".to_owned()), false);
+        shell.set("prompt", Value::String("\\u\\$ ".to_owned()), false);
+
+        let transform = |shell: &mut Shell, name: &str, transformation| {
+            let op = ExpansionOp::Transform(transformation);
+            expand_param_at(shell, name, None, &op).unwrap()
+        };
+        assert_eq!(
+            transform(&mut shell, "x", Transformation::Quote),
+            vec![Some("'it'\\''s\\tme'".to_owned())]
+        );
+        assert_eq!(
+            transform(&mut shell, "x", Transformation::Escape),
+            vec![Some("it's\tme".to_owned())]
+        );
+        let prompt = if getuid().is_root() {
+            "smash# "
+        } else {
+            "smash$ "
+        };
+        assert_eq!(
+            transform(&mut shell, "prompt", Transformation::Prompt),
+            vec![Some(prompt.to_owned())]
+        );
+
+        // `@Q` output can be reused as input.
+        let quoted = transform(&mut shell, "x", Transformation::Quote)[0]
+            .clone()
+            .unwrap();
+        let ast = parser::parse(&format!("echo {} \"${{x@Q}}\"", quoted)).unwrap();
+        let argv = match &ast.terms[0].pipelines[0].commands[0] {
+            parser::Command::SimpleCommand { argv, .. } => argv.clone(),
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            expand_words(&mut shell, &argv).unwrap(),
+            vec!["echo", "it's\\tme", quoted.as_str()]
+        );
+    }
+
+    #[test]
+    fn substring_bounds() {
+        // (offset, length, expected) against "hello".
+        let cases: &[(isize, Option<isize>, &str)] = &[
+            (0, None, "hello"),
+            (2, None, "llo"),
+            (5, None, ""),
+            (6, None, ""),
+            (-2, None, "lo"),
+            (-5, None, "hello"),
+            (-6, None, ""),
+            (0, Some(0), ""),
+            (1, Some(3), "ell"),
+            (1, Some(10), "ello"),
+            (5, Some(1), ""),
+            (-3, Some(2), "ll"),
+            (-3, Some(10), "llo"),
+            (0, Some(-1), "hell"),
+            (1, Some(-1), "ell"),
+            (2, Some(-3), ""),
+            (3, Some(-3), ""),
+            (-4, Some(-1), "ell"),
+            (-1, Some(-1), ""),
+            (0, Some(-5), ""),
+            (0, Some(-6), ""),
+            (isize::MAX, Some(isize::MAX), ""),
+            (isize::MIN, None, ""),
+        ];
+
+        for (offset, length, expected) in cases {
+            assert_eq!(
+                substring("hello", *offset, *length),
+                *expected,
+                "offset={} length={:?}",
+                offset,
+                length
+            );
+        }
+
+        // Multibyte characters are not split.
+        assert_eq!(substring("日本語テキスト", -4, Some(-1)), "テキス");
+    }
+
+    #[test]
+    fn substring_expansion() {
+        let mut shell = Shell::new_for_test();
+        shell.set("x", Value::String("hello".to_owned()), false);
+        let ast = parser::parse("echo ${x:1:3} ${x: -3} ${x:1:-1} ${x::2} ${x:-default}").unwrap();
+        let argv = match &ast.terms[0].pipelines[0].commands[0] {
+            parser::Command::SimpleCommand { argv, .. } => argv.clone(),
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            expand_words(&mut shell, &argv).unwrap(),
+            vec!["echo", "ell", "llo", "ell", "he", "hello"]
+        );
+    }
+
+    #[test]
+    fn default_value_expansions() {
+        let mut shell = Shell::new_for_test();
+        shell.set("empty", Value::String(String::new()), false);
+        shell.set("x", Value::String("set".to_owned()), false);
+
+        let (_, stdout, _) = shell.run_capture(
+            "echo \"${unset:-a b}\" \"${empty:-d}\" \"[${empty-d}]\" \"${x:+alt}\" \"[${unset:+alt}]\"",
+        );
+        assert_eq!(stdout, "a b d [] alt []\n");
+
+        let (_, stdout, _) = shell.run_capture("echo ${y:=assigned}; echo $y");
+        assert_eq!(stdout, "assigned\nassigned\n");
+
+        let (status, stdout, stderr) =
+            shell.run_capture("echo ${unset:?is required}; echo ${unset?}; echo next");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "next\n");
+        assert_eq!(
+            stderr,
+            "smash: unset: is required\nsmash: unset: parameter not set\n"
+        );
+    }
+
+    #[test]
+    fn length_expansion() {
+        let mut shell = Shell::new_for_test();
+        shell.run_script("x=日本語abc; arr=(a bb ccc); count() { echo ${#} ${#@} ${#*}; }");
+        let (_, stdout, _) =
+            shell.run_capture("echo ${#x} ${#undefined} ${#arr[@]} ${#arr[*]} ${#arr}; count a b");
+        assert_eq!(stdout, "6 0 3 3 1\n2 2 2\n");
+    }
+
+    #[test]
+    fn trimming_expansions() {
+        assert_eq!(remove_prefix("a.b.c", "*.", false), "b.c");
+        assert_eq!(remove_prefix("a.b.c", "*.", true), "c");
+        assert_eq!(remove_suffix("a.b.c", ".*", false), "a.b");
+        assert_eq!(remove_suffix("a.b.c", ".*", true), "a");
+        assert_eq!(remove_suffix("日本語", "語", false), "日本");
+        assert_eq!(remove_prefix("abc", "x*", true), "abc");
+
+        let mut shell = Shell::new_for_test();
+        shell.run_script("file=notes.txt.txt; path=/usr/local/bin/smash; star='*'");
+        let (_, stdout, _) = shell.run_capture(concat!(
+            "echo ${file%.txt} ${file%%.*} ${file#*.} ${file##*.};",
+            "echo ${path##*/} ${path%/*} ${path#/*/} ${path%%/l*};",
+            "echo ${file#$star.} ${file#\"$star\"}"
+        ));
+        assert_eq!(
+            stdout,
+            concat!(
+                "notes.txt notes txt.txt txt\n",
+                "smash /usr/local/bin local/bin/smash /usr\n",
+                "txt.txt notes.txt.txt\n"
+            )
+        );
+    }
+
+    #[test]
+    fn replace_expansions() {
+        use ReplaceMode::*;
+        assert_eq!(replace("a-b-c", "-", "+", First), "a+b-c");
+        assert_eq!(replace("a-b-c", "-", "+", All), "a+b+c");
+        assert_eq!(replace("a-b-c", "-", "", All), "abc");
+        assert_eq!(replace("a-b-c", "", "+", All), "a-b-c");
+        assert_eq!(replace("a-b-c", "-*", "", First), "a");
+        assert_eq!(replace("aXbXc", "?X", "_", All), "__c");
+        assert_eq!(replace("abcab", "ab", "_", Prefix), "_cab");
+        assert_eq!(replace("abcab", "ab", "_", Suffix), "abc_");
+        assert_eq!(replace("abcab", "c", "_", Prefix), "abcab");
+        assert_eq!(replace("日本語", "本", "x", First), "日x語");
+
+        let mut shell = Shell::new_for_test();
+        shell.run_script("path=/usr/local/bin");
+        let (_, stdout, _) = shell.run_capture(
+            "echo ${path/local/share} ${path//\\//:} ${path/#\\/usr/X} \"${path//\\// }\"",
+        );
+        assert_eq!(
+            stdout,
+            "/usr/share/bin :usr:local:bin X/local/bin  usr local bin\n"
+        );
+    }
+
+    #[test]
+    fn bashpid_in_subshell() {
+        let mut shell = Shell::new_for_test();
+        let word = command_span_word("echo $$ $BASHPID", false);
+        let output = expand_word_into_string(&mut shell, &word).unwrap();
+        let (pid, bashpid) = output.split_once(' ').unwrap();
+        assert_eq!(pid, std::process::id().to_string());
+        assert_ne!(bashpid, pid);
+
+        // Outside subshells, `$BASHPID` is the same as `$$`.
+        assert_eq!(
+            expand_param_at(&mut shell, "BASHPID", None, &ExpansionOp::GetOrEmpty).unwrap(),
+            vec![Some(std::process::id().to_string())]
+        );
+    }
+
+    #[test]
+    fn special_parameters() {
+        let mut shell = Shell::new_for_test();
+        let mut expand = |name: &str| {
+            expand_param_at(&mut shell, name, None, &ExpansionOp::GetOrEmpty).unwrap()[0]
+                .clone()
+                .unwrap_or_default()
+        };
+
+        assert!(expand("$").parse::<u32>().is_ok());
+        assert_eq!(expand("!"), "");
+        assert_eq!(expand("0"), "smash");
+        assert_eq!(expand("1"), "");
+        assert_eq!(expand("99999999999999999999"), "");
+
+        let randoms: Vec<u16> = (0..8).map(|_| expand("RANDOM").parse().unwrap()).collect();
+        assert!(randoms.iter().all(|n| *n <= 32767));
+        assert!(randoms.iter().any(|n| *n != randoms[0]));
+    }
+
+    #[test]
+    fn brace_expansion() {
+        let mut shell = Shell::new_for_test();
+        let (_, stdout, _) = shell.run_capture(concat!(
+            "echo {a,b}{1,2} {1..3} {1..10..3} {c..a} x{a,{b,c}}y;",
+            "echo {} {a} {05..1..2} {1..a} \"{a,b}\" '{a,b}' \\{a,b\\};",
+            "x={a,b}; echo $x {$x,c}"
+        ));
+        assert_eq!(
+            stdout,
+            concat!(
+                "a1 a2 b1 b2 1 2 3 1 4 7 10 c b a xay xby xcy\n",
+                "{} {a} 05 03 01 {1..a} {a,b} {a,b} {a,b}\n",
+                "{a,b} {a,b} c\n"
+            )
+        );
+    }
+
+    #[test]
+    fn tilde_expansion() {
+        let mut shell = Shell::new_for_test();
+        shell.set_string("HOME", "/home/me");
+        let root = User::from_uid(nix::unistd::Uid::from_raw(0))
+            .unwrap()
+            .unwrap();
+        let script = format!("echo ~ ~/bin ~{} ~no-such-user/bin", root.name);
+        let (_, stdout, _) = shell.run_capture(&script);
+        assert_eq!(
+            stdout,
+            format!(
+                "/home/me /home/me/bin {} ~no-such-user/bin\n",
+                root.dir.display()
+            )
+        );
+    }
+
+    #[test]
+    fn last_argument() {
+        let mut shell = Shell::new_for_test();
+        let (_, stdout, _) = shell.run_capture("echo a b; echo $_; x=1; echo $_ \"$0\"");
+        assert_eq!(stdout, "a b\nb\nb smash\n");
+    }
+
+    #[test]
+    fn funcname() {
+        let mut shell = Shell::new_for_test();
+        shell.run_script(concat!(
+            "inner() { echo ${FUNCNAME[0]} ${FUNCNAME[1]} ${#FUNCNAME[@]} $FUNCNAME; };",
+            "outer() { inner; echo $FUNCNAME; }"
+        ));
+
+        let (_, stdout, _) = shell.run_capture("outer; echo \"[${FUNCNAME[@]}]\"");
+        assert_eq!(stdout, "inner outer 2 inner\nouter\n[]\n");
+    }
+
+    #[test]
+    fn lineno_and_bash_source() {
+        let script = temp_path("lineno.sh");
+        std::fs::write(
+            &script,
+            "f() { echo $FUNCNAME $LINENO; }\n\nf\necho ${BASH_SOURCE[0]}\n",
+        )
+        .unwrap();
+
+        let mut shell = Shell::new_for_test();
+        let (_, stdout, _) = shell.run_capture(&format!(
+            "echo \"[$BASH_SOURCE]\"\necho $LINENO; source {}",
+            script.display()
+        ));
+        assert_eq!(stdout, format!("[]\n2\nf 1\n{}\n", script.display()));
+        std::fs::remove_file(&script).unwrap();
+    }
+
+    #[test]
+    fn command_substitution_inherits_variables() {
+        let mut shell = Shell::new_for_test();
+
+        // Not exported: only visible through the forked copy of the shell.
+        shell.set("x", Value::String("5".to_owned()), false);
+        let word = command_span_word("echo $x", false);
+        assert_eq!(expand_word_into_string(&mut shell, &word).unwrap(), "5");
+
+        shell.set("y", Value::String("local".to_owned()), true);
+        let word = command_span_word("echo $x $y", true);
+        assert_eq!(
+            expand_word_into_string(&mut shell, &word).unwrap(),
+            "5 local"
+        );
+    }
+
+    #[test]
+    fn commands_do_not_inherit_fds() {
+        let mut shell = Shell::new_for_test();
+        let word = command_span_word("ls -l /proc/self/fd", true);
+        let listing = expand_word_into_string(&mut shell, &word).unwrap();
+
+        // Only 0-2 and the directory opened by ls itself.
+        for (fd, target) in listing.lines().filter_map(|line| line.split_once(" -> ")) {
+            let fd: i32 = fd.rsplit(' ').next().unwrap().parse().unwrap();
+            assert!(fd <= 2 || target.ends_with("/fd"), "{} -> {}", fd, target);
+        }
+    }
+
+    #[test]
+    fn epoch_variables() {
+        let mut shell = Shell::new_for_test();
+        let now = unix_time().as_secs();
+
+        let seconds =
+            expand_param_at(&mut shell, "EPOCHSECONDS", None, &ExpansionOp::GetOrEmpty).unwrap();
+        let seconds: u64 = seconds[0].as_ref().unwrap().parse().unwrap();
+        assert!(seconds >= now);
+
+        let realtime =
+            expand_param_at(&mut shell, "EPOCHREALTIME", None, &ExpansionOp::GetOrEmpty).unwrap();
+        let realtime = realtime[0].as_ref().unwrap();
+        let (secs, micros) = realtime.split_once('.').unwrap();
+        assert!(secs.parse::<u64>().unwrap() >= now);
+        assert_eq!(micros.len(), 6);
+    }
+
+    #[test]
+    fn assignment_does_not_split_command_substitution() {
+        let mut shell = Shell::new_for_test();
+
+        let word = command_span_word("printf 'a\\nb'", false);
+        assert_eq!(
+            expand_words(&mut shell, std::slice::from_ref(&word)).unwrap(),
+            vec!["a".to_owned(), "b".to_owned()]
+        );
+
+        let initializer = Initializer::String(word);
+        match evaluate_initializer(&mut shell, &initializer).unwrap() {
+            Value::String(value) => assert_eq!(value, "a\nb"),
+            value => panic!("unexpected value: {:?}", value),
+        }
+    }
+
+    #[test]
+    fn field_splitting() {
+        let mut shell = Shell::new_for_test();
+        shell.set_string("HOME", "/home/me");
+        shell.set_string("x", "a  b ");
+        let (_, stdout, _) = shell.run_capture("printf '[%s]' pre$x ~/$x${x}post");
+        assert_eq!(stdout, "[prea][b][/home/me/a][b][a][b][post]");
+    }
 }
@@ -1,6 +1,6 @@
 use nix::unistd;
 use std::io::Write;
-use std::os::unix::io::RawFd;
+use std::os::unix::io::{AsRawFd, RawFd};
 
 pub struct FdFile {
     fd: RawFd,
@@ -12,6 +12,12 @@ impl FdFile {
     }
 }
 
+impl AsRawFd for FdFile {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
 impl Write for FdFile {
     #[inline]
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
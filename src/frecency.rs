@@ -0,0 +1,262 @@
+use crate::history::unix_time;
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// The maximum number of directories to remember. The lowest-ranked ones are
+/// forgotten first.
+const MAX_ENTRIES: usize = 1000;
+/// The number of visits recorded in memory before saving the database.
+const SAVE_INTERVAL: usize = 16;
+
+/// Visited directories ranked by *frecency* (frequency × recency) for `j`.
+pub struct DirDatabase {
+    /// The file where the database is persisted. `None` keeps it in memory.
+    path: Option<PathBuf>,
+    entries: Vec<DirEntry>,
+    /// The number of visits not saved to the file yet.
+    unsaved: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct DirEntry {
+    dir: String,
+    count: u64,
+    /// The UNIX time of the last visit.
+    last_visit: u64,
+}
+
+impl DirDatabase {
+    pub fn new() -> DirDatabase {
+        DirDatabase {
+            path: None,
+            entries: Vec::new(),
+            unsaved: 0,
+        }
+    }
+
+    /// Loads the database file. Each line is `dir\tcount\tlast_visit`. Broken
+    /// lines are ignored.
+    pub fn load(path: &Path) -> DirDatabase {
+        let mut entries = Vec::new();
+        if let Ok(file) = File::open(path) {
+            for line in BufReader::new(file).lines().map_while(Result::ok) {
+                let mut cols = line.split('\t');
+                match (cols.next(), cols.next(), cols.next()) {
+                    (Some(dir), Some(count), Some(last_visit)) => {
+                        if let (Ok(count), Ok(last_visit)) = (count.parse(), last_visit.parse()) {
+                            entries.push(DirEntry {
+                                dir: dir.to_owned(),
+                                count,
+                                last_visit,
+                            });
+                        }
+                    }
+                    _ => debug!("ignoring a broken line in {}", path.display()),
+                }
+            }
+        }
+
+        DirDatabase {
+            path: Some(path.to_owned()),
+            entries,
+            unsaved: 0,
+        }
+    }
+
+    /// Records a visit to `dir`. The database is saved once in a while and
+    /// when it's flushed or dropped.
+    pub fn record(&mut self, dir: &str) {
+        let now = unix_time().as_secs();
+        match self.entries.iter_mut().find(|entry| entry.dir == dir) {
+            Some(entry) => {
+                entry.count += 1;
+                entry.last_visit = now;
+            }
+            None => self.entries.push(DirEntry {
+                dir: dir.to_owned(),
+                count: 1,
+                last_visit: now,
+            }),
+        }
+
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.sort_by(|a, b| {
+                let a = frecency(a.count, a.last_visit, now);
+                let b = frecency(b.count, b.last_visit, now);
+                b.total_cmp(&a)
+            });
+            self.entries.truncate(MAX_ENTRIES);
+        }
+
+        self.unsaved += 1;
+        if self.unsaved >= SAVE_INTERVAL {
+            self.flush();
+        }
+    }
+
+    /// Saves visits recorded since the last save.
+    pub fn flush(&mut self) {
+        if self.unsaved > 0 {
+            self.save();
+            self.unsaved = 0;
+        }
+    }
+
+    /// Returns directories with their scores, the highest first.
+    pub fn ranked(&self) -> Vec<(f64, &str)> {
+        let now = unix_time().as_secs();
+        let mut ranked: Vec<(f64, &str)> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                (
+                    frecency(entry.count, entry.last_visit, now),
+                    entry.dir.as_str(),
+                )
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+        ranked
+    }
+
+    /// Returns the highest-ranked directory which contains all of `patterns`
+    /// except `current_dir`.
+    pub fn best_match(&self, patterns: &[String], current_dir: &str) -> Option<&str> {
+        self.ranked()
+            .into_iter()
+            .map(|(_, dir)| dir)
+            .filter(|dir| *dir != current_dir)
+            .find(|dir| {
+                patterns
+                    .iter()
+                    .all(|pattern| dir.contains(pattern.as_str()))
+            })
+    }
+
+    fn save(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+
+        let mut content = String::new();
+        for entry in &self.entries {
+            content.push_str(&format!(
+                "{}\t{}\t{}\n",
+                entry.dir, entry.count, entry.last_visit
+            ));
+        }
+
+        if let Err(err) = std::fs::write(path, content) {
+            debug!("failed to save {}: {}", path.display(), err);
+        }
+    }
+}
+
+impl Drop for DirDatabase {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// Weights the number of visits by how recently the directory was visited.
+fn frecency(count: u64, last_visit: u64, now: u64) -> f64 {
+    const HOUR: u64 = 60 * 60;
+    const DAY: u64 = 24 * HOUR;
+    const WEEK: u64 = 7 * DAY;
+
+    let weight = match now.saturating_sub(last_visit) {
+        elapsed if elapsed < HOUR => 4.0,
+        elapsed if elapsed < DAY => 2.0,
+        elapsed if elapsed < WEEK => 0.5,
+        _ => 0.25,
+    };
+
+    count as f64 * weight
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::temp_path;
+
+    #[test]
+    fn rank_by_frecency() {
+        let now = unix_time().as_secs();
+        let mut db = DirDatabase::new();
+        db.entries = vec![
+            // Visited often but long ago: 10 * 0.25
+            DirEntry {
+                dir: "/home/user/src/old".to_owned(),
+                count: 10,
+                last_visit: now - 30 * 24 * 60 * 60,
+            },
+            // Visited a few times recently: 3 * 4
+            DirEntry {
+                dir: "/home/user/src/new".to_owned(),
+                count: 3,
+                last_visit: now,
+            },
+        ];
+
+        assert_eq!(
+            db.best_match(&["src".to_owned()], "/"),
+            Some("/home/user/src/new")
+        );
+        assert_eq!(
+            db.best_match(&["src".to_owned()], "/home/user/src/new"),
+            Some("/home/user/src/old")
+        );
+        assert_eq!(
+            db.best_match(&["user".to_owned(), "old".to_owned()], "/"),
+            Some("/home/user/src/old")
+        );
+        assert_eq!(db.best_match(&["tmp".to_owned()], "/"), None);
+
+        // A recent visit boosts the score: 11 * 4
+        db.record("/home/user/src/old");
+        assert_eq!(db.entries[0].count, 11);
+        assert_eq!(
+            db.best_match(&["src".to_owned()], "/"),
+            Some("/home/user/src/old")
+        );
+    }
+
+    #[test]
+    fn load_and_save() {
+        let path = temp_path("dirs");
+        std::fs::write(&path, "/tmp\t2\t100\nbroken line\n").unwrap();
+
+        let mut db = DirDatabase::load(&path);
+        assert_eq!(db.entries.len(), 1);
+        db.record("/usr");
+        assert_eq!(DirDatabase::load(&path).entries.len(), 1);
+        db.flush();
+
+        let db = DirDatabase::load(&path);
+        let dirs: Vec<&str> = db.entries.iter().map(|entry| entry.dir.as_str()).collect();
+        assert_eq!(dirs, vec!["/tmp", "/usr"]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn forget_lowest_ranked_dirs() {
+        let mut db = DirDatabase::new();
+        db.entries = (0..MAX_ENTRIES)
+            .map(|i| DirEntry {
+                dir: format!("/old/{}", i),
+                count: if i == 0 { 1 } else { 2 },
+                last_visit: 0,
+            })
+            .collect();
+
+        db.record("/new");
+        assert_eq!(db.entries.len(), MAX_ENTRIES);
+        assert_eq!(db.best_match(&["new".to_owned()], "/"), Some("/new"));
+        assert_eq!(db.best_match(&["/old/0".to_owned()], "/"), None);
+    }
+}
@@ -4,9 +4,21 @@ use crate::shell::Shell;
 use crossterm::style::{Attribute, Color, SetAttribute, SetForegroundColor};
 use std::path::Path;
 
+/// Returns `true` if the output can be colored: the shell is interactive,
+/// `$TERM` is not `dumb`, and `$NO_COLOR` is not set.
+pub fn colors_enabled(shell: &Shell) -> bool {
+    shell.interactive()
+        && shell.get_str("TERM").as_deref() != Some("dumb")
+        && shell.get_str("NO_COLOR").unwrap_or_default().is_empty()
+}
+
 pub fn highlight(ctx: &InputContext, shell: &mut Shell) -> String {
     use std::fmt::Write;
 
+    if !colors_enabled(shell) {
+        return ctx.input.clone();
+    }
+
     let argv0_color = SetForegroundColor(Color::Green);
     let invalid_argv0_color = SetForegroundColor(Color::Red);
     let option_color = SetForegroundColor(Color::Cyan);
@@ -28,7 +40,8 @@ pub fn highlight(ctx: &InputContext, shell: &mut Shell) -> String {
                 let command_exists = (cmd.starts_with('/') && Path::new(cmd.as_str()).exists())
                     || shell.path_table().contains(cmd)
                     || builtin_command(cmd.as_str()).is_some()
-                    || shell.has_lookup_alias(cmd.as_str());
+                    || shell.has_lookup_alias(cmd.as_str())
+                    || shell.lookup_function(cmd.as_str()).is_some();
 
                 if command_exists {
                     write!(buf, "{}{}{}", argv0_color, cmd, reset).ok();
@@ -110,3 +123,30 @@ pub fn highlight(ctx: &InputContext, shell: &mut Shell) -> String {
 
     buf
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context_parser;
+    use crate::variable::Value;
+
+    #[test]
+    fn no_colors() {
+        let input = "ls -l | grep foo";
+        let ctx = context_parser::parse(input, input.len());
+
+        let mut shell = Shell::new_for_test();
+        assert_eq!(highlight(&ctx, &mut shell), input);
+
+        shell.interactive = true;
+        shell.set("TERM", Value::String("xterm".to_owned()), false);
+        assert_ne!(highlight(&ctx, &mut shell), input);
+
+        shell.set("NO_COLOR", Value::String("1".to_owned()), false);
+        assert_eq!(highlight(&ctx, &mut shell), input);
+
+        shell.set("NO_COLOR", Value::String("".to_owned()), false);
+        shell.set("TERM", Value::String("dumb".to_owned()), false);
+        assert_eq!(highlight(&ctx, &mut shell), input);
+    }
+}
@@ -1,9 +1,17 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::{File, OpenOptions};
 use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tracing::debug;
 
+/// Returns the elapsed time since the UNIX epoch.
+pub fn unix_time() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("failed to get the UNIX timestamp")
+}
+
 /// Command history.
 #[derive(Debug)]
 pub struct History {
@@ -30,7 +38,7 @@ impl History {
                         }
                         (_, _, false) => {
                             smash_err!(
-                                "smash: warning: failed to parse ~/.smash_history: at line {}",
+                                "warning: failed to parse ~/.smash_history: at line {}",
                                 i + 1
                             );
                             warned = true;
@@ -52,6 +60,16 @@ impl History {
         self.history.len()
     }
 
+    /// Returns the history entries, the oldest first.
+    pub fn entries(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Returns the directory where `cmd` was last run.
+    pub fn cwd(&self, cmd: &str) -> Option<&Path> {
+        self.path2cwd.get(cmd).map(PathBuf::as_path)
+    }
+
     /// Appends a history to the history file.
     pub fn append(&mut self, cmd: &str) {
         if cmd.is_empty() {
@@ -67,18 +85,27 @@ impl History {
 
         let cwd = std::env::current_dir().unwrap();
         if let Ok(mut file) = OpenOptions::new().append(true).open(&self.path) {
-            let time = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .expect("failed to get the UNIX timestamp")
-                .as_secs() as usize;
+            let time = unix_time().as_secs() as usize;
             let dir = cwd.to_str().unwrap().to_owned();
-            file.write(format!("{}\t{}\t{}\n", time, dir, cmd).as_bytes())
+            file.write_all(format!("{}\t{}\t{}\n", time, dir, cmd).as_bytes())
                 .ok();
         }
 
         self.history.push(cmd.to_string());
         self.path2cwd.insert(cmd.to_string(), cwd);
     }
+
+    /// Returns history entries containing `pattern`, the most recent first.
+    /// Duplicated entries are omitted.
+    pub fn search(&self, pattern: &str) -> Vec<String> {
+        let mut seen = HashSet::new();
+        self.history
+            .iter()
+            .rev()
+            .filter(|cmd| cmd.contains(pattern) && seen.insert(cmd.as_str()))
+            .cloned()
+            .collect()
+    }
 }
 
 pub struct HistorySelector {
@@ -1,29 +1,33 @@
 use crossterm::tty::IsTty;
 use std::fs::File;
-use std::path::Path;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use tracing_subscriber::{self, fmt, prelude::*, EnvFilter};
 
 use event::SmashState;
 use process::ExitStatus;
 use shell::Shell;
-use variable::Value;
 
 #[macro_use]
 mod macros;
 
+mod arith;
 mod builtins;
 mod context_parser;
 mod eval;
 mod event;
 mod expand;
 mod fd_file;
+mod frecency;
 mod highlight;
 mod history;
 mod parser;
 mod path;
+mod pattern;
 mod process;
 mod resolve;
 mod shell;
+mod timing;
 mod variable;
 
 fn main() {
@@ -32,6 +36,54 @@ fn main() {
         .with(EnvFilter::from_default_env())
         .init();
 
+    let mut rcfile = None;
+    let mut norc = false;
+    let mut noexec = false;
+    let mut command = None;
+    let mut script_file = None;
+    let mut script_args = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--norc" => norc = true,
+            "--rcfile" => match args.next() {
+                Some(path) => rcfile = Some(PathBuf::from(path)),
+                None => {
+                    smash_err!("--rcfile: option requires an argument");
+                    std::process::exit(2);
+                }
+            },
+            _ if !arg.starts_with('-') => {
+                script_file = Some(PathBuf::from(arg));
+                script_args = args.by_ref().collect();
+                break;
+            }
+            // Single-letter options can be grouped (e.g. `-nc`).
+            _ if !arg.starts_with("--") && arg.len() > 1 => {
+                for flag in arg[1..].chars() {
+                    match flag {
+                        'n' => noexec = true,
+                        'c' => match args.next() {
+                            Some(script) => command = Some(script),
+                            None => {
+                                smash_err!("-c: option requires an argument");
+                                std::process::exit(2);
+                            }
+                        },
+                        _ => {
+                            smash_err!("unknown option `-{}`", flag);
+                            std::process::exit(2);
+                        }
+                    }
+                }
+            }
+            _ => {
+                smash_err!("unknown option `{}`", arg);
+                std::process::exit(2);
+            }
+        }
+    }
+
     let home_dir = dirs::home_dir().expect("failed to get the path to the home directory");
     let history_path = Path::new(&home_dir).join(".smash_history");
     if !history_path.exists() {
@@ -41,14 +93,78 @@ fn main() {
     let mut shell = Shell::new(&history_path);
 
     for (key, value) in std::env::vars() {
-        shell.set(&key, Value::String(value.to_owned()), false);
+        shell.set_string(&key, value);
+    }
+
+    shell.increment_shlvl();
+
+    // `-n` checks the syntax of a script. It's ignored in the interactive
+    // mode like other shells.
+    if noexec && (command.is_some() || script_file.is_some() || !std::io::stdin().is_tty()) {
+        shell.set_option("noexec", true);
+    }
+
+    // Non-interactive modes. Job control is disabled in these modes.
+    if let Some(command) = command {
+        let status = shell.run_script(&command);
+        exit_with(&shell, status);
+    }
+
+    if let Some(script_file) = script_file {
+        shell.set_arg0(&script_file.to_string_lossy());
+        shell.current_frame_mut().replace_args(script_args);
+        match shell.run_file(script_file.clone()) {
+            Ok(status) => exit_with(&shell, status),
+            Err(err) => {
+                smash_err!("{}: {}", script_file.display(), err);
+                std::process::exit(127);
+            }
+        }
+    }
+
+    if !std::io::stdin().is_tty() {
+        let mut script = String::new();
+        if let Err(err) = std::io::stdin().read_to_string(&mut script) {
+            smash_err!("failed to read stdin: {}", err);
+            std::process::exit(1);
+        }
+
+        let status = shell.run_script(&script);
+        exit_with(&shell, status);
+    }
+
+    // Load aliases saved by `alias -s` first so that the rc file can override them.
+    if !norc {
+        if let Some(aliases_file) = builtins::aliases_file() {
+            shell.run_file(aliases_file).ok();
+        }
     }
 
     let home_dir = dirs::home_dir().unwrap();
-    shell.run_file(home_dir.join(".smashrc")).ok();
+    shell.load_dir_database(&home_dir.join(".smash_dirs"));
+
+    match rcfile {
+        // `--norc` takes precedence over `--rcfile`.
+        _ if norc => (),
+        Some(rcfile) => {
+            if let Err(err) = shell.run_file(rcfile.clone()) {
+                smash_err!("warning: failed to load {}: {}", rcfile.display(), err);
+            }
+        }
+        None => {
+            shell.run_file(home_dir.join(".smashrc")).ok();
+        }
+    }
 
     let is_tty = std::io::stdout().is_tty();
     shell.set_interactive(is_tty);
 
     SmashState::new(shell).run();
 }
+
+fn exit_with(shell: &Shell, status: ExitStatus) -> ! {
+    match status {
+        ExitStatus::ExitedWith(status) => std::process::exit(status),
+        _ => std::process::exit(shell.last_status()),
+    }
+}
@@ -1,6 +1,7 @@
 use pest::iterators::Pair;
 use pest::Parser;
 use pest_derive::Parser;
+use std::os::unix::io::RawFd;
 use tracing::debug;
 
 #[derive(Parser)]
@@ -17,6 +18,8 @@ pub struct Term {
     pub code: String,
     pub pipelines: Vec<Pipeline>,
     pub background: bool,
+    /// The line number (1-origin) where the term starts.
+    pub line: usize,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -41,15 +44,26 @@ pub struct Assignment {
     pub index: Option<Expr>,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct BinaryExpr {
-    pub lhs: Box<Expr>,
-    pub rhs: Box<Expr>,
-}
-
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Expr {
-    Assign { name: String, rhs: Box<Expr> },
+    Num(i32),
+    /// `x` or `$x`.
+    Var(String),
+    /// `${x}` and the other parameter expansions.
+    Parameter(Word),
+    Neg(Box<Expr>),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Div(Box<Expr>, Box<Expr>),
+    /// `i++`
+    Inc(String),
+    /// `i--`
+    Dec(String),
+    Assign {
+        name: String,
+        rhs: Box<Expr>,
+    },
     Eq(Box<Expr>, Box<Expr>),
     Ne(Box<Expr>, Box<Expr>),
     Lt(Box<Expr>, Box<Expr>),
@@ -77,6 +91,45 @@ pub enum Command {
     Assignment {
         assignments: Vec<Assignment>,
     },
+    Return {
+        status: Option<i32>,
+    },
+    // break 2
+    Break {
+        count: usize,
+    },
+    // continue 2
+    Continue {
+        count: usize,
+    },
+    // { echo hello; echo world; }
+    Group {
+        terms: Vec<Term>,
+    },
+    // while cond; do echo a; done
+    // until cond; do echo a; done
+    While {
+        condition: Vec<Term>,
+        body: Vec<Term>,
+        until: bool,
+    },
+    // case word in pattern1 | pattern2) echo a;; *) echo b;; esac
+    Case {
+        word: Word,
+        arms: Vec<(Vec<Word>, Vec<Term>)>,
+    },
+    // if cond; then echo a; elif cond; then echo b; else echo c; fi
+    If {
+        condition: Vec<Term>,
+        then_part: Vec<Term>,
+        elif_parts: Vec<(Vec<Term>, Vec<Term>)>,
+        else_part: Option<Vec<Term>>,
+    },
+    // foo() { echo hello; }
+    FunctionDefinition {
+        name: String,
+        body: Box<Command>,
+    },
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -102,15 +155,99 @@ pub enum RedirectionDirection {
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum RedirectionType {
     File(Word),
+    Fd(usize), // echo foo 2>&1
+    HereDoc(HereDoc),
+}
+
+/// A here document (`cat <<EOF`).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct HereDoc {
+    /// The contents. It's filled when the newline following the command is
+    /// parsed.
+    pub body: Word,
+    /// `<<-EOF`: leading tabs in the body are removed.
+    pub strip_tabs: bool,
+    /// `<<'EOF'`: the body is not expanded.
+    pub quoted: bool,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum ExpansionOp {
     // $parameter and ${parameter}
     GetOrEmpty,
+    // ${parameter@Q}, ${parameter@E}, and ${parameter@P}
+    Transform(Transformation),
+    // ${parameter:offset} and ${parameter:offset:length}
+    Substring {
+        offset: isize,
+        length: Option<isize>,
+    },
+    // ${#parameter}
+    Length,
+    // ${#parameter[@]} and ${#parameter[*]}
+    NumElements,
+    // ${parameter#word} and ${parameter##word}
+    RemovePrefix {
+        pattern: Word,
+        longest: bool,
+    },
+    // ${parameter%word} and ${parameter%%word}
+    RemoveSuffix {
+        pattern: Word,
+        longest: bool,
+    },
+    // ${parameter/pattern/string} and the variants
+    Replace {
+        pattern: Word,
+        replacement: Word,
+        mode: ReplaceMode,
+    },
+    // ${parameter:-word} and ${parameter-word}. With a colon (`check_null`),
+    // an empty value is treated as unset in these four expansions.
+    UseDefault {
+        word: Word,
+        check_null: bool,
+    },
+    // ${parameter:=word} and ${parameter=word}
+    AssignDefault {
+        word: Word,
+        check_null: bool,
+    },
+    // ${parameter:?word} and ${parameter?word}
+    ErrorIfUnset {
+        word: Word,
+        check_null: bool,
+    },
+    // ${parameter:+word} and ${parameter+word}
+    UseAlternative {
+        word: Word,
+        check_null: bool,
+    },
     // TODO: support other expansions
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ReplaceMode {
+    /// `${parameter/pattern/string}`: the first match.
+    First,
+    /// `${parameter//pattern/string}`: all matches.
+    All,
+    /// `${parameter/#pattern/string}`: a match at the beginning.
+    Prefix,
+    /// `${parameter/%pattern/string}`: a match at the end.
+    Suffix,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Transformation {
+    /// `@Q`: quote the value so that it can be reused as input.
+    Quote,
+    /// `@E`: expand backslash escape sequences like `$'...'`.
+    Escape,
+    /// `@P`: expand the value as a prompt string.
+    Prompt,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Span {
     Literal(String),
@@ -118,15 +255,37 @@ pub enum Span {
     LiteralChars(Vec<LiteralChar>),
     Parameter {
         name: String,
+        /// The subscript in `${name[index]}` as written (e.g. `1` or `@`).
+        index: Option<String>,
         op: ExpansionOp,
         quoted: bool,
     },
     Tilde(Option<String>),
+    // `*`, `?`, and `[a-z]` in pathname expansion.
+    AnyString,
+    AnyChar,
+    Bracket(String),
     // $(echo hello && echo world)
     Command {
         body: Vec<Term>,
         quoted: bool,
     },
+    // $((1 + 2))
+    Arith(Expr),
+    /// `{a,b}` or `{1..3}` in brace expansion. `source` is the original text
+    /// used where brace expansion is not performed (e.g. assignments).
+    Brace {
+        words: Vec<Word>,
+        source: String,
+    },
+    /// `{1..10..2}` or `{a..e}` in brace expansion. The words are generated
+    /// in expansion so that a large range doesn't bloat the AST.
+    BraceRange {
+        from: String,
+        to: String,
+        step: u64,
+        source: String,
+    },
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -143,12 +302,16 @@ impl Word {
 pub struct Pipeline {
     pub run_if: RunIf,
     pub commands: Vec<Command>, // Separated by `|'.
+    /// Prefixed by the `time` keyword.
+    pub time: bool,
 }
 
 pub fn parse(script: &str) -> Result<Ast, ParseError> {
     match ShellParser::parse(Rule::script, script) {
         Ok(mut pairs) => {
-            let terms = visit_compound_list(pairs.next().unwrap());
+            let pair = pairs.next().unwrap();
+            validate(&pair)?;
+            let terms = visit_compound_list(pair);
 
             if terms.is_empty() {
                 Err(ParseError::Empty)
@@ -160,6 +323,42 @@ pub fn parse(script: &str) -> Result<Ast, ParseError> {
     }
 }
 
+/// Parses an arithmetic expression like `i = i + 1`.
+pub fn parse_arith(expr: &str) -> Result<Expr, ParseError> {
+    match ShellParser::parse(Rule::arith_expr, expr) {
+        Ok(mut pairs) => Ok(visit_expr(pairs.next().unwrap())),
+        Err(err) => Err(ParseError::Fatal(err.to_string())),
+    }
+}
+
+/// Returns an error if `pair` contains a construct which the grammar accepts
+/// but the visitors below can't handle: ones not supported yet and file
+/// descriptors out of range (e.g. `2>&99999999999`).
+fn validate(pair: &Pair<Rule>) -> Result<(), ParseError> {
+    for inner in pair.clone().into_inner().flatten() {
+        let name = match inner.as_rule() {
+            Rule::fd | Rule::target_fd => {
+                let fd = inner.as_str();
+                if !fd.is_empty() && fd.parse::<RawFd>().is_err() {
+                    return Err(ParseError::Fatal(format!("{}: bad file descriptor", fd)));
+                }
+
+                continue;
+            }
+            Rule::for_command | Rule::arith_for_command => "for",
+            Rule::subshell_group => "subshell",
+            Rule::cond_ex => "[[ ... ]]",
+            Rule::proc_subst_span => "process substitution",
+            Rule::backtick_span => "`...`",
+            _ => continue,
+        };
+
+        return Err(ParseError::Fatal(format!("{}: not supported yet", name)));
+    }
+
+    Ok(())
+}
+
 macro_rules! wsnl {
     ($pairs:expr) => {
         if let Some(next) = $pairs.next() {
@@ -178,6 +377,7 @@ fn visit_compound_list(pair: Pair<Rule>) -> Vec<Term> {
     let mut inner = pair.into_inner();
     if let Some(and_or_list) = inner.next() {
         let mut background = false;
+        let mut heredoc_bodies = Vec::new();
         let mut rest = None;
         while let Some(sep_or_rest) = wsnl!(inner) {
             debug!(?sep_or_rest);
@@ -193,7 +393,11 @@ fn visit_compound_list(pair: Pair<Rule>) -> Vec<Term> {
                             background = true;
                         }
                         Rule::newline => {
-                            // TODO: handle heredocs
+                            if let Some(body) = sep.into_inner().next() {
+                                if body.as_rule() == Rule::heredoc_body {
+                                    heredoc_bodies.push(body.as_str().to_owned());
+                                }
+                            }
                         }
                         Rule::seq_sep => (),
                         _ => (),
@@ -204,11 +408,21 @@ fn visit_compound_list(pair: Pair<Rule>) -> Vec<Term> {
 
         if and_or_list.as_rule() == Rule::and_or_list {
             let code = and_or_list.as_str().to_owned().trim().to_owned();
-            let pipelines = visit_and_or_list(and_or_list, RunIf::Always);
+            let line = and_or_list.as_span().start_pos().line_col().0;
+            let mut pipelines = visit_and_or_list(and_or_list, RunIf::Always);
+            let heredocs = pipelines
+                .iter_mut()
+                .flat_map(|pipeline| pipeline.commands.iter_mut())
+                .flat_map(heredocs_mut);
+            for (heredoc, body) in heredocs.zip(heredoc_bodies) {
+                fill_heredoc(heredoc, &body);
+            }
+
             terms.push(Term {
                 code,
                 pipelines,
                 background,
+                line,
             });
         }
 
@@ -220,12 +434,117 @@ fn visit_compound_list(pair: Pair<Rule>) -> Vec<Term> {
     terms
 }
 
+/// Returns here documents in `command` in the order of appearance.
+fn heredocs_mut(command: &mut Command) -> Vec<&mut HereDoc> {
+    match command {
+        Command::SimpleCommand { redirects, .. } => redirects
+            .iter_mut()
+            .filter_map(|redirect| match &mut redirect.target {
+                RedirectionType::HereDoc(heredoc) => Some(heredoc),
+                _ => None,
+            })
+            .collect(),
+        Command::Group { terms } => terms
+            .iter_mut()
+            .flat_map(|term| term.pipelines.iter_mut())
+            .flat_map(|pipeline| pipeline.commands.iter_mut())
+            .flat_map(heredocs_mut)
+            .collect(),
+        Command::If {
+            condition,
+            then_part,
+            elif_parts,
+            else_part,
+        } => condition
+            .iter_mut()
+            .chain(then_part.iter_mut())
+            .chain(
+                elif_parts
+                    .iter_mut()
+                    .flat_map(|(condition, then_part)| condition.iter_mut().chain(then_part)),
+            )
+            .chain(else_part.iter_mut().flatten())
+            .flat_map(|term| term.pipelines.iter_mut())
+            .flat_map(|pipeline| pipeline.commands.iter_mut())
+            .flat_map(heredocs_mut)
+            .collect(),
+        Command::While {
+            condition, body, ..
+        } => condition
+            .iter_mut()
+            .chain(body.iter_mut())
+            .flat_map(|term| term.pipelines.iter_mut())
+            .flat_map(|pipeline| pipeline.commands.iter_mut())
+            .flat_map(heredocs_mut)
+            .collect(),
+        Command::Case { arms, .. } => arms
+            .iter_mut()
+            .flat_map(|(_, body)| body.iter_mut())
+            .flat_map(|term| term.pipelines.iter_mut())
+            .flat_map(|pipeline| pipeline.commands.iter_mut())
+            .flat_map(heredocs_mut)
+            .collect(),
+        Command::FunctionDefinition { body, .. } => heredocs_mut(body),
+        Command::Assignment { .. }
+        | Command::Return { .. }
+        | Command::Break { .. }
+        | Command::Continue { .. } => Vec::new(),
+    }
+}
+
+fn fill_heredoc(heredoc: &mut HereDoc, body: &str) {
+    let body = if heredoc.strip_tabs {
+        body.lines()
+            .map(|line| format!("{}\n", line.trim_start_matches('\t')))
+            .collect()
+    } else {
+        body.to_owned()
+    };
+
+    heredoc.body = if heredoc.quoted {
+        Word(vec![Span::Literal(body)])
+    } else {
+        visit_heredoc_text(&body)
+    };
+}
+
+/// Parses the body of an unquoted here document.
+fn visit_heredoc_text(body: &str) -> Word {
+    let text = match ShellParser::parse(Rule::heredoc_text, body) {
+        Ok(mut pairs) => pairs.next().unwrap(),
+        Err(err) => {
+            debug!("failed to parse a here document: {}", err);
+            return Word(vec![Span::Literal(body.to_owned())]);
+        }
+    };
+
+    if let Err(err) = validate(&text) {
+        debug!("unsupported here document: {:?}", err);
+        return Word(vec![Span::Literal(body.to_owned())]);
+    }
+
+    let mut spans = Vec::new();
+    for span in text.into_inner() {
+        match span.as_rule() {
+            Rule::literal_in_heredoc => {
+                spans.push(Span::Literal(visit_escape_sequences(span, Some("$`\\"))));
+            }
+            Rule::heredoc_dollar => spans.push(Span::Literal("$".to_owned())),
+            Rule::param_span => spans.push(visit_param_span(span, true)),
+            Rule::param_ex_span => spans.push(visit_param_ex_span(span, true)),
+            Rule::command_span => spans.push(visit_command_span(span, true)),
+            _ => (),
+        }
+    }
+
+    Word(spans)
+}
+
 fn visit_and_or_list(pair: Pair<Rule>, run_if: RunIf) -> Vec<Pipeline> {
     let mut terms = Vec::new();
     let mut inner = pair.into_inner();
     if let Some(pipeline) = inner.next() {
-        let commands = visit_pipeline(pipeline);
-        terms.push(Pipeline { commands, run_if });
+        terms.push(visit_pipeline(pipeline, run_if));
 
         let next_run_if = inner
             .next()
@@ -244,14 +563,22 @@ fn visit_and_or_list(pair: Pair<Rule>, run_if: RunIf) -> Vec<Pipeline> {
     terms
 }
 
-fn visit_pipeline(pair: Pair<Rule>) -> Vec<Command> {
+fn visit_pipeline(pair: Pair<Rule>, run_if: RunIf) -> Pipeline {
     let mut commands = Vec::new();
+    let mut time = false;
     let mut inner = pair.into_inner();
     while let Some(command) = wsnl!(inner) {
-        commands.push(visit_command(command));
+        match command.as_rule() {
+            Rule::time_keyword => time = true,
+            _ => commands.push(visit_command(command)),
+        }
     }
 
-    commands
+    Pipeline {
+        run_if,
+        commands,
+        time,
+    }
 }
 
 fn visit_simple_command(pair: Pair<Rule>) -> Command {
@@ -270,6 +597,7 @@ fn visit_simple_command(pair: Pair<Rule>) -> Command {
         match word_or_redirect.as_rule() {
             Rule::word => argv.push(visit_word(word_or_redirect)),
             Rule::redirect => redirects.push(visit_redirect(word_or_redirect)),
+            Rule::heredoc => redirects.push(visit_heredoc(word_or_redirect)),
             _ => unreachable!(),
         }
     }
@@ -286,6 +614,28 @@ fn visit_simple_command(pair: Pair<Rule>) -> Command {
     }
 }
 
+fn visit_heredoc(pair: Pair<Rule>) -> Redirection {
+    let mut strip_tabs = false;
+    let mut quoted = false;
+    for inner in pair.into_inner() {
+        match inner.as_rule() {
+            Rule::heredoc_strip_tabs => strip_tabs = true,
+            Rule::heredoc_quoted_marker => quoted = true,
+            _ => (),
+        }
+    }
+
+    Redirection {
+        fd: 0,
+        direction: RedirectionDirection::Input,
+        target: RedirectionType::HereDoc(HereDoc {
+            body: Word(vec![]),
+            strip_tabs,
+            quoted,
+        }),
+    }
+}
+
 fn visit_redirect(pair: Pair<Rule>) -> Redirection {
     let mut inner = pair.into_inner();
     let fd = inner.next().unwrap();
@@ -299,20 +649,14 @@ fn visit_redirect(pair: Pair<Rule>) -> Redirection {
         _ => unreachable!(),
     };
 
+    // Fds out of range are rejected by validate().
     let fd = fd.as_span().as_str().parse().unwrap_or(default_fd);
     let target = match target.as_rule() {
         Rule::word => RedirectionType::File(visit_word(target)),
-        // Rule::redirect_to_fd => {
-        //     let target_fd = target
-        //         .into_inner()
-        //         .next()
-        //         .unwrap()
-        //         .as_span()
-        //         .as_str()
-        //         .parse()
-        //         .unwrap();
-        //     RedirectionType::Fd(target_fd)
-        // }
+        Rule::redirect_to_fd => {
+            let target_fd = target.into_inner().next().unwrap().as_str();
+            RedirectionType::Fd(target_fd.parse().unwrap())
+        }
         _ => unreachable!(),
     };
 
@@ -324,7 +668,44 @@ fn visit_redirect(pair: Pair<Rule>) -> Redirection {
 }
 
 fn visit_word(pair: Pair<Rule>) -> Word {
-    visit_escaped_word(pair, false)
+    visit_escaped_word(pair, false, false)
+}
+
+/// Visits the value of an assignment. Unlike other words, `~` after `:` is
+/// also expanded (e.g. `PATH=$PATH:~/bin`).
+fn visit_assignment_word(pair: Pair<Rule>) -> Word {
+    visit_escaped_word(pair, false, true)
+}
+
+/// Splits an unquoted literal in an assignment into literals and tildes
+/// following `:`.
+fn visit_assignment_literal(pair: Pair<Rule>) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut literal = String::new();
+    let mut chars = pair.as_str().chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => literal.extend(chars.next()),
+            ':' if chars.peek() == Some(&'~') => {
+                chars.next();
+                literal.push(':');
+                spans.push(Span::Literal(std::mem::take(&mut literal)));
+
+                let mut username = String::new();
+                while let Some(ch) = chars.next_if(|ch| !matches!(ch, '/' | ':' | '\\')) {
+                    username.push(ch);
+                }
+                spans.push(Span::Tilde((!username.is_empty()).then_some(username)));
+            }
+            _ => literal.push(ch),
+        }
+    }
+
+    if !literal.is_empty() {
+        spans.push(Span::Literal(literal));
+    }
+
+    spans
 }
 
 fn visit_escape_sequences(pair: Pair<Rule>, escaped_chars: Option<&str>) -> String {
@@ -349,8 +730,11 @@ fn visit_escape_sequences(pair: Pair<Rule>, escaped_chars: Option<&str>) -> Stri
     s
 }
 
-fn visit_escaped_word(pair: Pair<Rule>, literal_chars: bool) -> Word {
-    assert_eq!(pair.as_rule(), Rule::word);
+fn visit_escaped_word(pair: Pair<Rule>, literal_chars: bool, assignment: bool) -> Word {
+    assert!(matches!(
+        pair.as_rule(),
+        Rule::word | Rule::subst_pattern | Rule::brace_elem
+    ));
 
     let mut spans = Vec::new();
     for span in pair.into_inner() {
@@ -372,10 +756,17 @@ fn visit_escaped_word(pair: Pair<Rule>, literal_chars: bool) -> Word {
                 }
                 spans.push(Span::LiteralChars(chars));
             }
-            Rule::literal_span if !literal_chars => {
+            Rule::literal_span if assignment => spans.extend(visit_assignment_literal(span)),
+            Rule::literal_span | Rule::subst_literal_span | Rule::brace_elem_literal_span
+                if !literal_chars =>
+            {
                 spans.push(Span::Literal(visit_escape_sequences(span, None)));
             }
+            Rule::brace_span => spans.push(visit_brace_span(span)),
+            Rule::brace_literal_span => spans.push(Span::Literal(span.as_str().to_owned())),
             Rule::param_span => spans.push(visit_param_span(span, false)),
+            Rule::param_ex_span => spans.push(visit_param_ex_span(span, false)),
+            Rule::expr_span => spans.push(visit_expr_span(span)),
             Rule::assign_like_prefix => {
                 let mut inner = span.into_inner();
                 let var_name = inner.next().unwrap();
@@ -393,11 +784,16 @@ fn visit_escaped_word(pair: Pair<Rule>, literal_chars: bool) -> Word {
                             )));
                         }
                         Rule::param_span => spans.push(visit_param_span(span_in_quote, true)),
+                        Rule::param_ex_span => spans.push(visit_param_ex_span(span_in_quote, true)),
                         Rule::command_span => spans.push(visit_command_span(span_in_quote, true)),
+                        Rule::expr_span => spans.push(visit_expr_span(span_in_quote)),
                         rule => unreachable!("{:?}", rule),
                     }
                 }
             }
+            Rule::any_string_span => spans.push(Span::AnyString),
+            Rule::any_char_span => spans.push(Span::AnyChar),
+            Rule::bracket_span => spans.push(Span::Bracket(span.as_str().to_owned())),
             Rule::tilde_span => {
                 let username = span
                     .into_inner()
@@ -415,10 +811,9 @@ fn visit_escaped_word(pair: Pair<Rule>, literal_chars: bool) -> Word {
                     }
                 }
             }
-            _ => {
-                debug!(?span);
-                unimplemented!("span {:?}", span.as_rule());
-            }
+            Rule::command_span => spans.push(visit_command_span(span, false)),
+            // Rejected by validate().
+            rule => unreachable!("{:?}", rule),
         }
     }
 
@@ -430,21 +825,21 @@ fn visit_command(pair: Pair<Rule>) -> Command {
     let inner = pair.into_inner().next().unwrap();
     match inner.as_rule() {
         Rule::simple_command => visit_simple_command(inner),
-        // Rule::if_command => visit_if_command(inner),
-        // Rule::while_command => visit_while_command(inner),
-        // Rule::arith_for_command => visit_arith_for_command(inner),
-        // Rule::for_command => visit_for_command(inner),
-        // Rule::case_command => visit_case_command(inner),
-        // Rule::group => visit_group_command(inner),
-        // Rule::subshell_group => visit_subshell_group_command(inner),
-        // Rule::break_command => Command::Break,
-        // Rule::continue_command => Command::Continue,
-        // Rule::return_command => visit_return_command(inner),
+        Rule::if_command => visit_if_command(inner),
+        Rule::while_command => visit_while_command(inner),
+        Rule::case_command => visit_case_command(inner),
+        Rule::group => visit_group_command(inner),
+        Rule::return_command => visit_return_command(inner),
+        Rule::break_command => Command::Break {
+            count: visit_loop_count(inner),
+        },
+        Rule::continue_command => Command::Continue {
+            count: visit_loop_count(inner),
+        },
         Rule::assignment_command => visit_assignment_command(inner),
-        // Rule::local_definition => visit_local_definition(inner),
-        // Rule::function_definition => visit_function_definition(inner),
-        // Rule::cond_ex => visit_cond_ex(inner),
-        _ => unimplemented!("rule {:?}", inner.as_rule()),
+        Rule::function_definition => visit_function_definition(inner),
+        // Rejected by validate().
+        rule => unreachable!("{:?}", rule),
     }
 }
 
@@ -457,7 +852,129 @@ fn visit_param_span(pair: Pair<Rule>, quoted: bool) -> Span {
         .as_str()
         .to_owned();
     let op = ExpansionOp::GetOrEmpty;
-    Span::Parameter { name, op, quoted }
+    Span::Parameter {
+        name,
+        index: None,
+        op,
+        quoted,
+    }
+}
+
+fn visit_param_ex_span(pair: Pair<Rule>, quoted: bool) -> Span {
+    let mut inner = pair.into_inner();
+    let length_op = !inner.next().unwrap().as_str().is_empty();
+    let name = inner.next().unwrap().as_span().as_str().to_owned();
+    let index = inner.next().unwrap();
+    let all_elems = index.as_rule() == Rule::all_elems_index;
+    let index = Some(
+        index
+            .as_str()
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .trim(),
+    )
+    .filter(|index| !index.is_empty())
+    .map(str::to_owned);
+    let op = match inner.next() {
+        _ if length_op && all_elems => ExpansionOp::NumElements,
+        _ if length_op => ExpansionOp::Length,
+        Some(transform) if transform.as_rule() == Rule::param_transform => {
+            match transform.as_str() {
+                "@Q" => ExpansionOp::Transform(Transformation::Quote),
+                "@E" => ExpansionOp::Transform(Transformation::Escape),
+                "@P" => ExpansionOp::Transform(Transformation::Prompt),
+                _ => unreachable!(),
+            }
+        }
+        Some(substr) if substr.as_rule() == Rule::param_substr => {
+            let mut inner = substr.into_inner();
+            let offset = inner.next().unwrap().into_inner().next();
+            let length = inner.next().and_then(|length| length.into_inner().next());
+            ExpansionOp::Substring {
+                offset: offset
+                    .map(|num| visit_substr_num(num.as_str()))
+                    .unwrap_or(0),
+                length: length.map(|num| visit_substr_num(num.as_str())),
+            }
+        }
+        Some(subst) if subst.as_rule() == Rule::param_subst => visit_param_subst(subst),
+        Some(opt) if opt.as_rule() == Rule::param_opt => visit_param_opt(opt),
+        // TODO: support indices and the other expansions
+        _ => ExpansionOp::GetOrEmpty,
+    };
+
+    Span::Parameter {
+        name,
+        index,
+        op,
+        quoted,
+    }
+}
+
+/// Visits the word in `${var:-word}`, which may contain spaces.
+fn visit_param_opt_word(pair: Pair<Rule>) -> Word {
+    let mut spans = Vec::new();
+    for part in pair.into_inner() {
+        match part.as_rule() {
+            Rule::word => spans.extend(visit_word(part).0),
+            Rule::param_opt_space => spans.push(Span::Literal(part.as_str().to_owned())),
+            _ => unreachable!(),
+        }
+    }
+
+    Word(spans)
+}
+
+fn visit_param_opt(pair: Pair<Rule>) -> ExpansionOp {
+    let mut inner = pair.into_inner();
+    let param_op = inner.next().unwrap().as_str();
+    let word = visit_param_opt_word(inner.next().unwrap());
+    let check_null = param_op.starts_with(':');
+    match param_op.trim_start_matches(':') {
+        "#" | "##" => ExpansionOp::RemovePrefix {
+            pattern: word,
+            longest: param_op == "##",
+        },
+        "%" | "%%" => ExpansionOp::RemoveSuffix {
+            pattern: word,
+            longest: param_op == "%%",
+        },
+        "-" => ExpansionOp::UseDefault { word, check_null },
+        "=" => ExpansionOp::AssignDefault { word, check_null },
+        "?" => ExpansionOp::ErrorIfUnset { word, check_null },
+        "+" => ExpansionOp::UseAlternative { word, check_null },
+        _ => unreachable!(),
+    }
+}
+
+fn visit_param_subst(pair: Pair<Rule>) -> ExpansionOp {
+    let mut inner = pair.into_inner();
+    let mode = match inner.next().unwrap().as_str() {
+        "//" => ReplaceMode::All,
+        "/#" => ReplaceMode::Prefix,
+        "/%" => ReplaceMode::Suffix,
+        _ => ReplaceMode::First,
+    };
+    let pattern = visit_escaped_word(inner.next().unwrap(), false, false);
+    let replacement = inner
+        .next()
+        .map(visit_param_opt_word)
+        .unwrap_or_else(|| Word(Vec::new()));
+
+    ExpansionOp::Replace {
+        pattern,
+        replacement,
+        mode,
+    }
+}
+
+/// Parses an offset or a length in `${var:offset:length}`. Saturates on overflow.
+fn visit_substr_num(num: &str) -> isize {
+    num.parse().unwrap_or(if num.starts_with('-') {
+        isize::MIN
+    } else {
+        isize::MAX
+    })
 }
 
 fn visit_assignment(pair: Pair<Rule>) -> Assignment {
@@ -468,7 +985,9 @@ fn visit_assignment(pair: Pair<Rule>) -> Assignment {
     let initializer = inner.next().unwrap().into_inner().next().unwrap();
     match initializer.as_rule() {
         Rule::string_initializer => {
-            let word = Initializer::String(visit_word(initializer.into_inner().next().unwrap()));
+            let word = Initializer::String(visit_assignment_word(
+                initializer.into_inner().next().unwrap(),
+            ));
             Assignment {
                 name,
                 initializer: word,
@@ -489,58 +1008,237 @@ fn visit_assignment(pair: Pair<Rule>) -> Assignment {
 }
 
 fn visit_expr(pair: Pair<Rule>) -> Expr {
-    let mut inner = pair.clone().into_inner();
+    assert_eq!(pair.as_rule(), Rule::expr);
+
+    // Binary operators are left-associative: `1 < 2 == 1` is `(1 < 2) == 1`.
+    let mut inner = pair.into_inner();
+    let mut lhs = visit_assign_expr(inner.next().unwrap());
+    while let Some(op) = inner.next() {
+        let rhs = Box::new(visit_assign_expr(inner.next().unwrap()));
+        let lhs_ = Box::new(lhs);
+        lhs = match op.as_str() {
+            "==" => Expr::Eq(lhs_, rhs),
+            "!=" => Expr::Ne(lhs_, rhs),
+            ">" => Expr::Gt(lhs_, rhs),
+            ">=" => Expr::Ge(lhs_, rhs),
+            "<" => Expr::Lt(lhs_, rhs),
+            "<=" => Expr::Le(lhs_, rhs),
+            _ => unreachable!(),
+        };
+    }
+
+    lhs
+}
+
+fn visit_assign_expr(pair: Pair<Rule>) -> Expr {
+    let mut inner = pair.into_inner();
     let first = inner.next().unwrap();
-    let maybe_op = inner.next();
-
-    match pair.as_rule() {
-        Rule::assign => visit_assign_expr(pair),
-        Rule::expr => {
-            let lhs = visit_assign_expr(first);
-            if let Some(op) = maybe_op {
-                let rhs = visit_expr(inner.next().unwrap());
-                match op.as_span().as_str() {
-                    "==" => Expr::Eq(Box::new(lhs), Box::new(rhs)),
-                    "!=" => Expr::Ne(Box::new(lhs), Box::new(rhs)),
-                    ">" => Expr::Gt(Box::new(lhs), Box::new(rhs)),
-                    ">=" => Expr::Ge(Box::new(lhs), Box::new(rhs)),
-                    "<" => Expr::Lt(Box::new(lhs), Box::new(rhs)),
-                    "<=" => Expr::Le(Box::new(lhs), Box::new(rhs)),
-                    _ => unreachable!(),
-                }
+    match first.as_rule() {
+        Rule::var_name => {
+            let name = first.as_str().to_owned();
+            // Skip `=`.
+            inner.next();
+            let rhs = visit_assign_expr(inner.next().unwrap());
+            Expr::Assign {
+                name,
+                rhs: Box::new(rhs),
+            }
+        }
+        Rule::arith => visit_arith_expr(first),
+        _ => unreachable!(),
+    }
+}
+
+/// Visits `arith` (`+` and `-`) or `term` (`*` and `/`).
+fn visit_arith_expr(pair: Pair<Rule>) -> Expr {
+    let mut inner = pair.into_inner();
+    let visit_operand = |pair: Pair<Rule>| match pair.as_rule() {
+        Rule::term => visit_arith_expr(pair),
+        Rule::factor => visit_factor(pair),
+        _ => unreachable!(),
+    };
+
+    let mut lhs = visit_operand(inner.next().unwrap());
+    while let Some(op) = inner.next() {
+        let rhs = Box::new(visit_operand(inner.next().unwrap()));
+        let lhs_ = Box::new(lhs);
+        lhs = match op.as_str() {
+            "+" => Expr::Add(lhs_, rhs),
+            "-" => Expr::Sub(lhs_, rhs),
+            "*" => Expr::Mul(lhs_, rhs),
+            "/" => Expr::Div(lhs_, rhs),
+            _ => unreachable!(),
+        };
+    }
+
+    lhs
+}
+
+fn visit_factor(pair: Pair<Rule>) -> Expr {
+    let mut inner = pair.into_inner();
+    let negative = inner.next().unwrap().as_str() == "-";
+    let operand = inner.next().unwrap();
+    let expr = match operand.as_rule() {
+        // Saturates on overflow.
+        Rule::num => Expr::Num(operand.as_str().parse().unwrap_or(i32::MAX)),
+        Rule::var_name => Expr::Var(operand.as_str().to_owned()),
+        Rule::param_ex_span => Expr::Parameter(Word(vec![visit_param_ex_span(operand, true)])),
+        Rule::postfix_incdec => {
+            let inc = operand.as_str().ends_with("++");
+            let name = operand.into_inner().next().unwrap().as_str().to_owned();
+            if inc {
+                Expr::Inc(name)
             } else {
-                lhs
+                Expr::Dec(name)
             }
         }
+        Rule::expr => visit_expr(operand),
         _ => unreachable!(),
+    };
+
+    if negative {
+        Expr::Neg(Box::new(expr))
+    } else {
+        expr
     }
 }
 
-fn visit_assign_expr(pair: Pair<Rule>) -> Expr {
-    let mut inner = pair.clone().into_inner();
-    let first = inner.next().unwrap();
-    match first.as_rule() {
-        Rule::var_name => {
-            let name = first.as_span().as_str().to_owned();
-            let op = inner.next().unwrap();
-            let rhs = visit_expr(inner.next().unwrap());
-            match op.as_span().as_str() {
-                "=" => Expr::Assign {
-                    name,
-                    rhs: Box::new(rhs),
-                },
-                _ => unreachable!(),
+fn visit_brace_span(pair: Pair<Rule>) -> Span {
+    let source = pair.as_str().to_owned();
+    let inner = pair.into_inner().next().unwrap();
+    match inner.as_rule() {
+        Rule::brace_list => Span::Brace {
+            words: inner
+                .into_inner()
+                .map(|elem| visit_escaped_word(elem, false, false))
+                .collect(),
+            source,
+        },
+        Rule::brace_range => {
+            let mut inner = inner.into_inner();
+            let from = inner.next().unwrap().as_str().to_owned();
+            let to = inner.next().unwrap().as_str().to_owned();
+            let step = inner
+                .next()
+                .and_then(|step| step.as_str().parse::<i64>().ok())
+                .map(i64::unsigned_abs)
+                .filter(|step| *step > 0)
+                .unwrap_or(1);
+
+            // `{1..a}` is not expanded.
+            let is_num = |item: &str| item.parse::<i64>().is_ok();
+            if is_num(&from) != is_num(&to) {
+                return Span::Literal(source);
+            }
+
+            Span::BraceRange {
+                from,
+                to,
+                step,
+                source,
             }
         }
-        _ => todo!(),
+        _ => unreachable!(),
     }
 }
 
+fn visit_expr_span(pair: Pair<Rule>) -> Span {
+    Span::Arith(visit_expr(pair.into_inner().next().unwrap()))
+}
+
 fn visit_command_span(pair: Pair<Rule>, quoted: bool) -> Span {
     let body = visit_compound_list(pair.into_inner().next().unwrap());
     Span::Command { body, quoted }
 }
 
+fn visit_group_command(pair: Pair<Rule>) -> Command {
+    let terms = visit_compound_list(pair.into_inner().next().unwrap());
+    Command::Group { terms }
+}
+
+fn visit_if_command(pair: Pair<Rule>) -> Command {
+    let mut inner = pair.into_inner();
+    let condition = visit_compound_list(inner.next().unwrap());
+    let then_part = visit_compound_list(inner.next().unwrap());
+
+    let mut elif_parts = Vec::new();
+    let mut else_part = None;
+    for part in inner {
+        match part.as_rule() {
+            Rule::elif_part => {
+                let mut inner = part.into_inner();
+                let condition = visit_compound_list(inner.next().unwrap());
+                let then_part = visit_compound_list(inner.next().unwrap());
+                elif_parts.push((condition, then_part));
+            }
+            Rule::else_part => {
+                else_part = Some(visit_compound_list(part.into_inner().next().unwrap()));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    Command::If {
+        condition,
+        then_part,
+        elif_parts,
+        else_part,
+    }
+}
+
+fn visit_while_command(pair: Pair<Rule>) -> Command {
+    let mut inner = pair.into_inner();
+    let until = inner.next().unwrap().as_str() == "until";
+    let condition = visit_compound_list(inner.next().unwrap());
+    let body = visit_compound_list(inner.next().unwrap());
+    Command::While {
+        condition,
+        body,
+        until,
+    }
+}
+
+fn visit_case_command(pair: Pair<Rule>) -> Command {
+    let mut inner = pair.into_inner();
+    let word = visit_word(inner.next().unwrap());
+    let mut arms = Vec::new();
+    for item in inner.filter(|pair| pair.as_rule() == Rule::case_item) {
+        let mut inner = item.into_inner();
+        let patterns = inner.next().unwrap().into_inner().map(visit_word).collect();
+        let body = visit_compound_list(inner.next().unwrap());
+        arms.push((patterns, body));
+    }
+
+    Command::Case { word, arms }
+}
+
+fn visit_function_definition(pair: Pair<Rule>) -> Command {
+    let mut inner = pair.into_inner();
+    let name = inner.next().unwrap().as_span().as_str().to_owned();
+    let body = Box::new(visit_command(inner.next().unwrap()));
+    Command::FunctionDefinition { name, body }
+}
+
+fn visit_return_command(pair: Pair<Rule>) -> Command {
+    // Like bash, only the least significant 8 bits are used so that a large
+    // number (e.g. `return 99999999999`) does not overflow.
+    let status = pair.into_inner().next().map(|num| {
+        num.as_str().bytes().fold(0, |status, digit| {
+            (status * 10 + (digit - b'0') as i32) % 256
+        })
+    });
+    Command::Return { status }
+}
+
+/// The number of loops to exit in `break N` or `continue N`. A number too
+/// large for usize exits all the loops.
+fn visit_loop_count(pair: Pair<Rule>) -> usize {
+    match pair.into_inner().next() {
+        Some(num) => num.as_str().parse().unwrap_or(usize::MAX),
+        None => 1,
+    }
+}
+
 fn visit_assignment_command(pair: Pair<Rule>) -> Command {
     let assignments = pair.into_inner().map(visit_assignment).collect();
     Command::Assignment { assignments }
@@ -548,7 +1246,10 @@ fn visit_assignment_command(pair: Pair<Rule>) -> Command {
 
 #[cfg(test)]
 mod test {
-    use super::{parse, Ast, Command, Pipeline, RunIf, Span, Term, Word};
+    use super::{
+        parse, Assignment, Ast, Command, HereDoc, Initializer, ParseError, Pipeline, Redirection,
+        RedirectionDirection, RedirectionType, RunIf, Span, Term, Word,
+    };
 
     macro_rules! literal_word_vec {
         ($($x:expr), *) => {
@@ -565,6 +1266,7 @@ mod test {
                     code: "ls -G /tmp".into(),
                     pipelines: vec![Pipeline {
                         run_if: RunIf::Always,
+                        time: false,
                         commands: vec![Command::SimpleCommand {
                             argv: literal_word_vec!["ls", "-G", "/tmp"],
                             redirects: vec![],
@@ -572,8 +1274,262 @@ mod test {
                         }],
                     }],
                     background: false,
+                    line: 1,
+                }],
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_assignments() {
+        assert_eq!(
+            parse("FOO=bar"),
+            Ok(Ast {
+                terms: vec![Term {
+                    code: "FOO=bar".into(),
+                    pipelines: vec![Pipeline {
+                        run_if: RunIf::Always,
+                        time: false,
+                        commands: vec![Command::Assignment {
+                            assignments: vec![Assignment {
+                                name: "FOO".into(),
+                                initializer: Initializer::String(Word(vec![Span::Literal(
+                                    "bar".into()
+                                )])),
+                                index: None,
+                            }],
+                        }],
+                    }],
+                    background: false,
+                    line: 1,
+                }],
+            })
+        );
+
+        assert_eq!(
+            parse("RAILS_ENV=prod rails s"),
+            Ok(Ast {
+                terms: vec![Term {
+                    code: "RAILS_ENV=prod rails s".into(),
+                    pipelines: vec![Pipeline {
+                        run_if: RunIf::Always,
+                        time: false,
+                        commands: vec![Command::SimpleCommand {
+                            argv: literal_word_vec!["rails", "s"],
+                            redirects: vec![],
+                            assignments: vec![Assignment {
+                                name: "RAILS_ENV".into(),
+                                initializer: Initializer::String(Word(vec![Span::Literal(
+                                    "prod".into()
+                                )])),
+                                index: None,
+                            }],
+                        }],
+                    }],
+                    background: false,
+                    line: 1,
                 }],
             })
         );
     }
+
+    #[test]
+    pub fn test_if_command() {
+        let term = |code: &str| Term {
+            code: code.into(),
+            pipelines: vec![Pipeline {
+                run_if: RunIf::Always,
+                time: false,
+                commands: vec![Command::SimpleCommand {
+                    argv: code
+                        .split(' ')
+                        .map(|w| Word(vec![Span::Literal(w.into())]))
+                        .collect(),
+                    redirects: vec![],
+                    assignments: vec![],
+                }],
+            }],
+            background: false,
+            line: 1,
+        };
+
+        assert_eq!(
+            parse("if a; then b; elif c; then d; else e; fi"),
+            Ok(Ast {
+                terms: vec![Term {
+                    code: "if a; then b; elif c; then d; else e; fi".into(),
+                    pipelines: vec![Pipeline {
+                        run_if: RunIf::Always,
+                        time: false,
+                        commands: vec![Command::If {
+                            condition: vec![term("a")],
+                            then_part: vec![term("b")],
+                            elif_parts: vec![(vec![term("c")], vec![term("d")])],
+                            else_part: Some(vec![term("e")]),
+                        }],
+                    }],
+                    background: false,
+                    line: 1,
+                }],
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_function_definition() {
+        assert_eq!(
+            parse("greet() { echo hello; }"),
+            Ok(Ast {
+                terms: vec![Term {
+                    code: "greet() { echo hello; }".into(),
+                    pipelines: vec![Pipeline {
+                        run_if: RunIf::Always,
+                        time: false,
+                        commands: vec![Command::FunctionDefinition {
+                            name: "greet".into(),
+                            body: Box::new(Command::Group {
+                                terms: vec![Term {
+                                    code: "echo hello".into(),
+                                    pipelines: vec![Pipeline {
+                                        run_if: RunIf::Always,
+                                        time: false,
+                                        commands: vec![Command::SimpleCommand {
+                                            argv: literal_word_vec!["echo", "hello"],
+                                            redirects: vec![],
+                                            assignments: vec![],
+                                        }],
+                                    }],
+                                    background: false,
+                                    line: 1,
+                                }],
+                            }),
+                        }],
+                    }],
+                    background: false,
+                    line: 1,
+                }],
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_redirect_to_fd() {
+        assert_eq!(
+            parse("ls 2>&1"),
+            Ok(Ast {
+                terms: vec![Term {
+                    code: "ls 2>&1".into(),
+                    pipelines: vec![Pipeline {
+                        run_if: RunIf::Always,
+                        time: false,
+                        commands: vec![Command::SimpleCommand {
+                            argv: literal_word_vec!["ls"],
+                            redirects: vec![Redirection {
+                                fd: 2,
+                                direction: RedirectionDirection::Output,
+                                target: RedirectionType::Fd(1),
+                            }],
+                            assignments: vec![],
+                        }],
+                    }],
+                    background: false,
+                    line: 1,
+                }],
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_heredoc() {
+        assert_eq!(
+            parse("cat <<-'EOF'\n\thello $name\n\tEOF\n"),
+            Ok(Ast {
+                terms: vec![Term {
+                    code: "cat <<-'EOF'".into(),
+                    pipelines: vec![Pipeline {
+                        run_if: RunIf::Always,
+                        time: false,
+                        commands: vec![Command::SimpleCommand {
+                            argv: literal_word_vec!["cat"],
+                            redirects: vec![Redirection {
+                                fd: 0,
+                                direction: RedirectionDirection::Input,
+                                target: RedirectionType::HereDoc(HereDoc {
+                                    body: Word(vec![Span::Literal("hello $name\n".into())]),
+                                    strip_tabs: true,
+                                    quoted: true,
+                                }),
+                            }],
+                            assignments: vec![],
+                        }],
+                    }],
+                    background: false,
+                    line: 1,
+                }],
+            })
+        );
+    }
+
+    #[test]
+    pub fn test_break_and_continue() {
+        let command = |script: &str| {
+            let ast = parse(script).unwrap();
+            ast.terms[0].pipelines[0].commands[0].clone()
+        };
+        assert_eq!(command("break"), Command::Break { count: 1 });
+        assert_eq!(command("break 2"), Command::Break { count: 2 });
+        assert_eq!(command("continue"), Command::Continue { count: 1 });
+        assert_eq!(
+            command("continue 99999999999999999999"),
+            Command::Continue { count: usize::MAX }
+        );
+    }
+
+    #[test]
+    pub fn test_brace_range() {
+        let span = |script: &str| match &parse(script).unwrap().terms[0].pipelines[0].commands[0] {
+            Command::SimpleCommand { argv, .. } => argv[1].spans()[0].clone(),
+            _ => unreachable!(),
+        };
+
+        // A range is expanded later: it's kept small in the AST.
+        assert_eq!(
+            span("echo {1..1000000000..-2}"),
+            Span::BraceRange {
+                from: "1".into(),
+                to: "1000000000".into(),
+                step: 2,
+                source: "{1..1000000000..-2}".into(),
+            }
+        );
+        assert_eq!(span("echo {1..a}"), Span::Literal("{1..a}".into()));
+    }
+
+    #[test]
+    pub fn test_unsupported_constructs() {
+        let unsupported =
+            |name: &str| Err(ParseError::Fatal(format!("{}: not supported yet", name)));
+        assert_eq!(parse("for x in a b; do echo $x; done"), unsupported("for"));
+        assert_eq!(
+            parse("for ((i = 0; i < 3; i++)); do echo; done"),
+            unsupported("for")
+        );
+        assert_eq!(parse("(cd /tmp)"), unsupported("subshell"));
+        assert_eq!(parse("[[ a == b ]]"), unsupported("[[ ... ]]"));
+        assert_eq!(
+            parse("diff <(ls) <(ls -a)"),
+            unsupported("process substitution")
+        );
+        assert_eq!(parse("echo `ls`"), unsupported("`...`"));
+        assert_eq!(parse("echo \"$(echo `ls`)\""), unsupported("`...`"));
+    }
+
+    #[test]
+    pub fn test_bad_file_descriptors() {
+        let bad_fd = |fd: &str| Err(ParseError::Fatal(format!("{}: bad file descriptor", fd)));
+        assert_eq!(parse("echo 2>&99999999999"), bad_fd("99999999999"));
+        assert_eq!(parse("echo 4294967297>&2"), bad_fd("4294967297"));
+        assert_eq!(parse("echo 4294967297> /dev/null"), bad_fd("4294967297"));
+        assert!(parse("echo 2>&1 3> /dev/null").is_ok());
+    }
 }
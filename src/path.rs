@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 use std::fs::read_dir;
+use std::io;
+use std::path::{Path, PathBuf};
 
 pub struct PathTable {
     /// `$PATH`
@@ -47,3 +49,72 @@ impl PathTable {
         self.table.get(cmd).map(String::as_str)
     }
 }
+
+/// An entry returned by `DirReader::read_dir`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub path: PathBuf,
+    pub is_dir: bool,
+}
+
+/// Entries in a directory, read lazily.
+pub type DirEntries<'a> = Box<dyn Iterator<Item = io::Result<DirEntry>> + 'a>;
+
+/// Lists directories. Completion goes through this trait instead of `std::fs`
+/// so that it can be tested against an in-memory tree.
+pub trait DirReader {
+    fn read_dir(&self, dir: &Path) -> io::Result<DirEntries<'_>>;
+    fn is_dir(&self, path: &Path) -> bool;
+}
+
+/// Reads the real filesystem.
+pub struct FsDirReader;
+
+impl DirReader for FsDirReader {
+    fn read_dir(&self, dir: &Path) -> io::Result<DirEntries<'_>> {
+        let entries = read_dir(dir)?.map(|entry| {
+            let entry = entry?;
+            let is_dir = entry.file_type().map(|ty| ty.is_dir()).unwrap_or(false);
+            Ok(DirEntry {
+                path: entry.path(),
+                is_dir,
+            })
+        });
+
+        Ok(Box::new(entries))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+}
+
+/// "/home/username/path/to" -> "~/path/to"
+pub fn abbreviate_home(path: &str) -> String {
+    if let Some(home_dir) = dirs::home_dir() {
+        if let Some(rest) = path.strip_prefix(home_dir.to_string_lossy().as_ref()) {
+            if rest.is_empty() || rest.starts_with('/') {
+                return format!("~{}", rest);
+            }
+        }
+    }
+
+    path.to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn abbreviate_home_dir() {
+        let home_dir = dirs::home_dir().unwrap();
+        let home_dir = home_dir.to_string_lossy();
+        assert_eq!(abbreviate_home(&home_dir), "~");
+        assert_eq!(abbreviate_home(&format!("{}/src", home_dir)), "~/src");
+        assert_eq!(
+            abbreviate_home(&format!("{}-other", home_dir)),
+            format!("{}-other", home_dir)
+        );
+    }
+}
@@ -0,0 +1,259 @@
+//! Shell patterns (`*`, `?`, and `[...]`) used in pathname expansion and `case`.
+use crate::path::DirReader;
+
+use std::path::Path;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    Char(char),
+    AnyChar,
+    AnyString,
+    /// `[a-z]` or `[!0-9]`.
+    Bracket {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Token {
+    fn matches(&self, ch: char) -> bool {
+        match self {
+            Token::Char(c) => *c == ch,
+            Token::AnyChar => true,
+            Token::AnyString => false,
+            Token::Bracket { negated, ranges } => {
+                ranges.iter().any(|(from, to)| *from <= ch && ch <= *to) != *negated
+            }
+        }
+    }
+}
+
+fn tokenize(pattern: &str) -> Vec<Token> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let token = match chars[i] {
+            '*' => Token::AnyString,
+            '?' => Token::AnyChar,
+            '\\' if i + 1 < chars.len() => {
+                i += 1;
+                Token::Char(chars[i])
+            }
+            // `[` without the closing `]` is a literal.
+            '[' => match parse_bracket(&chars[i + 1..]) {
+                Some((token, len)) => {
+                    i += len;
+                    token
+                }
+                None => Token::Char('['),
+            },
+            ch => Token::Char(ch),
+        };
+        tokens.push(token);
+        i += 1;
+    }
+
+    tokens
+}
+
+/// Parses a bracket expression after `[`. Returns the token and the number of
+/// characters consumed (including `]`). A `]` right after `[` or `[!` is a
+/// member of the set.
+fn parse_bracket(chars: &[char]) -> Option<(Token, usize)> {
+    let negated = matches!(chars.first(), Some('!' | '^'));
+    let mut i = if negated { 1 } else { 0 };
+    let mut ranges = Vec::new();
+    loop {
+        let mut ch = *chars.get(i)?;
+        if ch == ']' && !ranges.is_empty() {
+            break;
+        }
+
+        if ch == '\\' {
+            i += 1;
+            ch = *chars.get(i)?;
+        }
+
+        match (chars.get(i + 1), chars.get(i + 2)) {
+            (Some('-'), Some(to)) if *to != ']' => {
+                ranges.push((ch, *to));
+                i += 3;
+            }
+            _ => {
+                ranges.push((ch, ch));
+                i += 1;
+            }
+        }
+    }
+
+    Some((Token::Bracket { negated, ranges }, i + 1))
+}
+
+/// Escapes `s` so that it matches itself literally.
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for ch in s.chars() {
+        if matches!(ch, '*' | '?' | '[' | '\\') {
+            escaped.push('\\');
+        }
+
+        escaped.push(ch);
+    }
+
+    escaped
+}
+
+/// Returns `true` if `pattern` contains unescaped `*`, `?`, or `[...]`.
+pub fn has_glob(pattern: &str) -> bool {
+    tokenize(pattern)
+        .iter()
+        .any(|token| !matches!(token, Token::Char(_)))
+}
+
+/// Returns `true` if the whole `text` matches `pattern`.
+pub fn match_pattern(pattern: &str, text: &str) -> bool {
+    let pattern = tokenize(pattern);
+    let text: Vec<char> = text.chars().collect();
+
+    // The position to retry from when a match after the last `*` fails.
+    let mut backtrack: Option<(usize, usize)> = None;
+    let (mut p, mut t) = (0, 0);
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(Token::AnyString) => {
+                backtrack = Some((p + 1, t));
+                p += 1;
+                continue;
+            }
+            Some(token) if token.matches(text[t]) => {
+                p += 1;
+                t += 1;
+                continue;
+            }
+            _ => (),
+        }
+
+        match backtrack {
+            // Let the `*` consume one more character.
+            Some((star_p, star_t)) => {
+                backtrack = Some((star_p, star_t + 1));
+                p = star_p;
+                t = star_t + 1;
+            }
+            None => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|token| *token == Token::AnyString)
+}
+
+/// Expands `pattern` into matching paths in alphabetical order. Like other
+/// shells, `*` and `?` don't match a leading `.` in file names.
+pub fn glob(reader: &dyn DirReader, pattern: &str) -> Vec<String> {
+    let only_dirs = pattern.ends_with('/');
+    let mut paths = vec![if pattern.starts_with('/') {
+        "/".to_owned()
+    } else {
+        String::new()
+    }];
+
+    for component in pattern.split('/').filter(|c| !c.is_empty()) {
+        let is_glob = has_glob(component);
+        let mut next = Vec::new();
+        for dir in &paths {
+            let entries = match reader.read_dir(Path::new(if dir.is_empty() { "." } else { dir })) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+
+            // Skip entries which can't be read (e.g. removed meanwhile).
+            for entry in entries.flatten() {
+                let name = match entry.path.file_name() {
+                    Some(name) => name.to_string_lossy().into_owned(),
+                    None => continue,
+                };
+
+                if is_glob && name.starts_with('.') && !component.starts_with('.') {
+                    continue;
+                }
+
+                if match_pattern(component, &name) {
+                    next.push(format!("{}{}/", dir, name));
+                }
+            }
+        }
+
+        paths = next;
+    }
+
+    let mut paths: Vec<String> = paths
+        .into_iter()
+        .filter(|path| !only_dirs || reader.is_dir(Path::new(path)))
+        .map(|path| {
+            if only_dirs || path == "/" {
+                path
+            } else {
+                path.trim_end_matches('/').to_owned()
+            }
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::FsDirReader;
+    use crate::shell::temp_path;
+
+    #[test]
+    fn match_patterns() {
+        assert!(match_pattern("*.rs", "main.rs"));
+        assert!(match_pattern("*", ""));
+        assert!(match_pattern("a*b*c", "aXbYbZc"));
+        assert!(match_pattern("?.txt", "a.txt"));
+        assert!(match_pattern("\\*", "*"));
+        assert!(!match_pattern("\\*", "a"));
+        assert!(!match_pattern("*.rs", "main.rc"));
+        assert!(!match_pattern("?", ""));
+        assert!(!match_pattern("abc", "ab"));
+        assert!(match_pattern("[a-c]x", "bx"));
+        assert!(!match_pattern("[a-c]x", "dx"));
+        assert!(match_pattern("[!a-c]x", "dx"));
+        assert!(match_pattern("[]]", "]"));
+        assert!(match_pattern("[ab", "[ab"));
+        assert!(has_glob("a*"));
+        assert!(has_glob("[ab]"));
+        assert!(!has_glob("a\\*"));
+        assert_eq!(escape("a*b?c\\[d]"), "a\\*b\\?c\\\\\\[d]");
+    }
+
+    #[test]
+    fn glob_files() {
+        let dir = temp_path("glob");
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        for name in ["a.txt", "b.txt", "c.rs", ".hidden.txt", "sub/d.txt"] {
+            std::fs::write(dir.join(name), "").unwrap();
+        }
+
+        let dir_str = escape(&dir.to_string_lossy());
+        let expand = |pattern: &str| -> Vec<String> {
+            glob(&FsDirReader, &format!("{}/{}", dir_str, pattern))
+                .iter()
+                .map(|path| path[dir_str.len() + 1..].to_owned())
+                .collect()
+        };
+
+        assert_eq!(expand("*.txt"), vec!["a.txt", "b.txt"]);
+        assert_eq!(expand(".*.txt"), vec![".hidden.txt"]);
+        assert_eq!(expand("?.rs"), vec!["c.rs"]);
+        assert_eq!(expand("*/*.txt"), vec!["sub/d.txt"]);
+        assert_eq!(expand("*/"), vec!["sub/"]);
+        assert_eq!(expand("sub/d.txt"), vec!["sub/d.txt"]);
+        assert!(expand("*.md").is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,21 +1,35 @@
 use crate::builtins::{BuiltinCommandContext, BuiltinCommandError};
-use crate::eval::evaluate_initializer;
+use crate::eval::{evaluate_initializer, EvalError};
+use crate::expand::{expand_redirection_target, expand_word_into_string};
 use crate::fd_file::FdFile;
 use crate::parser;
 use crate::shell::Shell;
 use crate::variable::Value;
 
-use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+use nix::fcntl::{fcntl, open, FcntlArg, OFlag};
+use nix::sys::signal::{kill, sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+use nix::sys::stat::Mode;
 use nix::sys::termios::{tcgetattr, tcsetattr, SetArg::TCSADRAIN, Termios};
 use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
-use nix::unistd::{execv, fork, getpid, setpgid, tcsetpgrp, ForkResult, Pid};
+use nix::unistd::{
+    close, dup2, execv, fork, getpid, pipe, setpgid, sysconf, tcsetpgrp, write, ForkResult, Pid,
+    SysconfVar,
+};
 use std::cell::RefCell;
 use std::ffi::CString;
 use std::fmt;
+use std::io::Write;
 use std::os::unix::io::RawFd;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use tracing::debug;
 
+/// The size of a here document which can be written into a pipe at once.
+const HEREDOC_PIPE_BUF: usize = 4096;
+
+/// Set when the interactive shell receives SIGINT (Ctrl-C).
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
 /// The process execution environment.
 #[derive(Debug, Copy, Clone)]
 pub struct Context {
@@ -33,10 +47,15 @@ pub struct Context {
 pub enum ExitStatus {
     ExitedWith(i32),
     Running(Pid),
-    // TODO: support noexec
+    /// `return` is called in a sourced script.
+    Return,
+    /// `break N`: exits N enclosing loops.
+    Break(usize),
+    /// `continue N`: exits N - 1 enclosing loops and resumes the next one.
+    Continue(usize),
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct JobId(usize);
 
 impl JobId {
@@ -73,6 +92,11 @@ impl Job {
         }
     }
 
+    #[inline]
+    pub fn id(&self) -> JobId {
+        self.id
+    }
+
     pub fn completed(&self, shell: &Shell) -> bool {
         self.processes.iter().all(|pid| {
             let state = shell.get_process_state(*pid).unwrap();
@@ -97,39 +121,89 @@ pub enum ProcessState {
     Stopped(Pid),
 }
 
-pub fn run_in_foreground(shell: &mut Shell, job: &Rc<Job>) -> ProcessState {
+pub fn run_in_foreground(shell: &mut Shell, job: &Rc<Job>) -> Result<ProcessState, EvalError> {
     debug!("run_in_foreground");
+    if !shell.interactive() {
+        // Job control is disabled. Don't touch the terminal.
+        return wait_for_job(shell, job);
+    }
+
     shell.last_fore_job = Some(job.clone());
     set_terminal_process_group(job.pgid);
 
     let status = wait_for_job(shell, job);
 
     // Save the current terminal status.
-    job.termios
-        .replace(Some(tcgetattr(0).expect("failed to tcgetattr")));
+    match tcgetattr(0) {
+        Ok(termios) => {
+            job.termios.replace(Some(termios));
+        }
+        Err(err) => debug!("failed to tcgetattr: {}", err),
+    }
 
     // Go back into the shell.
     set_terminal_process_group(shell.shell_pgid);
-    restore_terminal_attrs(shell.shell_termios.as_ref().unwrap());
+    if let Some(termios) = shell.shell_termios.as_ref() {
+        restore_terminal_attrs(termios);
+    }
 
     status
 }
 
+/// Gives the terminal to `pgid`. Does nothing (except logging) if stdin is
+/// not a terminal, e.g. `ENOTTY`.
 pub fn set_terminal_process_group(pgid: Pid) {
-    tcsetpgrp(0, pgid).expect("failed to tcsetpgrp");
+    if let Err(err) = tcsetpgrp(0, pgid) {
+        debug!("failed to tcsetpgrp: {}", err);
+    }
 }
 
+/// Restores the terminal attributes. Does nothing (except logging) if stdin
+/// is not a terminal.
 pub fn restore_terminal_attrs(termios: &Termios) {
-    tcsetattr(0, TCSADRAIN, termios).expect("failed to tcsetattr");
+    if let Err(err) = tcsetattr(0, TCSADRAIN, termios) {
+        debug!("failed to tcsetattr: {}", err);
+    }
+}
+
+/// Resumes a stopped job by sending `SIGCONT` to its processes.
+pub fn continue_job(shell: &mut Shell, job: &Rc<Job>) -> nix::Result<()> {
+    for pid in &job.processes {
+        if let Some(ProcessState::Stopped(_)) = shell.get_process_state(*pid) {
+            shell.set_process_state(*pid, ProcessState::Running);
+        }
+    }
+
+    signal_job(shell, job, Some(Signal::SIGCONT))
 }
 
-pub fn wait_for_job(shell: &mut Shell, job: &Rc<Job>) -> ProcessState {
+/// Sends `signal` to the process group of the job. `None` only checks if
+/// the processes exist like `kill -0`.
+pub fn signal_job(shell: &Shell, job: &Job, signal: Option<Signal>) -> nix::Result<()> {
+    if shell.interactive() {
+        kill(Pid::from_raw(-job.pgid.as_raw()), signal)
+    } else {
+        // Without job control, the processes share the shell's process group.
+        for pid in &job.processes {
+            if !matches!(
+                shell.get_process_state(*pid),
+                Some(ProcessState::Completed(_))
+            ) {
+                kill(*pid, signal)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn wait_for_job(shell: &mut Shell, job: &Rc<Job>) -> Result<ProcessState, EvalError> {
     loop {
         if job.completed(shell) || job.stopped(shell) {
             break;
         }
 
-        wait_for_any_process(shell, false);
+        wait_for_any_process(shell, false)?;
     }
 
     // Get the exit status of the last process.
@@ -141,24 +215,32 @@ pub fn wait_for_job(shell: &mut Shell, job: &Rc<Job>) -> ProcessState {
         Some(ProcessState::Completed(_)) => {
             // Remove the job and processes from the list.
             destroy_job(shell, job);
-            state.unwrap()
+            Ok(state.unwrap())
         }
         Some(ProcessState::Stopped(_)) => {
             smash_err!("[{}] Stopped: {}", job.id, job.cmd);
-            state.unwrap()
+            Ok(state.unwrap())
         }
         _ => unreachable!(),
     }
 }
 
-pub fn wait_for_any_process(shell: &mut Shell, no_block: bool) -> Option<Pid> {
+pub fn wait_for_any_process(shell: &mut Shell, no_block: bool) -> Result<Option<Pid>, EvalError> {
+    wait_for_process(shell, None, no_block)
+}
+
+fn wait_for_process(
+    shell: &mut Shell,
+    pid: Option<Pid>,
+    no_block: bool,
+) -> Result<Option<Pid>, EvalError> {
     let options = if no_block {
         WaitPidFlag::WUNTRACED | WaitPidFlag::WNOHANG
     } else {
         WaitPidFlag::WUNTRACED
     };
 
-    let result = waitpid(None, Some(options));
+    let result = waitpid(pid, Some(options));
     let (pid, state) = match result {
         Ok(WaitStatus::Exited(pid, status)) => {
             debug!("exited: pid={} status={}", pid, status);
@@ -171,15 +253,60 @@ pub fn wait_for_any_process(shell: &mut Shell, no_block: bool) -> Option<Pid> {
         Ok(WaitStatus::Stopped(pid, _signal)) => (pid, ProcessState::Stopped(pid)),
         Err(nix::errno::Errno::ECHILD) | Ok(WaitStatus::StillAlive) => {
             // No childs to be reported.
-            return None;
+            return Ok(None);
         }
         status => {
-            panic!("unexpected waitpid event: {:?}", status);
+            return Err(EvalError::UnexpectedWaitStatus(format!("{:?}", status)));
         }
     };
 
     shell.set_process_state(pid, state);
-    Some(pid)
+    Ok(Some(pid))
+}
+
+/// Reaps exited processes in jobs without blocking. Unlike `reap_jobs`, it
+/// keeps the jobs so that `jobs` can report them, and it doesn't touch other
+/// children (e.g. a command substitution being waited for).
+pub fn reap_job_processes(shell: &mut Shell) {
+    let pids: Vec<Pid> = shell
+        .jobs()
+        .values()
+        .flat_map(|job| job.processes.iter().copied())
+        .filter(|pid| shell.get_process_state(*pid) == Some(&ProcessState::Running))
+        .collect();
+
+    for pid in pids {
+        if let Err(err) = wait_for_process(shell, Some(pid), true) {
+            debug!("reap_job_processes: {}", err);
+        }
+    }
+}
+
+/// Reaps exited processes without blocking and returns the jobs that have
+/// completed. Their states are recorded in `shell` so that a job being waited
+/// for later (`wait_for_job`) still sees its exit status.
+pub fn reap_jobs(shell: &mut Shell) -> Vec<Rc<Job>> {
+    loop {
+        match wait_for_any_process(shell, true) {
+            Ok(Some(_)) => (),
+            Ok(None) => break,
+            Err(err) => {
+                debug!("reap_jobs: {}", err);
+                break;
+            }
+        }
+    }
+
+    let jobs: Vec<Rc<Job>> = shell.jobs_mut().values().cloned().collect();
+    let mut completed = Vec::new();
+    for job in jobs {
+        if job.completed(shell) {
+            destroy_job(shell, &job);
+            completed.push(job);
+        }
+    }
+
+    completed
 }
 
 pub fn destroy_job(shell: &mut Shell, job: &Rc<Job>) {
@@ -214,23 +341,62 @@ pub fn run_internal_command(
     stdin: RawFd,
     stdout: RawFd,
     stderr: RawFd,
-    _redirects: &[parser::Redirection],
+    redirects: &[parser::Redirection],
 ) -> anyhow::Result<ExitStatus> {
     let command = match crate::builtins::builtin_command(argv[0].as_str()) {
         Some(func) => func,
         _ => return Err(BuiltinCommandError::NotFound.into()),
     };
 
-    // TODO: support redirections
+    // Builtins run in the shell process: open redirection targets and pass
+    // them as the builtin's stdio instead of touching the shell's own fds.
+    // `exec` is an exception; it applies redirections to the shell itself.
+    let mut stdio = [stdin, stdout, stderr];
+    let mut opened_fds = Vec::new();
+    if argv[0] != "exec" {
+        for redirect in redirects {
+            let fd = match redirect.target {
+                parser::RedirectionType::Fd(target_fd) => {
+                    stdio.get(target_fd).copied().unwrap_or(target_fd as RawFd)
+                }
+                _ => match open_redirection(shell, redirect) {
+                    Ok(fd) => {
+                        opened_fds.push(fd);
+                        fd
+                    }
+                    Err(err) => {
+                        writeln!(FdFile::new(stdio[2]), "{}: {}", shell.error_prefix(), err).ok();
+                        for fd in opened_fds {
+                            close(fd).ok();
+                        }
+
+                        return Ok(ExitStatus::ExitedWith(1));
+                    }
+                },
+            };
+
+            if let Some(stdio_fd) = stdio.get_mut(redirect.fd) {
+                *stdio_fd = fd;
+            }
+        }
+    }
 
+    let mut stdout = FdFile::new(stdio[1]);
+    let mut stderr = FdFile::new(stdio[2]);
     let result = command.run(&mut BuiltinCommandContext {
         argv,
         shell,
-        stdin: FdFile::new(stdin),
-        stdout: FdFile::new(stdout),
-        stderr: FdFile::new(stderr),
+        stdin: FdFile::new(stdio[0]),
+        stdout: &mut stdout,
+        stderr: &mut stderr,
+        stdio,
+        redirects,
     });
 
+    for fd in opened_fds {
+        close(fd).ok();
+    }
+
     Ok(result)
 }
 
@@ -238,62 +404,47 @@ pub fn run_external_command(
     shell: &mut Shell,
     ctx: &Context,
     argv: Vec<String>,
-    _redirects: &[parser::Redirection],
+    redirects: &[parser::Redirection],
     assignments: &[parser::Assignment],
 ) -> anyhow::Result<ExitStatus> {
-    // TODO: support redirections
-
     let argv0 = if argv[0].starts_with('/') || argv[0].starts_with("./") {
         CString::new(argv[0].as_str())?
     } else {
         match shell.path_table().lookup(&argv[0]) {
             Some(path) => CString::new(path)?,
             None => {
-                smash_err!("command not found `{}`", argv[0]);
+                writeln!(
+                    FdFile::new(ctx.stderr),
+                    "{}: {}: command not found",
+                    shell.error_prefix(),
+                    argv[0]
+                )
+                .ok();
                 return Ok(ExitStatus::ExitedWith(1));
             }
         }
     };
 
     let mut args = Vec::new();
-    for arg in argv {
-        args.push(CString::new(arg)?);
+    for arg in &argv {
+        args.push(CString::new(arg.as_str())?);
     }
 
     // Spawn a child.
     match unsafe { fork() }.expect("failed to fork") {
         ForkResult::Parent { child } => Ok(ExitStatus::Running(child)),
         ForkResult::Child => {
-            // Create or join a process group.
-            if ctx.interactive {
-                let pid = getpid();
-                let pgid = match ctx.pgid {
-                    Some(pgid) => {
-                        setpgid(pid, pgid).expect("failed to setpgid");
-                        pgid
-                    }
-                    None => {
-                        setpgid(pid, pid).expect("failed to setpgid");
-                        pid
-                    }
-                };
-
-                if !ctx.background {
-                    set_terminal_process_group(pgid);
-                    restore_terminal_attrs(shell.shell_termios.as_ref().unwrap());
-                }
-
-                // Accept job-control-related signals (refer https://www.gnu.org/software/libc/manual/html_node/Launching-Jobs.html)
-                let action = SigAction::new(SigHandler::SigDfl, SaFlags::empty(), SigSet::empty());
-                unsafe {
-                    sigaction(Signal::SIGINT, &action).expect("failed to sigaction");
-                    sigaction(Signal::SIGQUIT, &action).expect("failed to sigaction");
-                    sigaction(Signal::SIGTSTP, &action).expect("failed to sigaction");
-                    sigaction(Signal::SIGTTIN, &action).expect("failed to sigaction");
-                    sigaction(Signal::SIGTTOU, &action).expect("failed to sigaction");
-                    sigaction(Signal::SIGCHLD, &action).expect("failed to sigaction");
-                }
-            }
+            join_process_group(shell, ctx);
+
+            // Connect stdin/stdout/stderr to the ones given by the caller (e.g.
+            // a pipe in a pipeline or a command substitution).
+            move_fd(ctx.stdin, 0);
+            move_fd(ctx.stdout, 1);
+            move_fd(ctx.stderr, 2);
+            close_inherited_fds();
+            // Report errors from now on to the command's stderr.
+            shell.set_stdio(2, 2);
+            redirect_fds(shell, redirects);
 
             for name in shell.exported_names() {
                 if let Some(var) = shell.get(name) {
@@ -301,13 +452,20 @@ pub fn run_external_command(
                 }
             }
 
+            // Assignment prefixes (e.g. `RAILS_ENV=production rails server`)
+            // are set only in the command's environment.
             for assignment in assignments {
-                let value = evaluate_initializer(shell, &assignment.initializer)
-                    .expect("failed to evaluate the initializer");
-                match value {
-                    Value::String(s) => std::env::set_var(&assignment.name, s),
-                    Value::Array(_) => {
-                        smash_err!("Array assignments in a command is not supported.");
+                match evaluate_initializer(shell, &assignment.initializer) {
+                    Ok(Value::String(s)) => std::env::set_var(&assignment.name, s),
+                    Err(err) => {
+                        shell.error(format_args!("{}: {}", assignment.name, err));
+                        std::process::exit(1);
+                    }
+                    Ok(Value::Array(_)) => {
+                        shell.error(format_args!(
+                            "{}: array assignments in a command is not supported",
+                            assignment.name
+                        ));
                         std::process::exit(1);
                     }
                 }
@@ -319,14 +477,237 @@ pub fn run_external_command(
                     unreachable!();
                 }
                 Err(nix::errno::Errno::EACCES) => {
-                    smash_err!("Failed to exec {:?} (EACCESS). chmod(1) may help.", argv0);
+                    shell.error(format_args!("{}: permission denied", argv[0]));
                     std::process::exit(1);
                 }
                 Err(err) => {
-                    smash_err!("Failed to exec {:?} ({})", argv0, err);
+                    shell.error(format_args!("{}: {}", argv[0], err.desc()));
                     std::process::exit(1);
                 }
             }
         }
     }
 }
+
+/// Creates or joins the process group of a job and restores the default
+/// signal handlers if job control is enabled. It must be called in a forked
+/// child.
+pub fn join_process_group(shell: &Shell, ctx: &Context) {
+    if ctx.interactive {
+        let pid = getpid();
+        let pgid = match ctx.pgid {
+            Some(pgid) => {
+                setpgid(pid, pgid).expect("failed to setpgid");
+                pgid
+            }
+            None => {
+                setpgid(pid, pid).expect("failed to setpgid");
+                pid
+            }
+        };
+
+        if !ctx.background {
+            set_terminal_process_group(pgid);
+            if let Some(termios) = shell.shell_termios.as_ref() {
+                restore_terminal_attrs(termios);
+            }
+        }
+
+        // Accept job-control-related signals (refer https://www.gnu.org/software/libc/manual/html_node/Launching-Jobs.html)
+        let action = SigAction::new(SigHandler::SigDfl, SaFlags::empty(), SigSet::empty());
+        unsafe {
+            sigaction(Signal::SIGINT, &action).expect("failed to sigaction");
+            sigaction(Signal::SIGQUIT, &action).expect("failed to sigaction");
+            sigaction(Signal::SIGTSTP, &action).expect("failed to sigaction");
+            sigaction(Signal::SIGTTIN, &action).expect("failed to sigaction");
+            sigaction(Signal::SIGTTOU, &action).expect("failed to sigaction");
+            sigaction(Signal::SIGCHLD, &action).expect("failed to sigaction");
+        }
+    }
+}
+
+fn move_fd(src: RawFd, dst: RawFd) {
+    if src != dst {
+        dup2(src, dst).expect("failed to dup2");
+    }
+}
+
+/// Opens redirection targets and connects them to the specified fds. Exits
+/// the process on failure; it must be called in a forked child.
+fn redirect_fds(shell: &mut Shell, redirects: &[parser::Redirection]) {
+    for redirect in redirects {
+        // `2>&1`: make `fd` refer to the same file as `target_fd`.
+        if let parser::RedirectionType::Fd(target_fd) = redirect.target {
+            if let Err(err) = dup2(target_fd as RawFd, redirect.fd as RawFd) {
+                shell.error(format_args!("{}: {}", target_fd, err.desc()));
+                std::process::exit(1);
+            }
+
+            continue;
+        }
+
+        let fd = match open_redirection(shell, redirect) {
+            Ok(fd) => fd,
+            Err(err) => {
+                shell.error(err);
+                std::process::exit(1);
+            }
+        };
+
+        move_fd(fd, redirect.fd as RawFd);
+        if fd != redirect.fd as RawFd {
+            close(fd).ok();
+        }
+    }
+}
+
+/// Opens the file or the here document which `redirect` points to. Returns an
+/// error message on failure.
+pub fn open_redirection(
+    shell: &mut Shell,
+    redirect: &parser::Redirection,
+) -> Result<RawFd, String> {
+    let word = match &redirect.target {
+        parser::RedirectionType::File(word) => word,
+        parser::RedirectionType::HereDoc(heredoc) => {
+            let body =
+                expand_word_into_string(shell, &heredoc.body).map_err(|err| err.to_string())?;
+            return heredoc_pipe(&body).map_err(|err| format!("here document: {}", err.desc()));
+        }
+        parser::RedirectionType::Fd(_) => unreachable!("fd redirections are handled by callers"),
+    };
+
+    let path = expand_redirection_target(shell, word).map_err(|err| err.to_string())?;
+    let flags = match redirect.direction {
+        parser::RedirectionDirection::Input => OFlag::O_RDONLY,
+        parser::RedirectionDirection::Output => OFlag::O_CREAT | OFlag::O_WRONLY | OFlag::O_TRUNC,
+        parser::RedirectionDirection::Append => OFlag::O_CREAT | OFlag::O_WRONLY | OFlag::O_APPEND,
+    };
+
+    open(path.as_str(), flags, Mode::from_bits_truncate(0o644))
+        .map_err(|err| format!("{}: {}", path, err.desc()))
+}
+
+extern "C" fn handle_sigint(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Catches SIGINT instead of ignoring it so that Ctrl-C can stop a loop run
+/// in the shell process (e.g. `while true; do :; done`).
+pub fn catch_sigint() {
+    let action = SigAction::new(
+        SigHandler::Handler(handle_sigint),
+        SaFlags::SA_RESTART,
+        SigSet::empty(),
+    );
+    unsafe {
+        sigaction(Signal::SIGINT, &action).expect("failed to sigaction");
+    }
+}
+
+/// Returns `true` if SIGINT has been received since `reset_interrupted`.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+pub fn reset_interrupted() {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+}
+
+/// Returns the read end of a pipe which supplies `body` (a here document). A
+/// large body is written by a forked process so that we don't block on a full
+/// pipe before the command starts reading it.
+pub fn heredoc_pipe(body: &str) -> nix::Result<RawFd> {
+    let (read_end, write_end) = pipe()?;
+    if body.len() <= HEREDOC_PIPE_BUF {
+        write_all(write_end, body.as_bytes());
+    } else {
+        match unsafe { fork() } {
+            Ok(ForkResult::Parent { child }) => {
+                // The child exits right after forking the writer, which is
+                // then reparented to init. No zombie is left behind.
+                waitpid(child, None).ok();
+            }
+            Ok(ForkResult::Child) => {
+                close(read_end).ok();
+                if let Ok(ForkResult::Child) = unsafe { fork() } {
+                    write_all(write_end, body.as_bytes());
+                }
+
+                // Don't run the shell's exit handlers or flush its buffers.
+                unsafe { libc::_exit(0) };
+            }
+            Err(err) => {
+                close(read_end).ok();
+                close(write_end).ok();
+                return Err(err);
+            }
+        }
+    }
+
+    close(write_end).ok();
+    Ok(read_end)
+}
+
+fn write_all(fd: RawFd, mut buf: &[u8]) {
+    while !buf.is_empty() {
+        match write(fd, buf) {
+            Ok(len) => buf = &buf[len..],
+            Err(nix::errno::Errno::EINTR) => (),
+            Err(_) => break,
+        }
+    }
+}
+
+/// Closes file descriptors inherited from the shell (e.g. pipes connected to
+/// other commands in the pipeline) except stdin, stdout, and stderr. Otherwise,
+/// the command may keep a write end of a pipe open and the reader never sees EOF.
+fn close_inherited_fds() {
+    // This runs between fork and exec: don't allocate or read directories.
+    #[cfg(target_os = "linux")]
+    if unsafe { libc::syscall(libc::SYS_close_range, 3, libc::c_uint::MAX, 0) } == 0 {
+        return;
+    }
+
+    let max_fd = match sysconf(SysconfVar::OPEN_MAX) {
+        Ok(Some(max_fd)) => max_fd as RawFd,
+        _ => 1024,
+    };
+
+    for fd in 3..max_fd {
+        if fcntl(fd, FcntlArg::F_GETFD).is_ok() {
+            close(fd).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shell::{temp_path, Shell};
+    use crate::ExitStatus;
+
+    #[test]
+    fn builtin_redirection() {
+        let out = temp_path("builtin.txt");
+
+        let mut shell = Shell::new_for_test();
+        shell.add_alias("ll", "ls -l".to_owned());
+        let (status, stdout, _) = shell.run_capture(&format!("alias > {}", out.display()));
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "");
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "alias ll='ls -l'\n");
+
+        // The shell's own stdout is not affected.
+        let (_, stdout, _) = shell.run_capture("alias");
+        assert_eq!(stdout, "alias ll='ls -l'\n");
+
+        let (status, _, stderr) = shell.run_capture("alias > /nonexistent/file");
+        assert_eq!(status, ExitStatus::ExitedWith(1));
+        assert_eq!(
+            stderr,
+            "smash: /nonexistent/file: No such file or directory\n"
+        );
+
+        std::fs::remove_file(&out).unwrap();
+    }
+}
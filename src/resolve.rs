@@ -1,39 +1,143 @@
 use crate::parser::{Span, Word};
 use crate::shell::Shell;
+use std::collections::HashSet;
 use tracing::debug;
 
+/// The maximum number of nested alias expansions.
+const MAX_ALIAS_DEPTH: usize = 32;
+
+/// Expands aliases in the first word repeatedly. An alias referring to itself
+/// (e.g. `alias ls='ls -G'`) is expanded only once. Expansion stops with a
+/// warning on a cycle (e.g. `alias a=b; alias b=a`) or too deep nesting.
 pub fn resolve_alias(shell: &Shell, argv: &[Word]) -> Vec<Word> {
     debug!("aliases={:?}", shell.aliases());
+    let mut argv = argv.to_owned();
+    let mut visited = HashSet::new();
+    for _ in 0..MAX_ALIAS_DEPTH {
+        let name = match first_literal(&argv) {
+            Some(name) => name.to_owned(),
+            None => return argv,
+        };
+
+        let alias_str = match shell.lookup_alias(&name) {
+            Some(alias_str) => alias_str,
+            None => return argv,
+        };
+
+        if !visited.insert(name.clone()) {
+            shell.error(format_args!("warning: alias cycle detected at `{}'", name));
+            return argv;
+        }
+
+        argv = expand_alias(&alias_str, &argv);
+        if first_literal(&argv) == Some(name.as_str()) {
+            // The alias refers to the command with the same name.
+            return argv;
+        }
+    }
+
+    // The last expansion may have resolved the chain completely.
+    if let Some(name) = first_literal(&argv) {
+        if shell.lookup_alias(name).is_some() {
+            shell.error("warning: too many nested aliases");
+        }
+    }
+
     argv
-        // Get the first word.
-        .get(0)
-        // Get the first span in the first word.
-        .and_then(|word| word.spans().get(0))
-        // Make sure that the span is a literal (not parameters, etc.).
+}
+
+/// Returns the very first span if it is a literal (not parameters, etc.).
+fn first_literal(argv: &[Word]) -> Option<&str> {
+    argv.first()
+        .and_then(|word| word.spans().first())
         .and_then(|span| match span {
-            Span::Literal(lit) => Some(lit),
+            Span::Literal(lit) => Some(lit.as_str()),
             _ => None,
         })
-        // The very first span is literal. Search the created aliases.
-        .and_then(|lit| shell.lookup_alias(lit.as_str()))
-        .map(|alias_str| {
-            // Found the alias. Split the alias string by whitespace into words.
-            let mut alias_words: Vec<Word> = alias_str
-                .trim()
-                .split(' ')
-                .map(|w| {
-                    let span = Span::Literal(w.to_owned());
-                    Word(vec![span])
-                })
-                .collect();
-
-            // Append argv except the first word (alias name).
-            for arg in argv.iter().skip(1) {
-                alias_words.push(arg.clone());
-            }
-
-            alias_words
+}
+
+/// Replaces the first word of `argv` with `alias_str`.
+fn expand_alias(alias_str: &str, argv: &[Word]) -> Vec<Word> {
+    // Split the alias string by whitespace into words.
+    let mut alias_words: Vec<Word> = alias_str
+        .trim()
+        .split(' ')
+        .map(|w| {
+            let span = Span::Literal(w.to_owned());
+            Word(vec![span])
         })
-        // Failed to resolve alias. Return argv as it is.
-        .unwrap_or_else(|| argv.to_owned())
+        .collect();
+
+    // Append argv except the first word (alias name).
+    for arg in argv.iter().skip(1) {
+        alias_words.push(arg.clone());
+    }
+
+    alias_words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::temp_path;
+
+    fn words(argv: &[&str]) -> Vec<Word> {
+        argv.iter()
+            .map(|w| Word(vec![Span::Literal(w.to_string())]))
+            .collect()
+    }
+
+    #[test]
+    fn nested_aliases() {
+        let mut shell = Shell::new_for_test();
+        shell.add_alias("ll", "ls -l".to_owned());
+        shell.add_alias("ls", "ls -G".to_owned());
+        assert_eq!(
+            resolve_alias(&shell, &words(&["ll", "/tmp"])),
+            words(&["ls", "-G", "-l", "/tmp"])
+        );
+    }
+
+    #[test]
+    fn direct_alias_cycle() {
+        let mut shell = Shell::new_for_test();
+        shell.add_alias("a", "a x".to_owned());
+        assert_eq!(resolve_alias(&shell, &words(&["a"])), words(&["a", "x"]));
+    }
+
+    #[test]
+    fn indirect_alias_cycle() {
+        let mut shell = Shell::new_for_test();
+        shell.add_alias("a", "b 1".to_owned());
+        shell.add_alias("b", "c 2".to_owned());
+        shell.add_alias("c", "a 3".to_owned());
+        assert_eq!(
+            resolve_alias(&shell, &words(&["a", "0"])),
+            words(&["a", "3", "2", "1", "0"])
+        );
+    }
+
+    #[test]
+    fn deeply_nested_aliases() {
+        use std::os::unix::io::IntoRawFd;
+
+        let log = temp_path("alias.log");
+        let mut shell = Shell::new_for_test();
+        shell.set_stdio(2, std::fs::File::create(&log).unwrap().into_raw_fd());
+        for i in 0..MAX_ALIAS_DEPTH {
+            shell.add_alias(&format!("a{}", i), format!("a{}", i + 1));
+        }
+
+        assert_eq!(resolve_alias(&shell, &words(&["a0"])), words(&["a32"]));
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), "");
+
+        shell.add_alias("a32", "a33".to_owned());
+        assert_eq!(resolve_alias(&shell, &words(&["a0"])), words(&["a32"]));
+        assert_eq!(
+            std::fs::read_to_string(&log).unwrap(),
+            "smash: warning: too many nested aliases\n"
+        );
+
+        std::fs::remove_file(&log).unwrap();
+    }
 }
@@ -1,22 +1,38 @@
-use crate::eval::eval;
-use crate::history::History;
+use crate::eval::{call_function, eval};
+use crate::fd_file::FdFile;
+use crate::frecency::DirDatabase;
+use crate::history::{unix_time, History};
 use crate::parser;
 use crate::path::PathTable;
-use crate::process::{Job, JobId, ProcessState};
+use crate::process::{Context, Job, JobId, ProcessState};
 use crate::variable::{Frame, Value, Variable};
 use crate::ExitStatus;
 
 use nix::sys::termios::{tcgetattr, Termios};
-use nix::unistd::{getpid, Pid};
-use std::collections::{HashMap, HashSet};
+use nix::unistd::{close, getpid, pipe, Pid};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs::File;
-use std::io::Read;
-use std::os::unix::io::RawFd;
+use std::io::{Read, Write};
+use std::os::unix::io::{FromRawFd, RawFd};
 use std::path::Path;
 use std::path::PathBuf;
 use std::rc::Rc;
+use std::thread::JoinHandle;
 use tracing::debug;
 
+/// The maximum number of directories remembered for `cdh`.
+pub const CD_HISTORY_SIZE: usize = 16;
+
+/// Options which can be enabled by `set -o NAME`.
+pub const SHELL_OPTIONS: &[&str] = &["auto_pushd", "noexec", "noglob", "pushd_ignore_dups"];
+
+/// Returns a path in the temporary directory which is unique to the test
+/// process (e.g. `/tmp/smash-1234-exec.log`).
+#[cfg(test)]
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("smash-{}-{}", std::process::id(), name))
+}
+
 pub struct Shell {
     last_status: i32,
 
@@ -25,10 +41,32 @@ pub struct Shell {
     pub last_fore_job: Option<Rc<Job>>,
     states: HashMap<Pid, ProcessState>,
     pub shell_pgid: Pid,
+    /// The pid of the shell (`$$`). Unlike `getpid()`, it's not changed in
+    /// subshells.
+    pid: Pid,
+    /// The name of the shell or the script being run (`$0`).
+    arg0: String,
+    /// The pid of the most recent background job (`$!`).
+    last_background_pid: Option<Pid>,
+    /// The last argument of the previous simple command (`$_`).
+    last_arg: String,
+    /// The script file being run by `run_file` (including `source`), if any.
+    script_name: Option<String>,
+    /// The line number of the term being evaluated.
+    current_line: usize,
+    /// The state of the pseudo-random number generator for `$RANDOM`.
+    random_state: u32,
+    /// `$OPTIND` and the index of the next character in a grouped option
+    /// (e.g. `b` in `-abc`) for `getopts`.
+    getopts_charindex: (usize, usize),
     pub shell_termios: Option<Termios>,
     pid_job_mapping: HashMap<Pid, Rc<Job>>,
     jobs: HashMap<JobId, Rc<Job>>,
     cd_stack: Vec<String>,
+    /// Recently visited directories (the most recent first) for `cdh`.
+    cd_history: VecDeque<String>,
+    /// Visited directories for `j`.
+    dir_database: DirDatabase,
 
     /// Local scopes (variables declared with `local').
     frames: Vec<Frame>,
@@ -37,8 +75,34 @@ pub struct Shell {
 
     exported: HashSet<String>,
 
+    /// Defined functions. Functions have a namespace separate from variables.
+    functions: HashMap<String, Box<parser::Command>>,
+
     aliases: HashMap<String, String>,
 
+    /// Enabled shell options (`set -o NAME`).
+    options: HashSet<String>,
+
+    /// The current stdin/stdout/stderr for commands. Changed by `exec > file`.
+    stdio: [RawFd; 3],
+
+    /// Hook functions being called. Used to prevent infinite recursion (e.g.
+    /// `cd` in `chpwd`).
+    running_hooks: HashSet<String>,
+
+    /// Commands set by `trap` (e.g. `DEBUG` and `ERR`).
+    traps: HashMap<String, String>,
+    /// Traps being run. Used to prevent a trap from triggering itself.
+    running_traps: HashSet<String>,
+    /// The number of conditions (e.g. `if` and `while`) being evaluated.
+    /// The `ERR` trap is not run in them.
+    condition_depth: usize,
+    /// The number of loops being run. Used by `break` and `continue`.
+    loop_depth: usize,
+    /// Whether the status of the last pipeline has been reported to the
+    /// `ERR` trap. Used to report a failure in a function only once.
+    err_trapped: bool,
+
     history: History,
 }
 
@@ -51,43 +115,197 @@ impl Shell {
             last_fore_job: None,
             states: HashMap::new(),
             shell_pgid: getpid(),
+            pid: getpid(),
+            arg0: "smash".to_owned(),
+            last_background_pid: None,
+            last_arg: String::new(),
+            script_name: None,
+            current_line: 0,
+            random_state: (unix_time().subsec_nanos() ^ getpid().as_raw() as u32) | 1,
+            getopts_charindex: (1, 1),
             shell_termios: None,
             pid_job_mapping: HashMap::new(),
             jobs: HashMap::new(),
             cd_stack: Vec::new(),
+            cd_history: VecDeque::new(),
+            dir_database: DirDatabase::new(),
             frames: Vec::new(),
             global: Frame::new(),
             exported: HashSet::new(),
+            functions: HashMap::new(),
             aliases: HashMap::new(),
+            options: HashSet::new(),
+            stdio: [0, 1, 2],
+            running_hooks: HashSet::new(),
+            traps: HashMap::new(),
+            running_traps: HashSet::new(),
+            condition_depth: 0,
+            loop_depth: 0,
+            err_trapped: false,
             history: History::new(history_path),
         }
     }
 
+    /// Creates a shell for tests which finds external commands in `$PATH`
+    /// of the test process.
+    #[cfg(test)]
+    pub fn new_for_test() -> Shell {
+        let mut shell = Shell::new(Path::new("/dev/null"));
+        shell.set_string("PATH", std::env::var("PATH").unwrap_or_default());
+        shell
+    }
+
     #[inline]
     pub fn interactive(&self) -> bool {
         self.interactive
     }
 
+    #[inline]
+    pub fn pid(&self) -> Pid {
+        self.pid
+    }
+
+    #[inline]
+    pub fn arg0(&self) -> &str {
+        &self.arg0
+    }
+
+    pub fn set_arg0(&mut self, arg0: &str) {
+        self.arg0 = arg0.to_owned();
+    }
+
+    #[inline]
+    pub fn last_background_pid(&self) -> Option<Pid> {
+        self.last_background_pid
+    }
+
+    pub fn set_last_background_pid(&mut self, pid: Pid) {
+        self.last_background_pid = Some(pid);
+    }
+
+    #[inline]
+    pub fn last_arg(&self) -> &str {
+        &self.last_arg
+    }
+
+    pub fn set_last_arg(&mut self, arg: &str) {
+        self.last_arg = arg.to_owned();
+    }
+
+    pub fn set_current_line(&mut self, line: usize) {
+        self.current_line = line;
+    }
+
+    /// The line number of the term being evaluated (`$LINENO`).
+    pub fn current_line(&self) -> usize {
+        self.current_line
+    }
+
+    /// The script file being run, if any (`$BASH_SOURCE`).
+    pub fn script_name(&self) -> Option<&str> {
+        self.script_name.as_deref()
+    }
+
+    /// Returns the prefix of error messages: `smash` (`$0`) in the
+    /// interactive mode or `dev.sh: line 12` while running a script.
+    pub fn error_prefix(&self) -> String {
+        match &self.script_name {
+            Some(script_name) => format!("{}: line {}", script_name, self.current_line),
+            None => self.arg0.clone(),
+        }
+    }
+
+    /// Prints an error message to the current stderr.
+    pub fn error(&self, msg: impl std::fmt::Display) {
+        writeln!(
+            FdFile::new(self.stdio[2]),
+            "{}: {}",
+            self.error_prefix(),
+            msg
+        )
+        .ok();
+    }
+
+    /// Returns a pseudo-random integer between 0 and 32767 (`$RANDOM`).
+    pub fn random(&mut self) -> u16 {
+        // xorshift32
+        let mut x = self.random_state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.random_state = x;
+        (x >> 16) as u16 & 0x7fff
+    }
+
+    /// Returns the index of the next character in the `optind`-th argument
+    /// to be parsed by `getopts`. It's reset if `$OPTIND` has been changed
+    /// by the script.
+    pub fn getopts_charindex(&self, optind: usize) -> usize {
+        match self.getopts_charindex {
+            (last_optind, charindex) if last_optind == optind => charindex,
+            _ => 1,
+        }
+    }
+
+    pub fn set_getopts_charindex(&mut self, optind: usize, charindex: usize) {
+        self.getopts_charindex = (optind, charindex);
+    }
+
     pub fn path_table(&self) -> &PathTable {
         &self.path_table
     }
 
     pub fn run_file(&mut self, script_file: PathBuf) -> std::io::Result<ExitStatus> {
-        let mut f = File::open(script_file)?;
+        let stdio = self.stdio;
+        self.run_file_with_stdio(script_file, stdio)
+    }
+
+    /// Runs a script file in the given context (e.g. `source` in a pipeline).
+    pub fn run_file_with_stdio(
+        &mut self,
+        script_file: PathBuf,
+        stdio: [RawFd; 3],
+    ) -> std::io::Result<ExitStatus> {
+        let mut f = File::open(&script_file)?;
         let mut script = String::new();
         f.read_to_string(&mut script)?;
-        Ok(self.run_script(script.as_str()))
+
+        // Errors in the file are reported with its name and line numbers.
+        let script_name = self
+            .script_name
+            .replace(script_file.to_string_lossy().into_owned());
+        let line = self.current_line;
+        let [stdin, stdout, stderr] = stdio;
+        let status = self.run_script_with_stdio(script.as_str(), stdin, stdout, stderr);
+        self.script_name = script_name;
+        self.current_line = line;
+        Ok(status)
     }
 
     /// Parse and run a script
     pub fn run_script(&mut self, script: &str) -> ExitStatus {
         // Inherit shell's stdin/stdout/stderr.
-        let stdin = 0;
-        let stdout = 1;
-        let stderr = 2;
+        let [stdin, stdout, stderr] = self.stdio;
         self.run_script_with_stdio(script, stdin, stdout, stderr)
     }
 
+    /// Returns the file descriptor currently used as `fd` (0, 1, or 2).
+    pub fn stdio(&self, fd: usize) -> RawFd {
+        self.stdio[fd]
+    }
+
+    /// Replaces the file descriptor used as `fd` (0, 1, or 2) by commands. The
+    /// shell's original stdin/stdout/stderr are kept open so that they can be
+    /// restored later.
+    pub fn set_stdio(&mut self, fd: usize, new_fd: RawFd) {
+        let old_fd = self.stdio[fd];
+        if old_fd > 2 && old_fd != new_fd {
+            close(old_fd).ok();
+        }
+
+        self.stdio[fd] = new_fd;
+    }
+
     /// Parse and run a script in the given context
     pub fn run_script_with_stdio(
         &mut self,
@@ -97,18 +315,61 @@ impl Shell {
         stderr: RawFd,
     ) -> ExitStatus {
         match parser::parse(script) {
-            Ok(ast) => eval(self, &ast, stdin, stdout, stderr),
+            Ok(ast) => match eval(self, &ast, stdin, stdout, stderr) {
+                Ok(status) => status,
+                Err(err) => {
+                    writeln!(FdFile::new(stderr), "{}: {}", self.error_prefix(), err).ok();
+                    self.set_last_status(1);
+                    ExitStatus::ExitedWith(1)
+                }
+            },
             Err(parser::ParseError::Empty) => {
                 // Just ignore.
                 ExitStatus::ExitedWith(0)
             }
             Err(parser::ParseError::Fatal(err)) => {
                 debug!("parse error: {}", err);
-                ExitStatus::ExitedWith(-1)
+                writeln!(
+                    FdFile::new(stderr),
+                    "{}: syntax error: {}",
+                    self.error_prefix(),
+                    err
+                )
+                .ok();
+                self.set_last_status(2);
+                ExitStatus::ExitedWith(2)
             }
         }
     }
 
+    /// Runs a script and returns its exit status along with everything it
+    /// wrote to stdout and stderr.
+    ///
+    /// `Shell` is not thread-safe: it changes process-global state such as
+    /// `std::env`, the current directory, and signal dispositions. Don't run
+    /// scripts in more than one thread at a time. Note that this function does
+    /// not return until every process holding the pipes (including background
+    /// jobs) has exited.
+    #[allow(dead_code)] // Part of the embedding API; the binary doesn't use it.
+    pub fn run_capture(&mut self, script: &str) -> (ExitStatus, String, String) {
+        let (stdout_out, stdout_in) = pipe().expect("failed to pipe");
+        let (stderr_out, stderr_in) = pipe().expect("failed to pipe");
+
+        // Read the pipes in background threads so that a command writing a
+        // large amount of output doesn't block on a full pipe.
+        let stdout_reader = spawn_pipe_reader(stdout_out);
+        let stderr_reader = spawn_pipe_reader(stderr_out);
+
+        let stdin = self.stdio[0];
+        let status = self.run_script_with_stdio(script, stdin, stdout_in, stderr_in);
+        close(stdout_in).ok();
+        close(stderr_in).ok();
+
+        let stdout = stdout_reader.join().expect("failed to read stdout");
+        let stderr = stderr_reader.join().expect("failed to read stderr");
+        (status, stdout, stderr)
+    }
+
     pub fn set_last_status(&mut self, status: i32) {
         self.last_status = status;
     }
@@ -131,22 +392,55 @@ impl Shell {
         }
     }
 
+    /// Returns the variable parsed as an integer. `None` if it's not set or
+    /// not a number.
+    pub fn get_int(&self, key: &str) -> Option<i64> {
+        self.get_str(key)
+            .and_then(|value| value.trim().parse::<i64>().ok())
+    }
+
+    /// Sets a string variable. See [`Shell::set`] for the scope.
+    pub fn set_string(&mut self, key: &str, value: impl Into<String>) {
+        self.set(key, Value::String(value.into()), false);
+    }
+
+    /// Sets a variable. If `is_local` is true, it's defined in the current
+    /// scope. Otherwise, like other shells, the innermost local variable
+    /// visible from the current function is updated, or the global one if
+    /// there's no such variable.
     pub fn set(&mut self, key: &str, value: Value, is_local: bool) {
+        let global = !is_local && self.frames.iter().all(|frame| frame.get(key).is_none());
         let frame = if is_local {
             self.current_frame_mut()
-        } else {
+        } else if global {
             &mut self.global
+        } else {
+            self.frames
+                .iter_mut()
+                .rev()
+                .find(|frame| frame.get(key).is_some())
+                .unwrap()
         };
 
-        frame.set(key, value.clone());
+        frame.set(key, value);
 
-        if !is_local && key == "PATH" {
-            if let Value::String(ref path) = value {
-                self.path_table.scan(path);
-            }
+        if key == "PATH" {
+            self.rescan_path();
         }
     }
 
+    /// Rebuilds the command table from `$PATH` visible from the current scope.
+    fn rescan_path(&mut self) {
+        let path = match self.get("PATH").and_then(|var| var.value().clone()) {
+            Some(Value::String(path)) => path,
+            // `PATH=(a b)` is the same as `PATH=a`.
+            Some(Value::Array(elems)) => elems.into_iter().next().unwrap_or_default(),
+            None => String::new(),
+        };
+
+        self.path_table.scan(&path);
+    }
+
     pub fn get_process_state(&self, pid: Pid) -> Option<&ProcessState> {
         self.states.get(&pid)
     }
@@ -167,6 +461,10 @@ impl Shell {
         job
     }
 
+    pub fn jobs(&self) -> &HashMap<JobId, Rc<Job>> {
+        &self.jobs
+    }
+
     pub fn jobs_mut(&mut self) -> &mut HashMap<JobId, Rc<Job>> {
         &mut self.jobs
     }
@@ -183,28 +481,302 @@ impl Shell {
     pub fn set_interactive(&mut self, interactive: bool) {
         self.interactive = interactive;
         self.shell_termios = if interactive {
-            Some(tcgetattr(0 /* stdin */).expect("failed to tcgetattr"))
+            // stdin may not be a terminal (e.g. `ENOTTY` in tests).
+            match tcgetattr(0 /* stdin */) {
+                Ok(termios) => Some(termios),
+                Err(err) => {
+                    debug!("failed to tcgetattr: {}", err);
+                    None
+                }
+            }
         } else {
             None
         };
     }
 
     pub fn pushd(&mut self, path: String) {
+        if self.option("pushd_ignore_dups") && self.cd_stack.last() == Some(&path) {
+            return;
+        }
+
         self.cd_stack.push(path);
+
+        // Drop the oldest entries if the stack is larger than `$DIRSTACKSIZE`.
+        if let Some(size) = self.get_int("DIRSTACKSIZE").filter(|size| *size > 0) {
+            let excess = self.cd_stack.len().saturating_sub(size as usize);
+            self.cd_stack.drain(..excess);
+        }
+    }
+
+    /// Records a visited directory in the `cdh` list. An existing entry is
+    /// moved to the front.
+    pub fn record_cd_history(&mut self, dir: String) {
+        self.cd_history.retain(|visited| *visited != dir);
+        self.cd_history.push_front(dir);
+        self.cd_history.truncate(CD_HISTORY_SIZE);
+    }
+
+    pub fn cd_history(&self) -> &VecDeque<String> {
+        &self.cd_history
+    }
+
+    /// Loads the database of visited directories for `j` from `path`. Visits
+    /// are saved to the file from now on.
+    pub fn load_dir_database(&mut self, path: &Path) {
+        self.dir_database = DirDatabase::load(path);
+    }
+
+    pub fn dir_database(&self) -> &DirDatabase {
+        &self.dir_database
+    }
+
+    pub fn dir_database_mut(&mut self) -> &mut DirDatabase {
+        &mut self.dir_database
     }
 
     pub fn popd(&mut self) -> Option<String> {
         self.cd_stack.pop()
     }
 
-    pub fn get(&self, key: &str) -> Option<Rc<Variable>> {
-        if let Some(var) = self.current_frame().get(key) {
-            Some(var)
+    /// Rotates the directory stack so that the `n`-th entry in the `dirs` order
+    /// (0 is `current_dir`) becomes the top. Returns the new top.
+    pub fn rotate_dir_stack(&mut self, current_dir: String, n: usize) -> Option<String> {
+        let mut dirs: Vec<String> = std::iter::once(current_dir)
+            .chain(self.cd_stack.iter().rev().cloned())
+            .collect();
+        if n >= dirs.len() {
+            return None;
+        }
+
+        dirs.rotate_left(n);
+        let top = dirs.remove(0);
+        self.cd_stack = dirs.into_iter().rev().collect();
+        Some(top)
+    }
+
+    /// Removes the `n`-th entry in the `dirs` order (1 is the most recently
+    /// pushed one) from the directory stack.
+    pub fn remove_dir_stack_entry(&mut self, n: usize) -> Option<String> {
+        if n == 0 || n > self.cd_stack.len() {
+            return None;
+        }
+
+        Some(self.cd_stack.remove(self.cd_stack.len() - n))
+    }
+
+    pub fn clear_dir_stack(&mut self) {
+        self.cd_stack.clear();
+    }
+
+    /// The directory stack. The last one is the most recently pushed one.
+    pub fn dir_stack(&self) -> &[String] {
+        &self.cd_stack
+    }
+
+    pub fn option(&self, name: &str) -> bool {
+        self.options.contains(name)
+    }
+
+    /// Whether commands are only parsed and not run (`set -n`). Like other
+    /// shells, it's ignored in the interactive mode.
+    pub fn noexec(&self) -> bool {
+        !self.interactive && self.option("noexec")
+    }
+
+    pub fn set_option(&mut self, name: &str, enabled: bool) {
+        if enabled {
+            self.options.insert(name.to_owned());
         } else {
-            self.global.get(key)
+            self.options.remove(name);
         }
     }
 
+    /// Returns the variable visible from the current scope. Like other
+    /// shells, local variables of callers are also visible (dynamic scoping).
+    pub fn get(&self, key: &str) -> Option<Rc<Variable>> {
+        self.frames
+            .iter()
+            .rev()
+            .chain(std::iter::once(&self.global))
+            .find_map(|frame| frame.get(key))
+    }
+
+    /// Enters a new local scope for a function call.
+    pub fn enter_frame(&mut self, function: &str, args: Vec<String>) {
+        self.frames.push(Frame::with_args(function, args));
+    }
+
+    /// Returns `true` if a function is being called.
+    pub fn in_function(&self) -> bool {
+        !self.frames.is_empty()
+    }
+
+    /// Returns the names of the functions being called (`$FUNCNAME`). The
+    /// first one is the innermost.
+    pub fn call_stack(&self) -> Vec<String> {
+        self.frames
+            .iter()
+            .rev()
+            .filter_map(|frame| frame.function().map(str::to_owned))
+            .collect()
+    }
+
+    pub fn leave_frame(&mut self) {
+        let frame = self.frames.pop();
+        // Restore the command table if `PATH` was local.
+        if frame.is_some_and(|frame| frame.get("PATH").is_some()) {
+            self.rescan_path();
+        }
+    }
+
+    /// Removes the innermost variable visible from the current scope. Returns
+    /// `false` if it's not defined.
+    pub fn unset(&mut self, name: &str) -> bool {
+        let removed = self
+            .frames
+            .iter_mut()
+            .rev()
+            .chain(std::iter::once(&mut self.global))
+            .any(|frame| frame.remove(name));
+
+        if removed {
+            self.exported.remove(name);
+            if name == "PATH" {
+                self.rescan_path();
+            }
+        }
+
+        removed
+    }
+
+    pub fn define_function(&mut self, name: &str, body: Box<parser::Command>) {
+        self.functions.insert(name.to_owned(), body);
+    }
+
+    /// Removes a function. Returns `false` if it's not defined.
+    pub fn undefine_function(&mut self, name: &str) -> bool {
+        self.functions.remove(name).is_some()
+    }
+
+    pub fn lookup_function(&self, name: &str) -> Option<Box<parser::Command>> {
+        self.functions.get(name).cloned()
+    }
+
+    /// Calls a hook function (e.g. `precmd`) if it is defined and not running.
+    pub fn run_hook(&mut self, name: &str, args: &[String]) -> Option<ExitStatus> {
+        if self.running_hooks.contains(name) {
+            return None;
+        }
+
+        let body = self.lookup_function(name)?;
+        let [stdin, stdout, stderr] = self.stdio;
+        let ctx = Context {
+            stdin,
+            stdout,
+            stderr,
+            pgid: None,
+            background: false,
+            interactive: self.interactive,
+        };
+
+        // Hooks should not clobber `$?` so that e.g. the prompt can show the
+        // status of the last command the user ran.
+        let last_status = self.last_status;
+        self.running_hooks.insert(name.to_owned());
+        let result = call_function(self, name, &body, args.to_vec(), &ctx);
+        self.running_hooks.remove(name);
+        self.last_status = last_status;
+
+        match result {
+            Ok(status) => Some(status),
+            Err(err) => {
+                self.error(format_args!("{}: {}", name, err));
+                Some(ExitStatus::ExitedWith(1))
+            }
+        }
+    }
+
+    /// Sets the command run on `name`. `None` resets it.
+    pub fn set_trap(&mut self, name: &str, command: Option<String>) {
+        match command {
+            Some(command) => self.traps.insert(name.to_owned(), command),
+            None => self.traps.remove(name),
+        };
+    }
+
+    pub fn traps(&self) -> std::collections::hash_map::Iter<'_, String, String> {
+        self.traps.iter()
+    }
+
+    /// Runs the command set by `trap` for `name` if any. Like hooks, it
+    /// doesn't clobber `$?`.
+    pub fn run_trap(
+        &mut self,
+        name: &str,
+        stdin: RawFd,
+        stdout: RawFd,
+        stderr: RawFd,
+    ) -> Option<ExitStatus> {
+        if self.running_traps.contains(name) {
+            return None;
+        }
+
+        let command = self.traps.get(name)?.clone();
+        let last_status = self.last_status;
+        self.running_traps.insert(name.to_owned());
+        let status = self.run_script_with_stdio(&command, stdin, stdout, stderr);
+        self.running_traps.remove(name);
+        self.last_status = last_status;
+        Some(status)
+    }
+
+    #[inline]
+    pub fn in_condition(&self) -> bool {
+        self.condition_depth > 0
+    }
+
+    pub fn enter_condition(&mut self) {
+        self.condition_depth += 1;
+    }
+
+    pub fn leave_condition(&mut self) {
+        self.condition_depth -= 1;
+    }
+
+    pub fn loop_depth(&self) -> usize {
+        self.loop_depth
+    }
+
+    pub fn set_loop_depth(&mut self, depth: usize) {
+        self.loop_depth = depth;
+    }
+
+    pub fn enter_loop(&mut self) {
+        self.loop_depth += 1;
+    }
+
+    pub fn leave_loop(&mut self) {
+        self.loop_depth -= 1;
+    }
+
+    #[inline]
+    pub fn err_trapped(&self) -> bool {
+        self.err_trapped
+    }
+
+    pub fn set_err_trapped(&mut self, err_trapped: bool) {
+        self.err_trapped = err_trapped;
+    }
+
+    /// Runs `$PROMPT_COMMAND` if it's set. Like hooks, it doesn't clobber `$?`.
+    pub fn run_prompt_command(&mut self) -> Option<ExitStatus> {
+        let command = self.get_str("PROMPT_COMMAND").filter(|c| !c.is_empty())?;
+        let last_status = self.last_status;
+        let status = self.run_script(&command);
+        self.last_status = last_status;
+        Some(status)
+    }
+
     #[inline]
     pub fn current_frame(&self) -> &Frame {
         self.frames.last().unwrap_or(&self.global)
@@ -223,11 +795,29 @@ impl Shell {
         self.frames.last_mut().unwrap_or(&mut self.global)
     }
 
-    pub fn exported_names(&self) -> std::collections::hash_set::Iter<String> {
+    pub fn exported_names(&self) -> std::collections::hash_set::Iter<'_, String> {
         self.exported.iter()
     }
 
-    pub fn aliases(&self) -> std::collections::hash_map::Iter<String, String> {
+    pub fn is_exported(&self, name: &str) -> bool {
+        self.exported.contains(name)
+    }
+
+    /// Returns the names of variables visible in the current scope in
+    /// alphabetical order.
+    pub fn variable_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .global
+            .names()
+            .chain(self.frames.iter().flat_map(|frame| frame.names()))
+            .cloned()
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    pub fn aliases(&self) -> std::collections::hash_map::Iter<'_, String, String> {
         self.aliases.iter()
     }
 
@@ -235,15 +825,312 @@ impl Shell {
         self.aliases.insert(name.to_string(), body);
     }
 
+    /// Removes an alias. Returns `false` if it's not defined.
+    pub fn remove_alias(&mut self, name: &str) -> bool {
+        self.aliases.remove(name).is_some()
+    }
+
+    pub fn remove_all_aliases(&mut self) {
+        self.aliases.clear();
+    }
+
     pub fn lookup_alias(&self, alias: &str) -> Option<String> {
-        self.aliases.get(&alias.to_string()).cloned()
+        self.aliases.get(alias).cloned()
     }
 
     pub fn has_lookup_alias(&self, alias: &str) -> bool {
-        self.aliases.contains_key(&alias.to_string())
+        self.aliases.contains_key(alias)
     }
 
     pub fn export(&mut self, name: &str) {
         self.exported.insert(name.to_string());
     }
+
+    /// Increments `$SHLVL` inherited from the parent shell and exports it so
+    /// that nested shells can tell how deep they are.
+    pub fn increment_shlvl(&mut self) {
+        let shlvl = self.get_int("SHLVL").unwrap_or(0);
+        self.set_string("SHLVL", (shlvl + 1).to_string());
+        self.export("SHLVL");
+    }
+}
+
+/// Reads `fd` until EOF in a new thread and closes it.
+fn spawn_pipe_reader(fd: RawFd) -> JoinHandle<String> {
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        unsafe { File::from_raw_fd(fd) }.read_to_end(&mut buf).ok();
+        String::from_utf8_lossy(&buf).into_owned()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_rescan() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let home = temp_path("path-home");
+        let bin = home.join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        let hello = bin.join("smash-hello");
+        std::fs::write(&hello, "#!/bin/sh\necho hello\n").unwrap();
+        std::fs::set_permissions(&hello, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let hello = hello.to_str().unwrap();
+
+        let mut shell = Shell::new_for_test();
+        shell.set_string("HOME", home.to_str().unwrap());
+        shell.run_script("PATH=/nonexistent; PATH=~/bin:$PATH");
+        assert_eq!(
+            shell.get_str("PATH"),
+            Some(format!("{}:/nonexistent", bin.display()))
+        );
+        assert_eq!(shell.path_table().lookup("smash-hello"), Some(hello));
+
+        // `~` after `:` is also expanded.
+        shell.run_script("PATH=/nonexistent:~/bin");
+        assert_eq!(shell.path_table().lookup("smash-hello"), Some(hello));
+
+        shell.run_script("unset PATH");
+        assert_eq!(shell.path_table().lookup("smash-hello"), None);
+
+        shell.run_script("PATH=(~/bin /nonexistent)");
+        assert_eq!(shell.path_table().lookup("smash-hello"), Some(hello));
+
+        // A local `PATH` is used only in the function.
+        let (_, stdout, _) =
+            shell.run_capture("PATH=/nonexistent; f() { local PATH=~/bin; smash-hello; }; f");
+        assert_eq!(stdout, "hello\n");
+        assert_eq!(shell.path_table().lookup("smash-hello"), None);
+
+        std::fs::remove_dir_all(&home).unwrap();
+    }
+
+    #[test]
+    fn run_capture() {
+        let mut shell = Shell::new_for_test();
+        let (status, stdout, stderr) =
+            shell.run_capture("echo out; echo err >&2; sh -c 'echo child; exit 3'");
+        assert_eq!(status, ExitStatus::ExitedWith(3));
+        assert_eq!(stdout, "out\nchild\n");
+        assert_eq!(stderr, "err\n");
+
+        // A large output doesn't block on a full pipe.
+        let (status, stdout, stderr) = shell.run_capture("seq 100000");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout.lines().count(), 100000);
+        assert_eq!(stderr, "");
+
+        // The shell's own stdio is not replaced.
+        assert_eq!(shell.stdio(1), 1);
+        assert_eq!(shell.stdio(2), 2);
+    }
+
+    #[test]
+    fn noexec() {
+        let mut shell = Shell::new_for_test();
+        let (_, stdout, _) = shell.run_capture("set -o");
+        assert_eq!(stdout.lines().count(), SHELL_OPTIONS.len());
+        assert!(stdout.contains("noexec"));
+
+        let (status, stdout, _) = shell.run_capture("echo a; set -n; echo b; x=1");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stdout, "a\n");
+        assert_eq!(shell.get_str("x"), None);
+
+        // Syntax errors are still reported.
+        let (status, _, stderr) = shell.run_capture("echo a; if true; then");
+        assert_eq!(status, ExitStatus::ExitedWith(2));
+        assert!(stderr.starts_with("smash: syntax error: "));
+    }
+
+    #[test]
+    fn error_prefix() {
+        let script = temp_path("error.sh");
+        std::fs::write(&script, "echo ok\npwd -x\n\nsmash-no-such-command\n").unwrap();
+
+        let mut shell = Shell::new_for_test();
+        let (_, stdout, stderr) =
+            shell.run_capture(&format!("source {}; pwd -x", script.display()));
+        assert_eq!(stdout, "ok\n");
+        assert_eq!(
+            stderr,
+            format!(
+                "{0}: line 2: pwd: -x: invalid option\n\
+                 {0}: line 4: smash-no-such-command: command not found\n\
+                 smash: pwd: -x: invalid option\n",
+                script.display()
+            )
+        );
+
+        std::fs::remove_file(&script).unwrap();
+    }
+
+    #[test]
+    fn interactive_without_terminal() {
+        use crossterm::tty::IsTty;
+        if std::io::stdin().is_tty() {
+            return;
+        }
+
+        let mut shell = Shell::new_for_test();
+        shell.set_interactive(true);
+        assert!(shell.shell_termios.is_none());
+        assert_eq!(shell.run_script("true"), ExitStatus::ExitedWith(0));
+    }
+
+    #[test]
+    fn pushd_ignore_dups() {
+        let mut shell = Shell::new_for_test();
+        shell.pushd("/tmp".to_owned());
+        shell.pushd("/tmp".to_owned());
+        assert_eq!(shell.dir_stack(), &["/tmp", "/tmp"]);
+
+        let mut shell = Shell::new_for_test();
+        shell.set_option("pushd_ignore_dups", true);
+        shell.pushd("/tmp".to_owned());
+        shell.pushd("/tmp".to_owned());
+        shell.pushd("/usr".to_owned());
+        shell.pushd("/tmp".to_owned());
+        assert_eq!(shell.dir_stack(), &["/tmp", "/usr", "/tmp"]);
+    }
+
+    #[test]
+    fn rotate_dir_stack() {
+        let mut shell = Shell::new_for_test();
+        for dir in &["/c", "/b", "/a"] {
+            shell.pushd(dir.to_string());
+        }
+
+        // dirs: /cwd /a /b /c
+        assert_eq!(
+            shell.rotate_dir_stack("/cwd".to_owned(), 2),
+            Some("/b".to_owned())
+        );
+        // dirs: /b /c /cwd /a
+        assert_eq!(shell.dir_stack(), &["/a", "/cwd", "/c"]);
+        assert_eq!(shell.rotate_dir_stack("/b".to_owned(), 4), None);
+
+        assert_eq!(shell.remove_dir_stack_entry(2), Some("/cwd".to_owned()));
+        assert_eq!(shell.dir_stack(), &["/a", "/c"]);
+        assert_eq!(shell.remove_dir_stack_entry(0), None);
+        assert_eq!(shell.remove_dir_stack_entry(3), None);
+    }
+
+    #[test]
+    fn cd_history() {
+        let mut shell = Shell::new_for_test();
+        for i in 0..CD_HISTORY_SIZE + 2 {
+            shell.record_cd_history(format!("/{}", i));
+        }
+        shell.record_cd_history("/5".to_owned());
+
+        let history = shell.cd_history();
+        assert_eq!(history.len(), CD_HISTORY_SIZE);
+        assert_eq!(history[0], "/5");
+        assert_eq!(history[1], format!("/{}", CD_HISTORY_SIZE + 1));
+        assert_eq!(history.iter().filter(|dir| *dir == "/5").count(), 1);
+    }
+
+    #[test]
+    fn dir_stack_size() {
+        let mut shell = Shell::new_for_test();
+        shell.set_string("DIRSTACKSIZE", "2");
+        shell.pushd("/a".to_owned());
+        shell.pushd("/b".to_owned());
+        shell.pushd("/c".to_owned());
+        assert_eq!(shell.dir_stack(), &["/b", "/c"]);
+
+        shell.clear_dir_stack();
+        assert!(shell.dir_stack().is_empty());
+    }
+
+    #[test]
+    fn typed_getters() {
+        let mut shell = Shell::new_for_test();
+        shell.set_string("HISTSIZE", " 500 ");
+        shell.set_string("COLUMNS", "80x");
+        shell.set_string("EMPTY", "");
+        assert_eq!(shell.get_int("HISTSIZE"), Some(500));
+        assert_eq!(shell.get_int("COLUMNS"), None);
+        assert_eq!(shell.get_int("EMPTY"), None);
+        assert_eq!(shell.get_int("UNDEFINED"), None);
+    }
+
+    #[test]
+    fn shlvl() {
+        let mut shell = Shell::new_for_test();
+        shell.increment_shlvl();
+        assert_eq!(shell.get_str("SHLVL"), Some("1".to_owned()));
+
+        shell.set_string("SHLVL", "3");
+        shell.increment_shlvl();
+        let (_, stdout, _) = shell.run_capture("printenv SHLVL");
+        assert_eq!(stdout, "4\n");
+    }
+
+    #[test]
+    fn hooks() {
+        let mut shell = Shell::new_for_test();
+        assert_eq!(shell.run_hook("preexec", &["ls -l".to_owned()]), None);
+
+        shell.run_script("preexec() { export LAST_COMMAND=\"$1\"; }");
+        assert_eq!(
+            shell.run_hook("preexec", &["ls -l".to_owned()]),
+            Some(ExitStatus::ExitedWith(0))
+        );
+        assert_eq!(shell.get_str("LAST_COMMAND"), Some("ls -l".to_owned()));
+    }
+
+    #[test]
+    fn hooks_preserve_last_status() {
+        let mut shell = Shell::new_for_test();
+        shell.run_script("precmd() { return 7; }");
+        shell.set_last_status(3);
+        assert_eq!(
+            shell.run_hook("precmd", &[]),
+            Some(ExitStatus::ExitedWith(7))
+        );
+        assert_eq!(shell.last_status(), 3);
+    }
+
+    #[test]
+    fn prompt_command() {
+        let mut shell = Shell::new_for_test();
+        assert_eq!(shell.run_prompt_command(), None);
+
+        shell.run_script("PROMPT_COMMAND='COUNT=x$COUNT'");
+        shell.set_last_status(3);
+        shell.run_prompt_command();
+        shell.run_prompt_command();
+        assert_eq!(shell.get_str("COUNT"), Some("xx".to_owned()));
+        assert_eq!(shell.last_status(), 3);
+    }
+
+    #[test]
+    fn pipeline_with_pipes_as_stdio() {
+        use std::io::{Read, Write};
+        use std::os::unix::io::FromRawFd;
+
+        let mut shell = Shell::new_for_test();
+
+        let (stdin_out, stdin_in) = pipe().unwrap();
+        let (stdout_out, stdout_in) = pipe().unwrap();
+        unsafe { std::fs::File::from_raw_fd(stdin_in) }
+            .write_all(b"b\na\n")
+            .unwrap();
+
+        let status = shell.run_script_with_stdio("cat | sort", stdin_out, stdout_in, 2);
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        close(stdin_out).unwrap();
+        close(stdout_in).unwrap();
+
+        let mut output = String::new();
+        unsafe { std::fs::File::from_raw_fd(stdout_out) }
+            .read_to_string(&mut output)
+            .unwrap();
+        assert_eq!(output, "a\nb\n");
+    }
 }
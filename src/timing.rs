@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+/// The format used by the `time` keyword if `$TIMEFORMAT` is not set.
+pub const DEFAULT_TIMEFORMAT: &str = "\nreal\t%3lR\nuser\t%3lU\nsys\t%3lS";
+
+/// CPU times consumed by the shell and its children.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct CpuTimes {
+    pub user: Duration,
+    pub sys: Duration,
+}
+
+impl CpuTimes {
+    pub fn now() -> CpuTimes {
+        let mut times = CpuTimes::default();
+        for who in [libc::RUSAGE_SELF, libc::RUSAGE_CHILDREN] {
+            let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+            if unsafe { libc::getrusage(who, &mut usage) } == 0 {
+                times.user += timeval_to_duration(usage.ru_utime);
+                times.sys += timeval_to_duration(usage.ru_stime);
+            }
+        }
+
+        times
+    }
+
+    pub fn elapsed_since(&self, earlier: &CpuTimes) -> CpuTimes {
+        CpuTimes {
+            user: self.user.saturating_sub(earlier.user),
+            sys: self.sys.saturating_sub(earlier.sys),
+        }
+    }
+}
+
+fn timeval_to_duration(tv: libc::timeval) -> Duration {
+    Duration::new(tv.tv_sec as u64, tv.tv_usec as u32 * 1000)
+}
+
+/// Formats the result of `time` like bash's `$TIMEFORMAT`: `%[p][l]R`,
+/// `%[p][l]U`, and `%[p][l]S` are the real, user, and system time where `p`
+/// is the precision (0 to 3, defaults to 3) and `l` selects the `MMmSS.FFFs`
+/// form. `%P` is the CPU percentage and `%%` is a literal `%`.
+pub fn format_times(format: &str, real: Duration, cpu: &CpuTimes) -> String {
+    let mut output = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch != '%' {
+            output.push(ch);
+            continue;
+        }
+
+        let mut precision = 3;
+        if let Some(digit) = chars.peek().and_then(|ch| ch.to_digit(10)) {
+            precision = digit.min(3) as usize;
+            chars.next();
+        }
+
+        let long = chars.peek() == Some(&'l');
+        if long {
+            chars.next();
+        }
+
+        let duration = match chars.next() {
+            Some('R') => real,
+            Some('U') => cpu.user,
+            Some('S') => cpu.sys,
+            Some('P') => {
+                let percentage = if real.is_zero() {
+                    0.0
+                } else {
+                    (cpu.user + cpu.sys).as_secs_f64() / real.as_secs_f64() * 100.0
+                };
+                output.push_str(&format!("{:.2}", percentage));
+                continue;
+            }
+            Some('%') => {
+                output.push('%');
+                continue;
+            }
+            // Unknown directives are printed as is.
+            Some(ch) => {
+                output.push('%');
+                output.push(ch);
+                continue;
+            }
+            None => {
+                output.push('%');
+                break;
+            }
+        };
+
+        output.push_str(&format_duration(duration, precision, long));
+    }
+
+    output
+}
+
+fn format_duration(duration: Duration, precision: usize, long: bool) -> String {
+    // Truncate instead of rounding like bash.
+    let scale = 10u128.pow(precision as u32);
+    let fraction = duration.subsec_millis() as u128 * scale / 1000;
+    let (minutes, seconds) = if long {
+        (Some(duration.as_secs() / 60), duration.as_secs() % 60)
+    } else {
+        (None, duration.as_secs())
+    };
+
+    let mut s = String::new();
+    if let Some(minutes) = minutes {
+        s.push_str(&format!("{}m", minutes));
+    }
+
+    s.push_str(&seconds.to_string());
+    if precision > 0 {
+        s.push_str(&format!(".{:0width$}", fraction, width = precision));
+    }
+
+    if long {
+        s.push('s');
+    }
+
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::Shell;
+    use crate::variable::Value;
+    use crate::ExitStatus;
+
+    #[test]
+    fn timeformat() {
+        let real = Duration::from_millis(62_345);
+        let cpu = CpuTimes {
+            user: Duration::from_millis(1_500),
+            sys: Duration::from_millis(250),
+        };
+
+        assert_eq!(
+            format_times(DEFAULT_TIMEFORMAT, real, &cpu),
+            "\nreal\t1m2.345s\nuser\t0m1.500s\nsys\t0m0.250s"
+        );
+        assert_eq!(format_times("%R %1U %0S", real, &cpu), "62.345 1.5 0");
+        assert_eq!(format_times("%2lR", real, &cpu), "1m2.34s");
+        assert_eq!(format_times("%P%% %X", real, &cpu), "2.81% %X");
+    }
+
+    #[test]
+    fn time_keyword() {
+        let mut shell = Shell::new_for_test();
+        shell.set(
+            "TIMEFORMAT",
+            Value::String("took %0R seconds".to_owned()),
+            false,
+        );
+        let (status, _, stderr) = shell.run_capture("time set -o pushd_ignore_dups");
+        assert_eq!(status, ExitStatus::ExitedWith(0));
+        assert_eq!(stderr, "took 0 seconds\n");
+        assert!(shell.option("pushd_ignore_dups"));
+
+        shell.set("TIMEFORMAT", Value::String(String::new()), false);
+        let (_, _, stderr) = shell.run_capture("time set -o");
+        assert_eq!(stderr, "");
+    }
+}
@@ -5,7 +5,14 @@ use std::rc::Rc;
 pub enum Value {
     String(String),
     Array(Vec<String>),
-    // TODO: support function
+}
+
+/// Returns `true` if `name` can be used as a variable name: a letter or `_`
+/// followed by letters, digits, or `_`.
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c == '_' || c.is_ascii_alphabetic())
+        && chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
 }
 
 #[derive(Debug)]
@@ -27,7 +34,7 @@ impl Variable {
     pub fn as_str(&self) -> &str {
         match &self.value {
             Some(Value::String(value)) => value,
-            Some(Value::Array(elems)) => match elems.get(0) {
+            Some(Value::Array(elems)) => match elems.first() {
                 Some(elem) => elem.as_str(),
                 _ => "",
             },
@@ -39,21 +46,65 @@ impl Variable {
 pub struct Frame {
     /// key: variable name, value: varible map.
     vars: HashMap<String, Rc<Variable>>,
+    /// Positional parameters (`$1`, `$2`, ...).
+    args: Vec<String>,
+    /// The name of the function being called. `None` in the global scope.
+    function: Option<String>,
 }
 
 impl Frame {
     pub fn new() -> Frame {
         Frame {
             vars: HashMap::new(),
+            args: Vec::new(),
+            function: None,
+        }
+    }
+
+    pub fn with_args(function: &str, args: Vec<String>) -> Frame {
+        Frame {
+            vars: HashMap::new(),
+            args,
+            function: Some(function.to_owned()),
         }
     }
 
+    pub fn function(&self) -> Option<&str> {
+        self.function.as_deref()
+    }
+
+    /// Returns `$n`. `n` starts from 1.
+    pub fn get_nth_arg(&self, n: usize) -> Option<&str> {
+        n.checked_sub(1)
+            .and_then(|index| self.args.get(index))
+            .map(String::as_str)
+    }
+
+    /// Returns the positional parameters (`$@`).
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// Replaces the positional parameters and returns the old ones.
+    pub fn replace_args(&mut self, args: Vec<String>) -> Vec<String> {
+        std::mem::replace(&mut self.args, args)
+    }
+
     pub fn get(&self, key: &str) -> Option<Rc<Variable>> {
         self.vars.get(key).cloned()
     }
 
+    pub fn names(&self) -> std::collections::hash_map::Keys<'_, String, Rc<Variable>> {
+        self.vars.keys()
+    }
+
     pub fn set(&mut self, key: &str, value: Value) {
         self.vars
             .insert(key.into(), Rc::new(Variable::new(Some(value))));
     }
+
+    /// Removes a variable. Returns `false` if it's not defined in the frame.
+    pub fn remove(&mut self, key: &str) -> bool {
+        self.vars.remove(key).is_some()
+    }
 }